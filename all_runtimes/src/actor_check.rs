@@ -0,0 +1,566 @@
+use std::fmt;
+use std::future::Future;
+use std::ops::Range;
+use std::sync::{Arc, Once};
+use std::time::Duration;
+
+use bastion::prelude::*;
+
+use crate::adapter::{chunk_ranges, process_range, run_category, Datasets, RuntimeAdapter};
+use crate::async_check::tokio_runtime;
+use crate::cli::Selection;
+use crate::collect::{Collection, Results, Returned};
+use crate::report::Progress;
+use crate::results::{BenchmarkResult, Category, WorkCost, Workload};
+use crate::timing::{self, Spawned};
+
+const CATEGORY: Category = Category::Actor;
+
+pub fn adapters() -> Vec<Box<dyn RuntimeAdapter>> {
+    vec![
+        Box::new(ActixActorAdapter::new(Pattern::Pool)),
+        Box::new(ActixActorAdapter::new(Pattern::PingPong)),
+        Box::new(BastionAdapter::new(Pattern::Pool)),
+        Box::new(BastionAdapter::new(Pattern::PingPong)),
+        Box::new(RactorAdapter::new(Pattern::Pool)),
+        Box::new(RactorAdapter::new(Pattern::PingPong)),
+        Box::new(MpscActorAdapter::new(Pattern::Pool)),
+        Box::new(MpscActorAdapter::new(Pattern::PingPong)),
+    ]
+}
+
+// How the chunks reach the actors
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Pattern {
+    // A pool of worker actors, sent one message per chunk round-robin, every reply awaited together
+    Pool,
+    // A single actor, sent one chunk at a time and answering before the next is sent
+    PingPong,
+}
+
+// One chunk of work, as every actor receives it
+pub struct Chunk {
+    data: Arc<Vec<u32>>,
+    range: Range<usize>,
+    cost: WorkCost,
+    results: Arc<Results>,
+    spawned: Spawned,
+}
+
+impl Chunk {
+    // Run by the actor that received it; the reply carries what goes to `Results::returned`
+    fn process(self) -> Returned {
+        let _task = self.spawned.start();
+        process_range(&self.data, None, self.range, self.cost, &self.results)
+    }
+}
+
+// Bastion only passes messages that can be debug-printed
+impl fmt::Debug for Chunk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Chunk").field("range", &self.range).finish()
+    }
+}
+
+// Send every chunk of `data` to the actors through `ask`, which takes the index of the
+// actor to send to and resolves to its reply, and return the collected values. If a
+// reply is lost the output comes back empty, which fails verification.
+async fn exchange<A, F>(pattern: Pattern, actors: usize, data: &Arc<Vec<u32>>, workload: &Workload, ask: A) -> Vec<u32>
+where
+    A: Fn(usize, Chunk) -> F,
+    F: Future<Output = Result<Returned, String>>,
+{
+    if actors == 0 {
+        eprintln!("No actors to send chunks to");
+        return Vec::new();
+    }
+    let results = Arc::new(Results::new(data.len(), workload.collection));
+    let chunks = chunk_ranges(data.len(), workload.chunk_size).map(|range| Chunk {
+        data: data.clone(),
+        range,
+        cost: workload.cost,
+        results: results.clone(),
+        spawned: timing::spawned(),
+    });
+
+    let answered: Result<(), String> = async {
+        match pattern {
+            Pattern::Pool => {
+                let replies: Vec<F> = chunks.enumerate().map(|(i, chunk)| ask(i % actors, chunk)).collect();
+                for returned in futures::future::join_all(replies).await {
+                    results.returned(returned?);
+                }
+            }
+            Pattern::PingPong => {
+                for chunk in chunks {
+                    results.returned(ask(0, chunk).await?);
+                }
+            }
+        }
+        Ok(())
+    }
+    .await;
+
+    match answered {
+        Ok(()) => results.take(),
+        Err(err) => {
+            eprintln!("{}", err);
+            Vec::new()
+        }
+    }
+}
+
+impl actix::Message for Chunk {
+    type Result = Returned;
+}
+
+// Pool workers each get a thread of their own from a `SyncArbiter`
+struct ActixSyncWorker;
+
+impl actix::Actor for ActixSyncWorker {
+    type Context = actix::SyncContext<Self>;
+}
+
+impl actix::Handler<Chunk> for ActixSyncWorker {
+    type Result = actix::MessageResult<Chunk>;
+
+    fn handle(&mut self, chunk: Chunk, _ctx: &mut Self::Context) -> Self::Result {
+        actix::MessageResult(chunk.process())
+    }
+}
+
+// The ping-pong actor runs on the system's own thread, like any plain actix actor
+struct ActixWorker;
+
+impl actix::Actor for ActixWorker {
+    type Context = actix::Context<Self>;
+}
+
+impl actix::Handler<Chunk> for ActixWorker {
+    type Result = actix::MessageResult<Chunk>;
+
+    fn handle(&mut self, chunk: Chunk, _ctx: &mut Self::Context) -> Self::Result {
+        actix::MessageResult(chunk.process())
+    }
+}
+
+// Actix actors on an actix system: a `SyncArbiter` pool, or one async actor
+pub struct ActixActorAdapter {
+    pattern: Pattern,
+    workers: Option<usize>,
+    system: Option<actix_rt::SystemRunner>,
+    // Either kind of worker, seen as something chunks can be sent to
+    recipient: Option<actix::Recipient<Chunk>>,
+}
+
+impl ActixActorAdapter {
+    fn new(pattern: Pattern) -> Self {
+        ActixActorAdapter { pattern, workers: None, system: None, recipient: None }
+    }
+}
+
+impl RuntimeAdapter for ActixActorAdapter {
+    fn name(&self) -> &'static str {
+        match self.pattern {
+            Pattern::Pool => "Actix Actor Pool",
+            Pattern::PingPong => "Actix Ping-Pong",
+        }
+    }
+
+    fn category(&self) -> Category {
+        CATEGORY
+    }
+
+    fn threads(&self) -> usize {
+        match self.pattern {
+            Pattern::Pool => self.workers.unwrap_or_else(num_cpus::get),
+            Pattern::PingPong => 1,
+        }
+    }
+
+    // Sizes the pool; a ping-pong run always has the one actor
+    fn set_workers(&mut self, workers: usize) -> bool {
+        if self.pattern == Pattern::PingPong {
+            return false;
+        }
+        self.workers = Some(workers);
+        true
+    }
+
+    fn build(&mut self) {
+        let system = actix_rt::System::new();
+        let workers = self.threads();
+        let pattern = self.pattern;
+        self.recipient = Some(system.block_on(async move {
+            match pattern {
+                Pattern::Pool => actix::SyncArbiter::start(workers, || ActixSyncWorker).recipient(),
+                Pattern::PingPong => actix::Actor::start(ActixWorker).recipient(),
+            }
+        }));
+        self.system = Some(system);
+    }
+
+    // Every reply goes back to the sender, which hands it to `Results::returned`
+    fn supports_collection(&self, _collection: Collection) -> bool {
+        true
+    }
+
+    fn run(&mut self, data: Arc<Vec<u32>>, delays: Option<Arc<[Duration]>>, workload: &Workload) -> Option<Vec<u32>> {
+        if delays.is_some() {
+            return None;
+        }
+        let system = self.system.as_ref().expect("Actix system not built");
+        let recipient = self.recipient.as_ref().expect("Actix actors not started");
+        // The SyncArbiter hands each message to whichever of its threads is free
+        let (pattern, workers) = (self.pattern, self.threads());
+        Some(system.block_on(exchange(pattern, workers, &data, workload, |_, chunk| {
+            let reply = recipient.send(chunk);
+            async move { reply.await.map_err(|err| format!("An Actix actor didn't answer: {}", err)) }
+        })))
+    }
+
+    fn teardown(&mut self) {
+        // The pool's threads stop once their address is gone
+        self.recipient = None;
+        self.system = None;
+    }
+}
+
+// Bastion's system is global and can only be started once per process
+static BASTION_START: Once = Once::new();
+
+// Bastion answers with any message; this is the one its children reply with
+struct BastionReply(Returned);
+
+impl fmt::Debug for BastionReply {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("BastionReply")
+    }
+}
+
+// A Bastion child: answer every chunk it is asked to process, forever
+async fn bastion_worker(ctx: BastionContext) -> Result<(), ()> {
+    loop {
+        msg! { ctx.recv().await?,
+            chunk: Chunk =!> {
+                // If the answer can't be delivered, the asker sees it as lost
+                let _ = answer!(ctx, BastionReply(chunk.process()));
+            };
+            _: _ => ();
+        }
+    }
+}
+
+// Bastion children under a one-for-one supervisor, on Bastion's own executor
+pub struct BastionAdapter {
+    pattern: Pattern,
+    // Children started under the supervisor
+    redundancy: usize,
+    supervisor: Option<SupervisorRef>,
+    children: Option<ChildrenRef>,
+}
+
+impl BastionAdapter {
+    fn new(pattern: Pattern) -> Self {
+        let redundancy = match pattern {
+            Pattern::Pool => num_cpus::get(),
+            Pattern::PingPong => 1,
+        };
+        BastionAdapter { pattern, redundancy, supervisor: None, children: None }
+    }
+
+    // Stop the supervisor, and with it its children
+    fn stop(&mut self) {
+        if let Some(supervisor) = self.supervisor.take() {
+            let _ = supervisor.stop();
+        }
+        self.children = None;
+    }
+}
+
+impl RuntimeAdapter for BastionAdapter {
+    fn name(&self) -> &'static str {
+        match self.pattern {
+            Pattern::Pool => "Bastion Pool",
+            Pattern::PingPong => "Bastion Ping-Pong",
+        }
+    }
+
+    fn category(&self) -> Category {
+        CATEGORY
+    }
+
+    // The children the chunks are spread over. Bastion's executor itself always has
+    // one thread per CPU.
+    fn threads(&self) -> usize {
+        self.redundancy
+    }
+
+    // Sizes the pool; a ping-pong run always has the one child
+    fn set_workers(&mut self, workers: usize) -> bool {
+        if self.pattern == Pattern::PingPong {
+            return false;
+        }
+        self.redundancy = workers;
+        true
+    }
+
+    fn build(&mut self) {
+        BASTION_START.call_once(|| {
+            Bastion::init();
+            Bastion::start();
+        });
+        // The system outlives every build, so a supervisor that was never torn down
+        // would keep its children running next to the new ones
+        self.stop();
+        let redundancy = self.redundancy;
+        let supervisor = Bastion::supervisor(|sp| sp.with_strategy(SupervisionStrategy::OneForOne))
+            .expect("couldn't start a Bastion supervisor");
+        let children = supervisor
+            .children(|children| children.with_redundancy(redundancy).with_exec(bastion_worker))
+            .expect("couldn't start Bastion children");
+        self.supervisor = Some(supervisor);
+        self.children = Some(children);
+    }
+
+    fn supports_collection(&self, _collection: Collection) -> bool {
+        true
+    }
+
+    fn run(&mut self, data: Arc<Vec<u32>>, delays: Option<Arc<[Duration]>>, workload: &Workload) -> Option<Vec<u32>> {
+        if delays.is_some() {
+            return None;
+        }
+        let children = self.children.as_ref().expect("Bastion children not started");
+        let children = children.elems();
+        Some(futures::executor::block_on(exchange(self.pattern, children.len(), &data, workload, |i, chunk| {
+            let answer = children[i].ask_anonymously(chunk);
+            async move {
+                let answer = answer.map_err(|_| "Couldn't ask a Bastion child".to_string())?;
+                msg! { answer.await.map_err(|_| "A Bastion child didn't answer".to_string())?,
+                    reply: BastionReply => Ok(reply.0);
+                    _: _ => Err("A Bastion child answered with something other than a chunk".to_string());
+                }
+            }
+        })))
+    }
+
+    fn teardown(&mut self) {
+        self.stop();
+    }
+}
+
+// A chunk and where to send what processing it returned
+type RactorMsg = (Chunk, ractor::RpcReplyPort<Returned>);
+
+// A ractor actor answering every chunk through the reply port it came with
+struct RactorWorker;
+
+impl ractor::Actor for RactorWorker {
+    type Msg = RactorMsg;
+    type State = ();
+    type Arguments = ();
+
+    async fn pre_start(&self, _myself: ractor::ActorRef<Self::Msg>, _args: ()) -> Result<(), ractor::ActorProcessingErr> {
+        Ok(())
+    }
+
+    async fn handle(&self, _myself: ractor::ActorRef<Self::Msg>, (chunk, reply): Self::Msg,
+                    _state: &mut ()) -> Result<(), ractor::ActorProcessingErr> {
+        reply.send(chunk.process())?;
+        Ok(())
+    }
+}
+
+// ractor actors, each a task on a Tokio runtime
+pub struct RactorAdapter {
+    pattern: Pattern,
+    workers: Option<usize>,
+    runtime: Option<tokio::runtime::Runtime>,
+    actors: Vec<(ractor::ActorRef<RactorMsg>, tokio::task::JoinHandle<()>)>,
+}
+
+impl RactorAdapter {
+    fn new(pattern: Pattern) -> Self {
+        RactorAdapter { pattern, workers: None, runtime: None, actors: Vec::new() }
+    }
+}
+
+impl RuntimeAdapter for RactorAdapter {
+    fn name(&self) -> &'static str {
+        match self.pattern {
+            Pattern::Pool => "ractor Pool",
+            Pattern::PingPong => "ractor Ping-Pong",
+        }
+    }
+
+    fn category(&self) -> Category {
+        CATEGORY
+    }
+
+    fn threads(&self) -> usize {
+        match self.pattern {
+            Pattern::Pool => self.workers.unwrap_or_else(num_cpus::get),
+            Pattern::PingPong => 1,
+        }
+    }
+
+    // Sizes both the Tokio runtime and the pool; a ping-pong run always has the one actor
+    fn set_workers(&mut self, workers: usize) -> bool {
+        if self.pattern == Pattern::PingPong {
+            return false;
+        }
+        self.workers = Some(workers);
+        true
+    }
+
+    fn build(&mut self) {
+        let runtime = tokio_runtime(self.workers);
+        let actors = self.threads();
+        self.actors = runtime.block_on(async {
+            let mut spawned = Vec::new();
+            for _ in 0..actors {
+                spawned.push(ractor::Actor::spawn(None, RactorWorker, ()).await.expect("couldn't spawn a ractor actor"));
+            }
+            spawned
+        });
+        self.runtime = Some(runtime);
+    }
+
+    fn supports_collection(&self, _collection: Collection) -> bool {
+        true
+    }
+
+    fn run(&mut self, data: Arc<Vec<u32>>, delays: Option<Arc<[Duration]>>, workload: &Workload) -> Option<Vec<u32>> {
+        if delays.is_some() {
+            return None;
+        }
+        let runtime = self.runtime.as_ref().expect("Tokio runtime not built");
+        let actors = &self.actors;
+        Some(runtime.block_on(exchange(self.pattern, actors.len(), &data, workload, |i, chunk| {
+            let actor = actors[i].0.clone();
+            async move {
+                match actor.call(|reply| (chunk, reply), None).await {
+                    Ok(ractor::rpc::CallResult::Success(returned)) => Ok(returned),
+                    Ok(_) => Err("A ractor actor didn't answer".to_string()),
+                    Err(err) => Err(format!("Couldn't reach a ractor actor: {}", err)),
+                }
+            }
+        })))
+    }
+
+    fn teardown(&mut self) {
+        if let Some(runtime) = self.runtime.take() {
+            runtime.block_on(async {
+                for (actor, handle) in self.actors.drain(..) {
+                    actor.stop(None);
+                    let _ = handle.await;
+                }
+            });
+        }
+    }
+}
+
+// Messages waiting in a hand-rolled actor's mailbox before senders have to wait
+const MAILBOX: usize = 64;
+
+type Mailbox = tokio::sync::mpsc::Sender<(Chunk, tokio::sync::oneshot::Sender<Returned>)>;
+
+// A hand-rolled actor: a Tokio task owning the receiving end of its mailbox,
+// answering each chunk over the oneshot channel it came with. It stops when
+// every sender is gone.
+fn spawn_mpsc_actor(runtime: &tokio::runtime::Runtime) -> Mailbox {
+    let (mailbox, mut inbox): (Mailbox, _) = tokio::sync::mpsc::channel(MAILBOX);
+    runtime.spawn(async move {
+        while let Some((chunk, reply)) = inbox.recv().await {
+            let _ = reply.send(chunk.process());
+        }
+    });
+    mailbox
+}
+
+// Actors built from nothing but Tokio channels, as a baseline for the frameworks
+pub struct MpscActorAdapter {
+    pattern: Pattern,
+    workers: Option<usize>,
+    runtime: Option<tokio::runtime::Runtime>,
+    mailboxes: Vec<Mailbox>,
+}
+
+impl MpscActorAdapter {
+    fn new(pattern: Pattern) -> Self {
+        MpscActorAdapter { pattern, workers: None, runtime: None, mailboxes: Vec::new() }
+    }
+}
+
+impl RuntimeAdapter for MpscActorAdapter {
+    fn name(&self) -> &'static str {
+        match self.pattern {
+            Pattern::Pool => "mpsc Actor Pool",
+            Pattern::PingPong => "mpsc Ping-Pong",
+        }
+    }
+
+    fn category(&self) -> Category {
+        CATEGORY
+    }
+
+    fn threads(&self) -> usize {
+        match self.pattern {
+            Pattern::Pool => self.workers.unwrap_or_else(num_cpus::get),
+            Pattern::PingPong => 1,
+        }
+    }
+
+    // Sizes both the Tokio runtime and the pool; a ping-pong run always has the one actor
+    fn set_workers(&mut self, workers: usize) -> bool {
+        if self.pattern == Pattern::PingPong {
+            return false;
+        }
+        self.workers = Some(workers);
+        true
+    }
+
+    fn build(&mut self) {
+        let runtime = tokio_runtime(self.workers);
+        self.mailboxes = (0..self.threads()).map(|_| spawn_mpsc_actor(&runtime)).collect();
+        self.runtime = Some(runtime);
+    }
+
+    fn supports_collection(&self, _collection: Collection) -> bool {
+        true
+    }
+
+    fn run(&mut self, data: Arc<Vec<u32>>, delays: Option<Arc<[Duration]>>, workload: &Workload) -> Option<Vec<u32>> {
+        if delays.is_some() {
+            return None;
+        }
+        let runtime = self.runtime.as_ref().expect("Tokio runtime not built");
+        let mailboxes = &self.mailboxes;
+        Some(runtime.block_on(exchange(self.pattern, mailboxes.len(), &data, workload, |i, chunk| {
+            let mailbox = mailboxes[i].clone();
+            async move {
+                let (reply, answer) = tokio::sync::oneshot::channel();
+                mailbox.send((chunk, reply)).await.map_err(|_| "An mpsc actor stopped".to_string())?;
+                answer.await.map_err(|_| "An mpsc actor didn't answer".to_string())
+            }
+        })))
+    }
+
+    fn teardown(&mut self) {
+        // Dropping the mailboxes stops the actors
+        self.mailboxes.clear();
+        self.runtime = None;
+    }
+}
+
+// Main function to benchmark the actor frameworks
+pub fn benchmark_actor_libraries(workload: &Workload, datasets: &Datasets, selection: &Selection,
+                                 progress: Progress) -> Vec<BenchmarkResult> {
+    progress.line("Starting actor framework benchmarks...");
+
+    let results = run_category(CATEGORY, workload, datasets, selection);
+
+    progress.line("Actor framework benchmarks completed.");
+    results
+}
@@ -0,0 +1,372 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::future::Future;
+use std::io;
+use std::net::TcpListener;
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::actor_check;
+use crate::async_check::{self, process_value};
+use crate::cli::Selection;
+use crate::collect::{Collection, Results, Returned};
+use crate::hybrid_check;
+use crate::parallel_check;
+use crate::sequential_check;
+use crate::results::{
+    BenchmarkResult, Category, Latency, PhaseTimes, Verification, VerifyMode, Warmup, WorkCost, Workload,
+};
+use crate::stats;
+use crate::timing::{self, TaskTimes};
+use crate::trace;
+
+// A library under test. The harness owns data generation, timing and bookkeeping,
+// so an adapter only has to describe how its library builds a runtime, spawns the
+// work for every item and joins it all.
+pub trait RuntimeAdapter {
+    fn name(&self) -> &'static str;
+    fn category(&self) -> Category;
+
+    // Worker threads the work is spread over, for parallel efficiency
+    fn threads(&self) -> usize {
+        num_cpus::get()
+    }
+
+    // Use this many worker threads instead of the library default, through the
+    // library's own builder. False if it has no such setting.
+    fn set_workers(&mut self, _workers: usize) -> bool {
+        false
+    }
+
+    // Environment variable a global executor sizes itself from when it first
+    // starts. Such a library can only be resized in a fresh process.
+    fn workers_env_var(&self) -> Option<&'static str> {
+        None
+    }
+
+    // Create the runtime/executor/pool used by `run` (timed as the startup phase)
+    fn build(&mut self) {}
+
+    // False for libraries whose work split is fixed by their own model and
+    // ignores `chunk_size`
+    fn honours_chunk_size(&self) -> bool {
+        true
+    }
+
+    // Spawn one unit of work per `workload.chunk_size` items, wait for all of it and
+    // return the processed values in input order (timed as the workload phase).
+    // Values are collected in a `Results`, with the strategy the workload selects.
+    // With `delays`, every value first waits out `delays[idx]`: on the runtime's
+    // timer for async libraries, by blocking the worker thread for thread-based ones.
+    // None if there are delays and the library has no simulated I/O variant.
+    fn run(&mut self, data: Arc<Vec<u32>>, delays: Option<Arc<[Duration]>>, workload: &Workload) -> Option<Vec<u32>>;
+
+    // Whether the library can run with `collection`. Everything `Results` does on its
+    // own works anywhere; handing chunks back through join handles needs the
+    // adapter to pass whatever its tasks return to `Results::returned`.
+    fn supports_collection(&self, collection: Collection) -> bool {
+        collection != Collection::JoinHandles
+    }
+
+    // Hands single jobs to the runtime made by `build` without waiting for them,
+    // for the open-loop load generator. None if the library can't take work from
+    // outside a `run`.
+    fn submitter(&self) -> Option<Submitter<'_>> {
+        None
+    }
+
+    // An echo server on the runtime made by `build`, for the TCP echo benchmark.
+    // None if the library has no echo server.
+    fn echo_server(&self) -> Option<EchoServer<'_>> {
+        None
+    }
+
+    // Drop whatever `build` created (timed as the shutdown phase)
+    fn teardown(&mut self) {}
+}
+
+// A unit of work submitted on its own, and something that submits them
+pub type Job = Box<dyn FnOnce() + Send + 'static>;
+pub type Submitter<'a> = Box<dyn Fn(Job) + 'a>;
+
+// Echoes back whatever arrives on connections accepted from the listener (already
+// non-blocking) until the receiver gets a message or disconnects. Blocks the
+// calling thread, and returns how many connections were lost while being accepted.
+pub type EchoServer<'a> = Box<dyn FnOnce(TcpListener, flume::Receiver<()>) -> io::Result<u64> + 'a>;
+
+// Every library known to the harness, in the order they are reported
+pub fn registry() -> Vec<Box<dyn RuntimeAdapter>> {
+    let mut adapters = sequential_check::adapters();
+    adapters.extend(async_check::adapters());
+    adapters.extend(parallel_check::adapters());
+    adapters.extend(hybrid_check::adapters());
+    adapters.extend(actor_check::adapters());
+    adapters
+}
+
+// Index ranges of consecutive `chunk_size`-item chunks covering `0..len`
+pub fn chunk_ranges(len: usize, chunk_size: usize) -> impl Iterator<Item = Range<usize>> {
+    let chunk_size = chunk_size.max(1);
+    (0..len).step_by(chunk_size).map(move |start| start..(start + chunk_size).min(len))
+}
+
+// Process one chunk, storing every value as soon as it's done. With `delays`, the
+// calling thread first blocks for each value's delay. Return what this gives back
+// from the task, so it reaches `Results::returned` through the join handle.
+pub fn process_range(data: &[u32], delays: Option<&[Duration]>, range: Range<usize>, cost: WorkCost,
+                     results: &Results) -> Returned {
+    let mut trace = trace::task(range.start);
+    let mut sink = results.task(range.clone());
+    for idx in range {
+        if let Some(delays) = delays {
+            std::thread::sleep(delays[idx]);
+        }
+        let processed = trace.item(idx, || process_value(data[idx], cost));
+        sink.put(idx, processed);
+    }
+    sink.finish()
+}
+
+// The same inside an async task, awaiting each delay on the runtime's own timer (`sleep`).
+pub async fn process_range_async<S, F>(data: &[u32], delays: Option<&[Duration]>, range: Range<usize>,
+                                       cost: WorkCost, results: &Results, sleep: S) -> Returned
+where
+    S: Fn(Duration) -> F,
+    F: Future,
+{
+    let mut trace = trace::task(range.start);
+    let mut sink = results.task(range.clone());
+    for idx in range {
+        if let Some(delays) = delays {
+            sleep(delays[idx]).await;
+        }
+        let processed = trace.item(idx, || process_value(data[idx], cost));
+        sink.put(idx, processed);
+    }
+    sink.finish()
+}
+
+// Hand back the values collected behind a shared lock once every task has finished
+pub fn take_results(results: &Mutex<Vec<u32>>) -> Vec<u32> {
+    std::mem::take(&mut *results.lock().unwrap())
+}
+
+// The selected adapters belonging to one category
+pub fn adapters_in(category: Category, selection: &Selection) -> Vec<Box<dyn RuntimeAdapter>> {
+    registry()
+        .into_iter()
+        .filter(|adapter| adapter.category() == category && selection.includes(adapter.as_ref()))
+        .collect()
+}
+
+// Input for one run: a deterministic dataset per timed iteration, generated once
+// from the run's seed and handed to every library, so all of them process
+// exactly the same values and the run can be replayed with the same seed.
+// Each dataset comes with the output of processing it sequentially, which
+// every library's output is checked against.
+pub struct Datasets {
+    iterations: Vec<Arc<Vec<u32>>>,
+    expected: Vec<Vec<u32>>,
+    checksums: Vec<u64>,
+    // Per-value waits for simulated I/O, one set per dataset; empty for CPU-bound runs
+    delays: Vec<Arc<[Duration]>>,
+}
+
+impl Datasets {
+    pub fn generate(seed: u64, data_size: usize, iterations: usize, cost: WorkCost) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let iterations: Vec<Arc<Vec<u32>>> = (0..iterations.max(1))
+            .map(|_| Arc::new((0..data_size).map(|_| rng.random_range(0..10000)).collect()))
+            .collect();
+        let expected: Vec<Vec<u32>> = iterations
+            .iter()
+            .map(|data| data.iter().map(|&value| process_value(value, cost)).collect())
+            .collect();
+        let checksums = expected.iter().map(|values| checksum(values)).collect();
+        Datasets { iterations, expected, checksums, delays: Vec::new() }
+    }
+
+    // Draw a wait for every value from `latency`. The delays come from their own
+    // stream off the seed, so the values stay the same as in a CPU-bound run.
+    pub fn with_delays(mut self, seed: u64, latency: &Latency) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed ^ 0x9e3779b97f4a7c15);
+        self.delays = self.iterations
+            .iter()
+            .map(|data| data.iter().map(|_| latency.sample(&mut rng)).collect())
+            .collect();
+        self
+    }
+
+    pub fn delays(&self, iteration: usize) -> Option<Arc<[Duration]>> {
+        (!self.delays.is_empty()).then(|| self.delays[iteration % self.delays.len()].clone())
+    }
+
+    // Warm-up runs cycle through the same datasets as the timed iterations
+    pub fn get(&self, iteration: usize) -> Arc<Vec<u32>> {
+        self.iterations[iteration % self.iterations.len()].clone()
+    }
+
+    // Why `output` differs from the sequential reference for `iteration`, if it does.
+    // Output of the wrong length fails whatever the mode, since a run that lost values
+    // (e.g. an actor that never answered) costs nothing to catch.
+    pub fn check(&self, iteration: usize, output: &[u32], mode: VerifyMode) -> Option<String> {
+        let index = iteration % self.iterations.len();
+        let expected = &self.expected[index];
+        match mode {
+            _ if output.len() != expected.len() =>
+                Some(format!("returned {} values, expected {}", output.len(), expected.len())),
+            VerifyMode::Off => None,
+            VerifyMode::Full => {
+                let wrong = output.iter().zip(expected).filter(|(got, want)| got != want).count();
+                let first = output.iter().zip(expected).position(|(got, want)| got != want)?;
+                Some(format!("{} of {} values differ, first at index {} (got {}, expected {})",
+                             wrong, expected.len(), first, output[first], expected[first]))
+            }
+            VerifyMode::Checksum => {
+                let actual = checksum(output);
+                (actual != self.checksums[index])
+                    .then(|| format!("checksum {:016x}, expected {:016x}", actual, self.checksums[index]))
+            }
+        }
+    }
+}
+
+// 64-bit FNV-1a over the little-endian bytes of every value
+fn checksum(values: &[u32]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for value in values {
+        for byte in value.to_le_bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    hash
+}
+
+// Timings collected for one adapter
+pub struct Measurement {
+    // Total time of the runs executed before sampling started, excluded from statistics
+    pub warmup_times: Vec<Duration>,
+    pub phases: PhaseTimes,
+    pub verification: Verification,
+    // Per-task timings of the timed iterations, when task timing is enabled and
+    // the library spawned anything
+    pub task_times: Option<TaskTimes>,
+}
+
+// Build, run and tear down once, timing each phase. The output is returned
+// untouched so checking it never counts towards any phase; it is None when the
+// run needed simulated I/O the library doesn't have.
+fn run_once(adapter: &mut dyn RuntimeAdapter, data: Arc<Vec<u32>>, delays: Option<Arc<[Duration]>>,
+            workload: &Workload) -> (Duration, Duration, Duration, Option<Vec<u32>>) {
+    let start = Instant::now();
+    adapter.build();
+    let startup = start.elapsed();
+
+    let start = Instant::now();
+    let output = adapter.run(data, delays, workload);
+    let work = start.elapsed();
+
+    let start = Instant::now();
+    adapter.teardown();
+    let shutdown = start.elapsed();
+
+    (startup, work, shutdown, output)
+}
+
+// True once the last `window` warm-up timings vary by less than `cv_threshold`
+fn is_steady(times: &[Duration], window: usize, cv_threshold: f64) -> bool {
+    if window < 2 || times.len() < window {
+        return false;
+    }
+    let recent: Vec<f64> = times[times.len() - window..].iter().map(|d| d.as_nanos() as f64).collect();
+    let mean = stats::mean(&recent);
+    mean > 0.0 && stats::std_dev(&recent, mean) / mean < cv_threshold
+}
+
+// Warm one adapter up, then run it once per timed iteration of the shared datasets.
+// None if the datasets carry simulated I/O and the library has no variant for it.
+pub fn measure(adapter: &mut dyn RuntimeAdapter, workload: &Workload, datasets: &Datasets) -> Option<Measurement> {
+    let mut warmup_times = Vec::new();
+
+    match workload.warmup {
+        Warmup::Fixed { iterations } => {
+            for i in 0..iterations {
+                let (startup, work, shutdown, output) = run_once(adapter, datasets.get(i), datasets.delays(i), workload);
+                output?;
+                warmup_times.push(startup + work + shutdown);
+            }
+        }
+        Warmup::Auto { max_iterations, window, cv_threshold } => {
+            while warmup_times.len() < max_iterations && !is_steady(&warmup_times, window, cv_threshold) {
+                let (startup, work, shutdown, output) = run_once(adapter, datasets.get(warmup_times.len()),
+                                                                datasets.delays(warmup_times.len()), workload);
+                output?;
+                warmup_times.push(startup + work + shutdown);
+            }
+        }
+    }
+
+    // Tasks from the warm-up don't count
+    timing::take();
+
+    let mut phases = PhaseTimes::default();
+    let mut verification = Verification { mode: workload.verify, ..Verification::default() };
+    for i in 0..workload.iterations {
+        // The first timed iteration is the one traced, with --trace
+        let traced = trace::enabled() && i == 0;
+        if traced {
+            trace::start();
+        }
+        let (startup, work, shutdown, output) = run_once(adapter, datasets.get(i), datasets.delays(i), workload);
+        let Some(output) = output else {
+            if traced {
+                trace::discard();
+            }
+            return None;
+        };
+        if traced {
+            trace::stop(adapter.name());
+        }
+        phases.startup.push(startup);
+        phases.workload.push(work);
+        phases.shutdown.push(shutdown);
+
+        if let Some(detail) = datasets.check(i, &output, workload.verify) {
+            verification.passed = false;
+            verification.failed_iterations.push(i);
+            verification.detail.get_or_insert(detail);
+        }
+    }
+
+    let tasks = timing::take();
+    let task_times = (!tasks.is_empty()).then(|| TaskTimes::from_timings(&tasks));
+
+    Some(Measurement { warmup_times, phases, verification, task_times })
+}
+
+// Measure every selected adapter of one category against the given workload
+pub fn run_category(category: Category, workload: &Workload, datasets: &Datasets, selection: &Selection) -> Vec<BenchmarkResult> {
+    adapters_in(category, selection)
+        .into_iter()
+        .filter(|adapter| {
+            let runnable = adapter.supports_collection(workload.collection);
+            if !runnable {
+                eprintln!("{} can't collect through {}, skipped", adapter.name(), workload.collection.as_str());
+            }
+            runnable
+        })
+        .filter_map(|mut adapter| {
+            let Some(measurement) = measure(adapter.as_mut(), workload, datasets) else {
+                eprintln!("{} has no simulated I/O variant, skipped", adapter.name());
+                return None;
+            };
+            if !measurement.verification.passed {
+                eprintln!("{} produced wrong output: {}", adapter.name(),
+                          measurement.verification.detail.as_deref().unwrap_or_default());
+            }
+            Some(BenchmarkResult::from_measurement(adapter.as_ref(), workload, measurement))
+        })
+        .collect()
+}
@@ -1,166 +1,397 @@
-use futures::future::join_all;
-use rand::{thread_rng, Rng};
-use std::sync::{Arc, Mutex};
+use futures::future::{select, Either};
+use std::hint::black_box;
+use std::net::TcpListener;
+use std::pin::pin;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+use crate::adapter::{
+    chunk_ranges, process_range_async, run_category, Datasets, EchoServer, RuntimeAdapter, Submitter,
+};
+use crate::cli::Selection;
+use crate::collect::{Collection, Results};
+use crate::echo::{accept_is_transient, echo_connection, ACCEPT_BACKOFF};
+use crate::report::Progress;
+use crate::results::{BenchmarkResult, Category, WorkCost, Workload};
+use crate::timing;
+
+// Multiply-add rounds per item unless told otherwise, roughly a microsecond
+pub const DEFAULT_WORK_UNITS: u64 = 1000;
+
 // Shared CPU-bound work function
-pub fn process_value(value: u32) -> u32 {
-    // Simulate CPU-bound work with some calculations
+pub fn process_value(value: u32, cost: WorkCost) -> u32 {
+    work(value, cost.units)
+}
+
+// Simulate CPU-bound work with some calculations, one round per work unit
+pub fn work(value: u32, units: u64) -> u32 {
     let mut result = value;
-    for _ in 0..1000 {
+    for _ in 0..units {
         result = result.wrapping_mul(31).wrapping_add(17) % 10000;
     }
     result
 }
 
-// Results structure to collect benchmark data
-pub struct AsyncBenchmarkResult {
-    pub library: String,
-    pub best_time: Duration,
-    pub avg_time: Duration,
-    pub all_times: Vec<Duration>,
-}
+// Nanoseconds one work unit takes on this machine: the fastest of several
+// single-threaded batches, so a busy moment doesn't inflate it
+pub fn calibrate() -> f64 {
+    const UNITS_PER_BATCH: u64 = 1_000_000;
+    const BATCHES: usize = 10;
 
-// Main function to benchmark async libraries
-pub async fn benchmark_async_libraries(data_size: usize, iterations: usize) -> Vec<AsyncBenchmarkResult> {
-    println!("Starting async library benchmarks...");
-    
-    let mut results = Vec::new();
-    
-    // Benchmark Tokio
-    let mut tokio_times = Vec::with_capacity(iterations);
-    let mut tokio_best = Duration::from_secs(u64::MAX);
-    
-    for _ in 0..iterations {
-        // Generate random data
-        let mut rng = thread_rng();
-        let data: Vec<u32> = (0..data_size).map(|_| rng.gen_range(0..10000)).collect();
-        let data_arc = Arc::new(data);
-        
+    let mut best = f64::MAX;
+    for batch in 0..BATCHES {
         let start = Instant::now();
-        let runtime = tokio::runtime::Runtime::new().unwrap();
+        black_box(work(black_box(batch as u32), black_box(UNITS_PER_BATCH)));
+        best = best.min(start.elapsed().as_nanos() as f64);
+    }
+    best / UNITS_PER_BATCH as f64
+}
+
+const CATEGORY: Category = Category::Asynchronous;
+
+pub fn adapters() -> Vec<Box<dyn RuntimeAdapter>> {
+    vec![
+        Box::new(TokioAdapter::default()),
+        Box::new(AsyncStdAdapter),
+        Box::new(SmolAdapter::default()),
+    ]
+}
+
+// Multi-threaded Tokio runtime with `workers` threads, or Tokio's default of one per CPU
+pub fn tokio_runtime(workers: Option<usize>) -> tokio::runtime::Runtime {
+    let mut builder = tokio::runtime::Builder::new_multi_thread();
+    if let Some(workers) = workers {
+        builder.worker_threads(workers);
+    }
+    builder.enable_all().build().unwrap()
+}
+
+// Tokio multi-threaded runtime, one task per chunk
+#[derive(Default)]
+pub struct TokioAdapter {
+    workers: Option<usize>,
+    runtime: Option<tokio::runtime::Runtime>,
+}
+
+impl RuntimeAdapter for TokioAdapter {
+    fn name(&self) -> &'static str {
+        "Tokio"
+    }
+
+    fn category(&self) -> Category {
+        CATEGORY
+    }
+
+    fn threads(&self) -> usize {
+        self.workers.unwrap_or_else(num_cpus::get)
+    }
+
+    fn set_workers(&mut self, workers: usize) -> bool {
+        self.workers = Some(workers);
+        true
+    }
+
+    fn build(&mut self) {
+        self.runtime = Some(tokio_runtime(self.workers));
+    }
+
+    fn run(&mut self, data: Arc<Vec<u32>>, delays: Option<Arc<[Duration]>>, workload: &Workload) -> Option<Vec<u32>> {
+        let cost = workload.cost;
+        let runtime = self.runtime.as_ref().expect("Tokio runtime not built");
         runtime.block_on(async {
-            let results = Arc::new(Mutex::new(vec![0; data_arc.len()]));
+            let results = Arc::new(Results::new(data.len(), workload.collection));
             let mut handles = Vec::new();
-            
-            for (idx, &value) in data_arc.iter().enumerate() {
+
+            for range in chunk_ranges(data.len(), workload.chunk_size) {
+                let data = data.clone();
+                let delays = delays.clone();
                 let results_clone = results.clone();
+                let spawned = timing::spawned();
                 let handle = tokio::spawn(async move {
-                    let processed = process_value(value);
-                    let mut results = results_clone.lock().unwrap();
-                    results[idx] = processed;
+                    let _task = spawned.start();
+                    process_range_async(&data, delays.as_deref(), range, cost, &results_clone, tokio::time::sleep).await
                 });
                 handles.push(handle);
             }
-            
+
             for handle in handles {
-                let _ = handle.await.unwrap();
+                results.returned(handle.await.unwrap());
             }
-        });
-        
-        let duration = start.elapsed();
-        if duration < tokio_best {
-            tokio_best = duration;
-        }
-        tokio_times.push(duration);
-    }
-    
-    let tokio_avg = tokio_times.iter().sum::<Duration>() / tokio_times.len() as u32;
-    results.push(AsyncBenchmarkResult {
-        library: "Tokio".to_string(),
-        best_time: tokio_best,
-        avg_time: tokio_avg,
-        all_times: tokio_times,
-    });
-    
-    // Benchmark async-std
-    let mut async_std_times = Vec::with_capacity(iterations);
-    let mut async_std_best = Duration::from_secs(u64::MAX);
-    
-    for _ in 0..iterations {
-        let mut rng = thread_rng();
-        let data: Vec<u32> = (0..data_size).map(|_| rng.gen_range(0..10000)).collect();
-        let data_arc = Arc::new(data);
-        
-        let start = Instant::now();
+
+            Some(results.take())
+        })
+    }
+
+    fn supports_collection(&self, _collection: Collection) -> bool {
+        true
+    }
+
+    fn submitter(&self) -> Option<Submitter<'_>> {
+        let handle = self.runtime.as_ref()?.handle().clone();
+        Some(Box::new(move |job| {
+            handle.spawn(async move { job() });
+        }))
+    }
+
+    fn echo_server(&self) -> Option<EchoServer<'_>> {
+        let runtime = self.runtime.as_ref()?;
+        Some(Box::new(move |listener: TcpListener, stop: flume::Receiver<()>| {
+            runtime.block_on(async {
+                let listener = tokio::net::TcpListener::from_std(listener)?;
+                let mut dropped = 0;
+                loop {
+                    tokio::select! {
+                        _ = stop.recv_async() => break,
+                        accepted = listener.accept() => match accepted {
+                            Ok((mut socket, _)) => {
+                                let _ = socket.set_nodelay(true);
+                                tokio::spawn(async move {
+                                    let (mut reader, mut writer) = socket.split();
+                                    let _ = tokio::io::copy(&mut reader, &mut writer).await;
+                                });
+                            }
+                            Err(err) if accept_is_transient(&err) => {
+                                dropped += 1;
+                                tokio::time::sleep(ACCEPT_BACKOFF).await;
+                            }
+                            Err(err) => return Err(err),
+                        }
+                    }
+                }
+                Ok(dropped)
+            })
+        }))
+    }
+
+    fn teardown(&mut self) {
+        self.runtime = None;
+    }
+}
+
+// async-std global executor, one task per chunk
+pub struct AsyncStdAdapter;
+
+impl RuntimeAdapter for AsyncStdAdapter {
+    fn name(&self) -> &'static str {
+        "async-std"
+    }
+
+    fn category(&self) -> Category {
+        CATEGORY
+    }
+
+    fn threads(&self) -> usize {
+        std::env::var("ASYNC_STD_THREAD_COUNT").ok().and_then(|n| n.parse().ok()).unwrap_or_else(num_cpus::get)
+    }
+
+    // The global executor reads this once, on first use
+    fn workers_env_var(&self) -> Option<&'static str> {
+        Some("ASYNC_STD_THREAD_COUNT")
+    }
+
+    fn run(&mut self, data: Arc<Vec<u32>>, delays: Option<Arc<[Duration]>>, workload: &Workload) -> Option<Vec<u32>> {
+        let cost = workload.cost;
         async_std::task::block_on(async {
-            let results = Arc::new(Mutex::new(vec![0; data_arc.len()]));
+            let results = Arc::new(Results::new(data.len(), workload.collection));
             let mut handles = Vec::new();
-            
-            for (idx, &value) in data_arc.iter().enumerate() {
+
+            for range in chunk_ranges(data.len(), workload.chunk_size) {
+                let data = data.clone();
+                let delays = delays.clone();
                 let results_clone = results.clone();
+                let spawned = timing::spawned();
                 let handle = async_std::task::spawn(async move {
-                    let processed = process_value(value);
-                    let mut results = results_clone.lock().unwrap();
-                    results[idx] = processed;
+                    let _task = spawned.start();
+                    process_range_async(&data, delays.as_deref(), range, cost, &results_clone, async_std::task::sleep).await
                 });
                 handles.push(handle);
             }
-            
+
             for handle in handles {
-                handle.await;
+                results.returned(handle.await);
             }
-        });
-        
-        let duration = start.elapsed();
-        if duration < async_std_best {
-            async_std_best = duration;
+
+            Some(results.take())
+        })
+    }
+
+    fn supports_collection(&self, _collection: Collection) -> bool {
+        true
+    }
+
+    fn submitter(&self) -> Option<Submitter<'_>> {
+        Some(Box::new(|job| {
+            async_std::task::spawn(async move { job() });
+        }))
+    }
+
+    fn echo_server(&self) -> Option<EchoServer<'_>> {
+        Some(Box::new(|listener: TcpListener, stop: flume::Receiver<()>| {
+            async_std::task::block_on(async {
+                let listener = async_std::net::TcpListener::from(listener);
+                let mut dropped = 0;
+                loop {
+                    match select(pin!(stop.recv_async()), pin!(listener.accept())).await {
+                        Either::Left(_) => break,
+                        Either::Right((Ok((socket, _)), _)) => {
+                            let _ = socket.set_nodelay(true);
+                            async_std::task::spawn(echo_connection(socket));
+                        }
+                        Either::Right((Err(err), _)) if accept_is_transient(&err) => {
+                            dropped += 1;
+                            async_std::task::sleep(ACCEPT_BACKOFF).await;
+                        }
+                        Either::Right((Err(err), _)) => return Err(err),
+                    }
+                }
+                Ok(dropped)
+            })
+        }))
+    }
+}
+
+// A smol executor driven by its own threads until `stop` is dropped
+pub struct SmolExecutor {
+    pub executor: Arc<smol::Executor<'static>>,
+    stop: smol::channel::Sender<()>,
+    threads: Vec<std::thread::JoinHandle<()>>,
+}
+
+impl SmolExecutor {
+    // Stop the executor's threads and wait for them to exit
+    pub fn shutdown(self) {
+        drop(self.stop);
+        for thread in self.threads {
+            thread.join().unwrap();
         }
-        async_std_times.push(duration);
-    }
-    
-    let async_std_avg = async_std_times.iter().sum::<Duration>() / async_std_times.len() as u32;
-    results.push(AsyncBenchmarkResult {
-        library: "async-std".to_string(),
-        best_time: async_std_best,
-        avg_time: async_std_avg,
-        all_times: async_std_times,
-    });
-    
-    // Benchmark smol
-    let mut smol_times = Vec::with_capacity(iterations);
-    let mut smol_best = Duration::from_secs(u64::MAX);
-    
-    for _ in 0..iterations {
-        let mut rng = thread_rng();
-        let data: Vec<u32> = (0..data_size).map(|_| rng.gen_range(0..10000)).collect();
-        let data_arc = Arc::new(data);
-        
-        let start = Instant::now();
+    }
+}
+
+// smol executor run on `threads` threads of its own. smol's global executor only
+// gets more than one thread through SMOL_THREADS, so anything compared against a
+// multi-threaded runtime uses one of these instead.
+pub fn smol_executor(threads: usize) -> SmolExecutor {
+    let executor = Arc::new(smol::Executor::new());
+    let (stop, stopped) = smol::channel::unbounded::<()>();
+    let threads = (0..threads.max(1))
+        .map(|_| {
+            let executor = executor.clone();
+            let stopped = stopped.clone();
+            std::thread::spawn(move || {
+                let _ = smol::block_on(executor.run(stopped.recv()));
+            })
+        })
+        .collect();
+    SmolExecutor { executor, stop, threads }
+}
+
+// smol executor run on `threads()` threads, one task per chunk. Sized like smol's
+// global executor by default: SMOL_THREADS, or a single thread.
+#[derive(Default)]
+pub struct SmolAdapter {
+    workers: Option<usize>,
+    executor: Option<SmolExecutor>,
+}
+
+impl RuntimeAdapter for SmolAdapter {
+    fn name(&self) -> &'static str {
+        "smol"
+    }
+
+    fn category(&self) -> Category {
+        CATEGORY
+    }
+
+    fn threads(&self) -> usize {
+        self.workers
+            .or_else(|| std::env::var("SMOL_THREADS").ok().and_then(|n| n.parse().ok()))
+            .unwrap_or(1)
+    }
+
+    fn set_workers(&mut self, workers: usize) -> bool {
+        self.workers = Some(workers);
+        true
+    }
+
+    fn build(&mut self) {
+        self.executor = Some(smol_executor(self.threads()));
+    }
+
+    fn run(&mut self, data: Arc<Vec<u32>>, delays: Option<Arc<[Duration]>>, workload: &Workload) -> Option<Vec<u32>> {
+        let cost = workload.cost;
+        let executor = &self.executor.as_ref().expect("smol executor not built").executor;
         smol::block_on(async {
-            let results = Arc::new(Mutex::new(vec![0; data_arc.len()]));
+            let results = Arc::new(Results::new(data.len(), workload.collection));
             let mut handles = Vec::new();
-            
-            for (idx, &value) in data_arc.iter().enumerate() {
+
+            for range in chunk_ranges(data.len(), workload.chunk_size) {
+                let data = data.clone();
+                let delays = delays.clone();
                 let results_clone = results.clone();
-                let handle = smol::spawn(async move {
-                    let processed = process_value(value);
-                    let mut results = results_clone.lock().unwrap();
-                    results[idx] = processed;
+                let spawned = timing::spawned();
+                let handle = executor.spawn(async move {
+                    let _task = spawned.start();
+                    process_range_async(&data, delays.as_deref(), range, cost, &results_clone, smol::Timer::after).await
                 });
                 handles.push(handle);
             }
-            
+
             for handle in handles {
-                handle.await;
+                results.returned(handle.await);
             }
-        });
-        
-        let duration = start.elapsed();
-        if duration < smol_best {
-            smol_best = duration;
+
+            Some(results.take())
+        })
+    }
+
+    fn supports_collection(&self, _collection: Collection) -> bool {
+        true
+    }
+
+    fn submitter(&self) -> Option<Submitter<'_>> {
+        let executor = &self.executor.as_ref()?.executor;
+        Some(Box::new(move |job| executor.spawn(async move { job() }).detach()))
+    }
+
+    fn echo_server(&self) -> Option<EchoServer<'_>> {
+        let executor = &self.executor.as_ref()?.executor;
+        Some(Box::new(move |listener: TcpListener, stop: flume::Receiver<()>| {
+            smol::block_on(async {
+                let listener = smol::net::TcpListener::try_from(listener)?;
+                let mut dropped = 0;
+                loop {
+                    match select(pin!(stop.recv_async()), pin!(listener.accept())).await {
+                        Either::Left(_) => break,
+                        Either::Right((Ok((socket, _)), _)) => {
+                            let _ = socket.set_nodelay(true);
+                            executor.spawn(echo_connection(socket)).detach();
+                        }
+                        Either::Right((Err(err), _)) if accept_is_transient(&err) => {
+                            dropped += 1;
+                            smol::Timer::after(ACCEPT_BACKOFF).await;
+                        }
+                        Either::Right((Err(err), _)) => return Err(err),
+                    }
+                }
+                Ok(dropped)
+            })
+        }))
+    }
+
+    fn teardown(&mut self) {
+        if let Some(executor) = self.executor.take() {
+            executor.shutdown();
         }
-        smol_times.push(duration);
-    }
-    
-    let smol_avg = smol_times.iter().sum::<Duration>() / smol_times.len() as u32;
-    results.push(AsyncBenchmarkResult {
-        library: "smol".to_string(),
-        best_time: smol_best,
-        avg_time: smol_avg,
-        all_times: smol_times,
-    });
-    
-    println!("Async library benchmarks completed.");
+    }
+}
+
+// Main function to benchmark async libraries
+pub fn benchmark_async_libraries(workload: &Workload, datasets: &Datasets, selection: &Selection,
+                                 progress: Progress) -> Vec<BenchmarkResult> {
+    progress.line("Starting async library benchmarks...");
+
+    let results = run_category(CATEGORY, workload, datasets, selection);
+
+    progress.line("Async library benchmarks completed.");
     results
-}
\ No newline at end of file
+}
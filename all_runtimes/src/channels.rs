@@ -0,0 +1,757 @@
+use clap::ValueEnum;
+use hdrhistogram::Histogram;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::async_check::tokio_runtime;
+use crate::cli::ChannelsArgs;
+use crate::results::{nanos, RunMetadata};
+use crate::stats;
+
+// Which channel implementation carries the messages
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum ChannelKind {
+    Flume,
+    Crossbeam,
+    StdMpsc,
+    TokioMpsc,
+    TokioBroadcast,
+    AsyncChannel,
+    Kanal,
+}
+
+impl ChannelKind {
+    pub const ALL: [ChannelKind; 7] = [
+        ChannelKind::Flume,
+        ChannelKind::Crossbeam,
+        ChannelKind::StdMpsc,
+        ChannelKind::TokioMpsc,
+        ChannelKind::TokioBroadcast,
+        ChannelKind::AsyncChannel,
+        ChannelKind::Kanal,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChannelKind::Flume => "flume",
+            ChannelKind::Crossbeam => "crossbeam-channel",
+            ChannelKind::StdMpsc => "std mpsc",
+            ChannelKind::TokioMpsc => "tokio mpsc",
+            ChannelKind::TokioBroadcast => "tokio broadcast",
+            ChannelKind::AsyncChannel => "async-channel",
+            ChannelKind::Kanal => "kanal",
+        }
+    }
+}
+
+// How many tasks send and receive
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum Topology {
+    // One sender, one receiver
+    Spsc,
+    // --producers senders, one receiver
+    Mpsc,
+    // --producers senders, --consumers receivers sharing the messages
+    Mpmc,
+}
+
+impl Topology {
+    pub const ALL: [Topology; 3] = [Topology::Spsc, Topology::Mpsc, Topology::Mpmc];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Topology::Spsc => "SPSC",
+            Topology::Mpsc => "MPSC",
+            Topology::Mpmc => "MPMC",
+        }
+    }
+}
+
+// How the two ends wait on the channel
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum Flavor {
+    // Blocking calls on OS threads
+    Sync,
+    // Awaited on Tokio tasks
+    Async,
+    // Blocking senders on OS threads, awaited receivers on Tokio tasks
+    Mixed,
+}
+
+impl Flavor {
+    pub const ALL: [Flavor; 3] = [Flavor::Sync, Flavor::Async, Flavor::Mixed];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Flavor::Sync => "sync",
+            Flavor::Async => "async",
+            Flavor::Mixed => "mixed",
+        }
+    }
+}
+
+// Human-readable bound: a capacity, or unbounded
+pub fn capacity_str(capacity: Option<usize>) -> String {
+    capacity.map_or("unbounded".to_string(), |c| c.to_string())
+}
+
+// Messages tokio's broadcast channel dropped because a receiver lagged, since the last `take_lost`
+static LOST: AtomicU64 = AtomicU64::new(0);
+
+fn take_lost() -> u64 {
+    LOST.swap(0, Ordering::Relaxed)
+}
+
+// One channel implementation, seen through the operations the benchmark needs.
+// Everything is generic over it, so sends and receives are called directly.
+trait Channel {
+    type Tx: Clone + Send + 'static;
+    type Rx: Send + 'static;
+
+    // Whether receivers can be cloned to share the messages
+    const MULTI_CONSUMER: bool = true;
+    // Whether it can be awaited
+    const ASYNC: bool = true;
+    // Whether it has an unbounded mode
+    const UNBOUNDED: bool = true;
+
+    fn open(capacity: Option<usize>) -> (Self::Tx, Self::Rx);
+
+    // Only called when MULTI_CONSUMER is true
+    fn clone_rx(_rx: &Self::Rx) -> Self::Rx {
+        unreachable!("single-consumer channel")
+    }
+
+    fn send_blocking(tx: &Self::Tx, value: u64);
+
+    // None once every sender is gone
+    fn recv_blocking(rx: &mut Self::Rx) -> Option<u64>;
+
+    // Only called when ASYNC is true
+    fn send(tx: &Self::Tx, value: u64) -> impl Future<Output = ()> + Send;
+
+    fn recv(rx: &mut Self::Rx) -> impl Future<Output = Option<u64>> + Send;
+}
+
+struct Flume;
+
+impl Channel for Flume {
+    type Tx = flume::Sender<u64>;
+    type Rx = flume::Receiver<u64>;
+
+    fn open(capacity: Option<usize>) -> (Self::Tx, Self::Rx) {
+        capacity.map_or_else(flume::unbounded, flume::bounded)
+    }
+
+    fn clone_rx(rx: &Self::Rx) -> Self::Rx {
+        rx.clone()
+    }
+
+    fn send_blocking(tx: &Self::Tx, value: u64) {
+        tx.send(value).unwrap();
+    }
+
+    fn recv_blocking(rx: &mut Self::Rx) -> Option<u64> {
+        rx.recv().ok()
+    }
+
+    async fn send(tx: &Self::Tx, value: u64) {
+        tx.send_async(value).await.unwrap()
+    }
+
+    async fn recv(rx: &mut Self::Rx) -> Option<u64> {
+        rx.recv_async().await.ok()
+    }
+}
+
+struct Crossbeam;
+
+impl Channel for Crossbeam {
+    type Tx = crossbeam::channel::Sender<u64>;
+    type Rx = crossbeam::channel::Receiver<u64>;
+
+    const ASYNC: bool = false;
+
+    fn open(capacity: Option<usize>) -> (Self::Tx, Self::Rx) {
+        capacity.map_or_else(crossbeam::channel::unbounded, crossbeam::channel::bounded)
+    }
+
+    fn clone_rx(rx: &Self::Rx) -> Self::Rx {
+        rx.clone()
+    }
+
+    fn send_blocking(tx: &Self::Tx, value: u64) {
+        tx.send(value).unwrap();
+    }
+
+    fn recv_blocking(rx: &mut Self::Rx) -> Option<u64> {
+        rx.recv().ok()
+    }
+
+    async fn send(_tx: &Self::Tx, _value: u64) {
+        unreachable!("crossbeam-channel can't be awaited")
+    }
+
+    async fn recv(_rx: &mut Self::Rx) -> Option<u64> {
+        unreachable!("crossbeam-channel can't be awaited")
+    }
+}
+
+struct StdMpsc;
+
+#[derive(Clone)]
+enum StdTx {
+    Unbounded(std::sync::mpsc::Sender<u64>),
+    Bounded(std::sync::mpsc::SyncSender<u64>),
+}
+
+impl Channel for StdMpsc {
+    type Tx = StdTx;
+    type Rx = std::sync::mpsc::Receiver<u64>;
+
+    const MULTI_CONSUMER: bool = false;
+    const ASYNC: bool = false;
+
+    fn open(capacity: Option<usize>) -> (Self::Tx, Self::Rx) {
+        match capacity {
+            Some(capacity) => {
+                let (tx, rx) = std::sync::mpsc::sync_channel(capacity);
+                (StdTx::Bounded(tx), rx)
+            }
+            None => {
+                let (tx, rx) = std::sync::mpsc::channel();
+                (StdTx::Unbounded(tx), rx)
+            }
+        }
+    }
+
+    fn send_blocking(tx: &Self::Tx, value: u64) {
+        match tx {
+            StdTx::Unbounded(tx) => tx.send(value).unwrap(),
+            StdTx::Bounded(tx) => tx.send(value).unwrap(),
+        }
+    }
+
+    fn recv_blocking(rx: &mut Self::Rx) -> Option<u64> {
+        rx.recv().ok()
+    }
+
+    async fn send(_tx: &Self::Tx, _value: u64) {
+        unreachable!("std mpsc can't be awaited")
+    }
+
+    async fn recv(_rx: &mut Self::Rx) -> Option<u64> {
+        unreachable!("std mpsc can't be awaited")
+    }
+}
+
+struct TokioMpsc;
+
+#[derive(Clone)]
+enum TokioTx {
+    Bounded(tokio::sync::mpsc::Sender<u64>),
+    Unbounded(tokio::sync::mpsc::UnboundedSender<u64>),
+}
+
+enum TokioRx {
+    Bounded(tokio::sync::mpsc::Receiver<u64>),
+    Unbounded(tokio::sync::mpsc::UnboundedReceiver<u64>),
+}
+
+impl Channel for TokioMpsc {
+    type Tx = TokioTx;
+    type Rx = TokioRx;
+
+    const MULTI_CONSUMER: bool = false;
+
+    fn open(capacity: Option<usize>) -> (Self::Tx, Self::Rx) {
+        match capacity {
+            Some(capacity) => {
+                let (tx, rx) = tokio::sync::mpsc::channel(capacity);
+                (TokioTx::Bounded(tx), TokioRx::Bounded(rx))
+            }
+            None => {
+                let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+                (TokioTx::Unbounded(tx), TokioRx::Unbounded(rx))
+            }
+        }
+    }
+
+    fn send_blocking(tx: &Self::Tx, value: u64) {
+        match tx {
+            TokioTx::Bounded(tx) => tx.blocking_send(value).unwrap(),
+            TokioTx::Unbounded(tx) => tx.send(value).unwrap(),
+        }
+    }
+
+    fn recv_blocking(rx: &mut Self::Rx) -> Option<u64> {
+        match rx {
+            TokioRx::Bounded(rx) => rx.blocking_recv(),
+            TokioRx::Unbounded(rx) => rx.blocking_recv(),
+        }
+    }
+
+    async fn send(tx: &Self::Tx, value: u64) {
+        match tx {
+            TokioTx::Bounded(tx) => tx.send(value).await.unwrap(),
+            TokioTx::Unbounded(tx) => tx.send(value).unwrap(),
+        }
+    }
+
+    async fn recv(rx: &mut Self::Rx) -> Option<u64> {
+        match rx {
+            TokioRx::Bounded(rx) => rx.recv().await,
+            TokioRx::Unbounded(rx) => rx.recv().await,
+        }
+    }
+}
+
+// Every receiver sees every message, and a receiver that falls more than the
+// capacity behind loses the oldest ones, counted in LOST. Run with one receiver.
+struct TokioBroadcast;
+
+impl Channel for TokioBroadcast {
+    type Tx = tokio::sync::broadcast::Sender<u64>;
+    type Rx = tokio::sync::broadcast::Receiver<u64>;
+
+    const MULTI_CONSUMER: bool = false;
+    const UNBOUNDED: bool = false;
+
+    fn open(capacity: Option<usize>) -> (Self::Tx, Self::Rx) {
+        tokio::sync::broadcast::channel(capacity.expect("tokio broadcast is always bounded"))
+    }
+
+    // Never waits: a full channel overwrites its oldest message
+    fn send_blocking(tx: &Self::Tx, value: u64) {
+        tx.send(value).unwrap();
+    }
+
+    fn recv_blocking(rx: &mut Self::Rx) -> Option<u64> {
+        loop {
+            match rx.blocking_recv() {
+                Ok(value) => return Some(value),
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(lost)) => {
+                    LOST.fetch_add(lost, Ordering::Relaxed);
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+
+    async fn send(tx: &Self::Tx, value: u64) {
+        tx.send(value).unwrap();
+    }
+
+    async fn recv(rx: &mut Self::Rx) -> Option<u64> {
+        loop {
+            match rx.recv().await {
+                Ok(value) => return Some(value),
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(lost)) => {
+                    LOST.fetch_add(lost, Ordering::Relaxed);
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+struct AsyncChannel;
+
+impl Channel for AsyncChannel {
+    type Tx = async_channel::Sender<u64>;
+    type Rx = async_channel::Receiver<u64>;
+
+    fn open(capacity: Option<usize>) -> (Self::Tx, Self::Rx) {
+        capacity.map_or_else(async_channel::unbounded, async_channel::bounded)
+    }
+
+    fn clone_rx(rx: &Self::Rx) -> Self::Rx {
+        rx.clone()
+    }
+
+    fn send_blocking(tx: &Self::Tx, value: u64) {
+        tx.send_blocking(value).unwrap();
+    }
+
+    fn recv_blocking(rx: &mut Self::Rx) -> Option<u64> {
+        rx.recv_blocking().ok()
+    }
+
+    async fn send(tx: &Self::Tx, value: u64) {
+        tx.send(value).await.unwrap()
+    }
+
+    async fn recv(rx: &mut Self::Rx) -> Option<u64> {
+        rx.recv().await.ok()
+    }
+}
+
+struct Kanal;
+
+impl Channel for Kanal {
+    type Tx = kanal::Sender<u64>;
+    type Rx = kanal::Receiver<u64>;
+
+    fn open(capacity: Option<usize>) -> (Self::Tx, Self::Rx) {
+        capacity.map_or_else(kanal::unbounded, kanal::bounded)
+    }
+
+    fn clone_rx(rx: &Self::Rx) -> Self::Rx {
+        rx.clone()
+    }
+
+    fn send_blocking(tx: &Self::Tx, value: u64) {
+        tx.send(value).unwrap();
+    }
+
+    fn recv_blocking(rx: &mut Self::Rx) -> Option<u64> {
+        rx.recv().ok()
+    }
+
+    async fn send(tx: &Self::Tx, value: u64) {
+        tx.as_async().send(value).await.unwrap()
+    }
+
+    async fn recv(rx: &mut Self::Rx) -> Option<u64> {
+        rx.as_async().recv().await.ok()
+    }
+}
+
+// One channel, topology, bound and flavor
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ChannelPoint {
+    pub channel: ChannelKind,
+    pub topology: Topology,
+    // None for unbounded
+    pub capacity: Option<usize>,
+    pub flavor: Flavor,
+    pub producers: usize,
+    pub consumers: usize,
+    // From the first send to the last receive
+    #[serde(with = "nanos")]
+    pub median: Duration,
+    // Messages delivered per second of the median run
+    pub messages_per_sec: f64,
+    // Messages tokio broadcast dropped on a lagging receiver, over all runs
+    pub lost: u64,
+}
+
+// One message in flight at a time, there and back over a pair of channels
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RoundTrip {
+    pub channel: ChannelKind,
+    pub capacity: Option<usize>,
+    pub flavor: Flavor,
+    pub trips: usize,
+    #[serde(with = "nanos")]
+    pub p50: Duration,
+    #[serde(with = "nanos")]
+    pub p99: Duration,
+    #[serde(with = "nanos")]
+    pub max: Duration,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ChannelMatrix {
+    pub metadata: RunMetadata,
+    pub messages: usize,
+    pub iterations: usize,
+    pub producers: usize,
+    pub consumers: usize,
+    pub channels: Vec<ChannelKind>,
+    pub topologies: Vec<Topology>,
+    pub capacities: Vec<Option<usize>>,
+    pub flavors: Vec<Flavor>,
+    pub points: Vec<ChannelPoint>,
+    pub round_trips: Vec<RoundTrip>,
+    // Combinations a channel can't do, e.g. "std mpsc: MPMC"
+    pub unsupported: Vec<String>,
+}
+
+// Everything one channel is measured with
+struct Plan<'a> {
+    args: &'a ChannelsArgs,
+    runtime: &'a tokio::runtime::Runtime,
+    kind: ChannelKind,
+}
+
+// Push every message through every selected channel in every selected shape, and
+// time round trips for each bound and flavor
+pub fn run_channels(args: &ChannelsArgs) -> ChannelMatrix {
+    // Async ends run on one shared multi-threaded Tokio runtime
+    let runtime = tokio_runtime(None);
+    let mut matrix = ChannelMatrix {
+        metadata: RunMetadata::collect(),
+        messages: args.messages,
+        iterations: args.iterations,
+        producers: args.producers,
+        consumers: args.consumers,
+        channels: args.channels.clone(),
+        topologies: args.topologies.clone(),
+        capacities: args.capacities.clone(),
+        flavors: args.flavors.clone(),
+        points: Vec::new(),
+        round_trips: Vec::new(),
+        unsupported: Vec::new(),
+    };
+
+    for &kind in &args.channels {
+        let plan = Plan { args, runtime: &runtime, kind };
+        match kind {
+            ChannelKind::Flume => measure_channel::<Flume>(&plan, &mut matrix),
+            ChannelKind::Crossbeam => measure_channel::<Crossbeam>(&plan, &mut matrix),
+            ChannelKind::StdMpsc => measure_channel::<StdMpsc>(&plan, &mut matrix),
+            ChannelKind::TokioMpsc => measure_channel::<TokioMpsc>(&plan, &mut matrix),
+            ChannelKind::TokioBroadcast => measure_channel::<TokioBroadcast>(&plan, &mut matrix),
+            ChannelKind::AsyncChannel => measure_channel::<AsyncChannel>(&plan, &mut matrix),
+            ChannelKind::Kanal => measure_channel::<Kanal>(&plan, &mut matrix),
+        }
+    }
+    matrix
+}
+
+fn measure_channel<C: Channel>(plan: &Plan, matrix: &mut ChannelMatrix) {
+    let args = plan.args;
+    let name = plan.kind.as_str();
+    let mut unsupported = |what: String| {
+        let entry = format!("{}: {}", name, what);
+        if !matrix.unsupported.contains(&entry) {
+            matrix.unsupported.push(entry);
+        }
+    };
+
+    let mut points = Vec::new();
+    let mut round_trips = Vec::new();
+    for &capacity in &args.capacities {
+        if capacity.is_none() && !C::UNBOUNDED {
+            unsupported("unbounded".to_string());
+            continue;
+        }
+        for &flavor in &args.flavors {
+            if flavor != Flavor::Sync && !C::ASYNC {
+                unsupported(flavor.as_str().to_string());
+                continue;
+            }
+
+            for &topology in &args.topologies {
+                let (producers, consumers) = match topology {
+                    Topology::Spsc => (1, 1),
+                    Topology::Mpsc => (args.producers, 1),
+                    Topology::Mpmc => (args.producers, args.consumers),
+                };
+                if consumers > 1 && !C::MULTI_CONSUMER {
+                    unsupported(topology.as_str().to_string());
+                    continue;
+                }
+                eprintln!("Measuring {} {} {} {}...", name, topology.as_str(), capacity_str(capacity), flavor.as_str());
+
+                throughput::<C>(plan.runtime, capacity, flavor, args.messages.min(1000), producers, consumers);
+                take_lost();
+                let mut times = Vec::new();
+                let mut wrong = None;
+                for _ in 0..args.iterations {
+                    let (elapsed, received, sum) =
+                        throughput::<C>(plan.runtime, capacity, flavor, args.messages, producers, consumers);
+                    times.push(elapsed);
+                    // Lossy channels deliver a subset; everything else must deliver every message once
+                    let expected_sum = (args.messages as u64).saturating_sub(1) * args.messages as u64 / 2;
+                    if plan.kind != ChannelKind::TokioBroadcast && (received != args.messages as u64 || sum != expected_sum) {
+                        wrong.get_or_insert(format!("received {} of {} messages", received, args.messages));
+                    }
+                }
+                if let Some(detail) = wrong {
+                    eprintln!("{} {} lost or duplicated messages, skipped: {}", name, topology.as_str(), detail);
+                    continue;
+                }
+
+                let lost = take_lost();
+                let median = stats::median(&times);
+                let delivered = args.messages as f64 - lost as f64 / args.iterations as f64;
+                points.push(ChannelPoint {
+                    channel: plan.kind,
+                    topology,
+                    capacity,
+                    flavor,
+                    producers,
+                    consumers,
+                    median,
+                    messages_per_sec: delivered / median.as_secs_f64().max(1e-9),
+                    lost,
+                });
+            }
+
+            if args.round_trips > 0 {
+                let histogram = round_trip::<C>(plan.runtime, capacity, flavor, args.round_trips);
+                round_trips.push(RoundTrip {
+                    channel: plan.kind,
+                    capacity,
+                    flavor,
+                    trips: args.round_trips,
+                    p50: Duration::from_nanos(histogram.value_at_quantile(0.5)),
+                    p99: Duration::from_nanos(histogram.value_at_quantile(0.99)),
+                    max: Duration::from_nanos(histogram.max()),
+                });
+            }
+        }
+    }
+    matrix.points.extend(points);
+    matrix.round_trips.extend(round_trips);
+}
+
+// The values producer `i` of `producers` sends, so together they send 0..messages
+fn share(messages: usize, producers: usize, i: usize) -> Range<u64> {
+    let start = messages * i / producers;
+    let end = messages * (i + 1) / producers;
+    start as u64..end as u64
+}
+
+// The original receiver plus clones, one per consumer
+fn receivers<C: Channel>(rx: C::Rx, consumers: usize) -> Vec<C::Rx> {
+    let mut receivers: Vec<C::Rx> = (1..consumers).map(|_| C::clone_rx(&rx)).collect();
+    receivers.push(rx);
+    receivers
+}
+
+// Send `messages` values from `producers` senders to `consumers` receivers. Returns
+// the time from the first send to the last receive, how many values arrived and their sum.
+fn throughput<C: Channel>(runtime: &tokio::runtime::Runtime, capacity: Option<usize>, flavor: Flavor, messages: usize,
+                          producers: usize, consumers: usize) -> (Duration, u64, u64) {
+    let (tx, rx) = C::open(capacity);
+    let receivers = receivers::<C>(rx, consumers);
+    let start = Instant::now();
+
+    let totals: Vec<(u64, u64)> = match flavor {
+        Flavor::Sync => std::thread::scope(|s| {
+            let consumers: Vec<_> = receivers
+                .into_iter()
+                .map(|mut rx| {
+                    s.spawn(move || {
+                        let (mut count, mut sum) = (0, 0);
+                        while let Some(value) = C::recv_blocking(&mut rx) {
+                            count += 1;
+                            sum += value;
+                        }
+                        (count, sum)
+                    })
+                })
+                .collect();
+            for i in 0..producers {
+                let tx = tx.clone();
+                s.spawn(move || {
+                    for value in share(messages, producers, i) {
+                        C::send_blocking(&tx, value);
+                    }
+                });
+            }
+            drop(tx);
+            consumers.into_iter().map(|c| c.join().unwrap()).collect()
+        }),
+        Flavor::Async | Flavor::Mixed => {
+            let consumers: Vec<_> = receivers
+                .into_iter()
+                .map(|mut rx| {
+                    runtime.spawn(async move {
+                        let (mut count, mut sum) = (0, 0);
+                        while let Some(value) = C::recv(&mut rx).await {
+                            count += 1;
+                            sum += value;
+                        }
+                        (count, sum)
+                    })
+                })
+                .collect();
+            let mut threads = Vec::new();
+            for i in 0..producers {
+                let tx = tx.clone();
+                if flavor == Flavor::Async {
+                    runtime.spawn(async move {
+                        for value in share(messages, producers, i) {
+                            C::send(&tx, value).await;
+                        }
+                    });
+                } else {
+                    threads.push(std::thread::spawn(move || {
+                        for value in share(messages, producers, i) {
+                            C::send_blocking(&tx, value);
+                        }
+                    }));
+                }
+            }
+            drop(tx);
+            let totals = runtime.block_on(async {
+                let mut totals = Vec::new();
+                for consumer in consumers {
+                    totals.push(consumer.await.unwrap());
+                }
+                totals
+            });
+            for thread in threads {
+                thread.join().unwrap();
+            }
+            totals
+        }
+    };
+
+    let elapsed = start.elapsed();
+    let (received, sum) = totals.iter().fold((0, 0), |(count, sum), &(c, s)| (count + c, sum + s));
+    (elapsed, received, sum)
+}
+
+// Bounce one message at a time off an echoing end, `trips` times. The echo end is
+// a thread for sync, a Tokio task otherwise; the timing end awaits only for async.
+fn round_trip<C: Channel>(runtime: &tokio::runtime::Runtime, capacity: Option<usize>, flavor: Flavor,
+                          trips: usize) -> Histogram<u64> {
+    let (ping_tx, mut ping_rx) = C::open(capacity);
+    let (pong_tx, mut pong_rx) = C::open(capacity);
+    let mut histogram = Histogram::<u64>::new(3).unwrap();
+
+    match flavor {
+        Flavor::Sync => std::thread::scope(|s| {
+            s.spawn(move || {
+                while let Some(value) = C::recv_blocking(&mut ping_rx) {
+                    C::send_blocking(&pong_tx, value);
+                }
+            });
+            for trip in 0..trips as u64 {
+                let sent = Instant::now();
+                C::send_blocking(&ping_tx, trip);
+                C::recv_blocking(&mut pong_rx);
+                histogram.record(sent.elapsed().as_nanos() as u64).unwrap();
+            }
+            drop(ping_tx);
+        }),
+        Flavor::Async | Flavor::Mixed => {
+            let echo = runtime.spawn(async move {
+                while let Some(value) = C::recv(&mut ping_rx).await {
+                    C::send(&pong_tx, value).await;
+                }
+            });
+            if flavor == Flavor::Async {
+                runtime.block_on(async {
+                    for trip in 0..trips as u64 {
+                        let sent = Instant::now();
+                        C::send(&ping_tx, trip).await;
+                        C::recv(&mut pong_rx).await;
+                        histogram.record(sent.elapsed().as_nanos() as u64).unwrap();
+                    }
+                });
+            } else {
+                for trip in 0..trips as u64 {
+                    let sent = Instant::now();
+                    C::send_blocking(&ping_tx, trip);
+                    C::recv_blocking(&mut pong_rx);
+                    histogram.record(sent.elapsed().as_nanos() as u64).unwrap();
+                }
+            }
+            drop(ping_tx);
+            runtime.block_on(echo).unwrap();
+        }
+    }
+    histogram
+}
@@ -0,0 +1,896 @@
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::adapter::RuntimeAdapter;
+use crate::async_check::DEFAULT_WORK_UNITS;
+use crate::channels::{ChannelKind, Flavor, Topology};
+use crate::collect::Collection;
+use crate::export::OutputFormat;
+use crate::locks::{LockKind, LockRuntime};
+use crate::openloop::Arrivals;
+use crate::pipeline::PipelineRuntime;
+use crate::report::RankBy;
+use crate::results::{Category, Latency, Phase, VerifyMode, Warmup};
+use crate::significance::SignificanceTest;
+
+#[derive(Parser, Debug)]
+#[command(name = "all_runtimes", about = "Benchmark Rust concurrency libraries on CPU-bound and simulated I/O workloads")]
+pub struct Cli {
+    /// Defaults to `run` with default settings
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Run the benchmarks and print or export the results
+    Run(RunArgs),
+    /// List the registered libraries and their categories
+    List(Selection),
+    /// Compare two runs previously exported with `--format json`
+    Compare(CompareArgs),
+    /// Measure how long one work unit takes on this machine
+    Calibrate,
+    /// Vary per-item cost and chunk size to find where spawning pays off
+    Sweep(SweepArgs),
+    /// Run every library at a range of data sizes and report time and throughput
+    Scale(ScaleArgs),
+    /// Run every library with 1, 2, 4, ... worker threads and fit Amdahl's law
+    Threads(ThreadsArgs),
+    /// Run every library with each way of collecting results and report the lock overhead
+    Collect(CollectArgs),
+    /// Swap the lock every item stores through between sync and async mutexes and RwLocks
+    Locks(LocksArgs),
+    /// Push messages through each channel library in several shapes and report messages/sec and round-trip latency
+    Channels(ChannelsArgs),
+    /// Run a multi-stage pipeline over bounded channels and report throughput, queue occupancy and latency
+    Pipeline(PipelineArgs),
+    /// Load a loopback TCP echo server on each async runtime and report requests/sec and latency
+    Echo(EchoArgs),
+    /// Submit work at fixed arrival rates and report latency against offered load
+    Load(LoadArgs),
+    /// Measure one library and print the result as JSON (used by `threads`)
+    #[command(hide = true)]
+    MeasureOne(MeasureOneArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct RunArgs {
+    /// Number of values processed per iteration
+    #[arg(long, default_value_t = 10000)]
+    pub size: usize,
+
+    /// Timed iterations per library
+    #[arg(long, default_value_t = 5)]
+    pub iterations: usize,
+
+    /// Multiply-add rounds of CPU work per value
+    #[arg(long, default_value_t = DEFAULT_WORK_UNITS)]
+    pub work_units: u64,
+
+    /// Target CPU time per value, e.g. `50ns`, `20us`, `10ms`; converted to
+    /// work units by calibrating on this machine, overriding --work-units
+    #[arg(long, value_parser = parse_duration)]
+    pub task_cost: Option<Duration>,
+
+    /// Values handed to each spawned task
+    #[arg(long, default_value_t = 1)]
+    pub chunk_size: usize,
+
+    /// What each value costs: CPU work only, or a simulated I/O wait followed by the CPU work
+    #[arg(long, value_enum, default_value_t = WorkloadKind::Cpu)]
+    pub workload: WorkloadKind,
+
+    /// Distribution of the per-value wait with `--workload io`: `fixed:1ms`,
+    /// `uniform:100us..2ms` or `exp:200us` (exponential with that mean)
+    #[arg(long, value_parser = parse_latency, default_value = "exp:200us")]
+    pub latency: Latency,
+
+    /// How tasks hand their processed values back; libraries that can't collect
+    /// this way are skipped
+    #[arg(long, value_enum, default_value_t = Collection::Mutex)]
+    pub collection: Collection,
+
+    /// Untimed warm-up iterations run before sampling each library
+    #[arg(long, default_value_t = 1)]
+    pub warmup: usize,
+
+    /// Keep warming up until timings stabilise instead of a fixed --warmup count
+    #[arg(long)]
+    pub auto_warmup: bool,
+
+    /// Upper bound on warm-up iterations in --auto-warmup mode
+    #[arg(long, default_value_t = 20)]
+    pub max_warmup: usize,
+
+    /// Coefficient of variation over the last 3 warm-up runs that counts as stable
+    #[arg(long, default_value_t = 0.05)]
+    pub warmup_cv: f64,
+
+    /// Seed for the input data; random if omitted, and always recorded in the report
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    #[command(flatten)]
+    pub selection: Selection,
+
+    /// How the report is rendered
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+    pub format: OutputFormat,
+
+    /// Write the report to this file instead of stdout
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+
+    /// Phase of each iteration the statistics and rankings are computed from
+    #[arg(long, value_enum, default_value_t = Phase::Workload)]
+    pub phase: Phase,
+
+    /// Figure the ranking tables are ordered by
+    #[arg(long, value_enum, default_value_t = RankBy::Best)]
+    pub rank_by: RankBy,
+
+    /// Test used to decide whether two libraries actually differ
+    #[arg(long, value_enum, default_value_t = SignificanceTest::MannWhitney)]
+    pub test: SignificanceTest,
+
+    /// Significance level for --test
+    #[arg(long, default_value_t = 0.05)]
+    pub alpha: f64,
+
+    /// Check every library's output against a sequential run; failures are left out of the rankings
+    #[arg(long, value_enum, default_value_t = VerifyMode::Full)]
+    pub verify: VerifyMode,
+
+    /// Record when every spawned task was queued, first ran and finished, and report
+    /// queue wait and execution time separately (adds a lock per task)
+    #[arg(long)]
+    pub task_timing: bool,
+
+    /// Record which thread processed every item of each library's first timed iteration,
+    /// and write it to this file as a Chrome trace (open in Perfetto or chrome://tracing)
+    #[arg(long)]
+    pub trace: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum WorkloadKind {
+    Cpu,
+    // Each value awaits a timer (or blocks its thread) before the CPU work;
+    // libraries without a simulated I/O variant are skipped
+    Io,
+}
+
+impl RunArgs {
+    // Simulated I/O latency, if this run waits at all
+    pub fn latency(&self) -> Option<Latency> {
+        (self.workload == WorkloadKind::Io).then_some(self.latency)
+    }
+
+    pub fn warmup(&self) -> Warmup {
+        if self.auto_warmup {
+            Warmup::Auto { max_iterations: self.max_warmup, window: 3, cv_threshold: self.warmup_cv }
+        } else {
+            Warmup::Fixed { iterations: self.warmup }
+        }
+    }
+}
+
+impl Default for RunArgs {
+    fn default() -> Self {
+        RunArgs {
+            size: 10000,
+            iterations: 5,
+            work_units: DEFAULT_WORK_UNITS,
+            task_cost: None,
+            chunk_size: 1,
+            workload: WorkloadKind::Cpu,
+            latency: Latency::Exponential { mean: Duration::from_micros(200) },
+            collection: Collection::Mutex,
+            warmup: 1,
+            auto_warmup: false,
+            max_warmup: 20,
+            warmup_cv: 0.05,
+            seed: None,
+            selection: Selection::default(),
+            format: OutputFormat::Table,
+            output: None,
+            phase: Phase::Workload,
+            rank_by: RankBy::Best,
+            test: SignificanceTest::MannWhitney,
+            alpha: 0.05,
+            verify: VerifyMode::Full,
+            task_timing: false,
+            trace: None,
+        }
+    }
+}
+
+// A number with a unit: ns, us (or µs), ms or s
+pub fn parse_duration(text: &str) -> Result<Duration, String> {
+    let text = text.trim();
+    let split = text.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: f64 = number.parse().map_err(|_| format!("invalid duration `{}`", text))?;
+    let nanos_per_unit = match unit.trim() {
+        "ns" => 1.0,
+        "us" | "µs" => 1e3,
+        "ms" => 1e6,
+        "s" => 1e9,
+        _ => return Err(format!("duration `{}` needs a unit: ns, us, ms or s", text)),
+    };
+    Ok(Duration::from_nanos((number * nanos_per_unit).round() as u64))
+}
+
+// `fixed:<duration>`, `uniform:<min>..<max>` or `exp:<mean>`; a bare duration is fixed
+pub fn parse_latency(text: &str) -> Result<Latency, String> {
+    let (kind, params) = text.split_once(':').unwrap_or(("fixed", text));
+    match kind.trim() {
+        "fixed" => Ok(Latency::Fixed { delay: parse_duration(params)? }),
+        "uniform" => {
+            let (min, max) = params.split_once("..")
+                .ok_or_else(|| format!("uniform latency `{}` needs a range like 100us..2ms", text))?;
+            let (min, max) = (parse_duration(min)?, parse_duration(max)?);
+            if min > max {
+                return Err(format!("latency range `{}` is backwards", text));
+            }
+            Ok(Latency::Uniform { min, max })
+        }
+        "exp" | "exponential" => Ok(Latency::Exponential { mean: parse_duration(params)? }),
+        other => Err(format!("unknown latency distribution `{}`: use fixed, uniform or exp", other)),
+    }
+}
+
+// A chunk size the sweep can multiply an item's cost by: from 1 up to u32::MAX
+pub fn parse_chunk_size(text: &str) -> Result<usize, String> {
+    match text.trim().parse::<u32>() {
+        Ok(0) | Err(_) => Err(format!("invalid chunk size `{}`: use a number from 1 to {}", text, u32::MAX)),
+        Ok(size) => Ok(size as usize),
+    }
+}
+
+// Which registered libraries take part. Library names are matched case-insensitively.
+#[derive(Args, Debug, Default)]
+pub struct Selection {
+    /// Only these libraries, e.g. `--only tokio,rayon`
+    #[arg(long, value_delimiter = ',')]
+    pub only: Vec<String>,
+
+    /// Skip these libraries
+    #[arg(long, value_delimiter = ',')]
+    pub exclude: Vec<String>,
+
+    /// Only these categories, e.g. `--category parallel`
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub category: Vec<Category>,
+}
+
+impl Selection {
+    // The sequential baseline is always included so speedups can be computed
+    pub fn includes_category(&self, category: Category) -> bool {
+        category == Category::Sequential || self.category.is_empty() || self.category.contains(&category)
+    }
+
+    pub fn includes(&self, adapter: &dyn RuntimeAdapter) -> bool {
+        let name = adapter.name();
+        if adapter.category() == Category::Sequential {
+            return true;
+        }
+        self.includes_category(adapter.category())
+            && (self.only.is_empty() || self.only.iter().any(|n| n.eq_ignore_ascii_case(name)))
+            && !self.exclude.iter().any(|n| n.eq_ignore_ascii_case(name))
+    }
+
+    // Names given to --only/--exclude that don't match any registered library
+    pub fn unknown_names(&self, adapters: &[Box<dyn RuntimeAdapter>]) -> Vec<String> {
+        self.only
+            .iter()
+            .chain(&self.exclude)
+            .filter(|n| !adapters.iter().any(|a| n.eq_ignore_ascii_case(a.name())))
+            .cloned()
+            .collect()
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct SweepArgs {
+    /// Number of values processed per iteration
+    #[arg(long, default_value_t = 2000)]
+    pub size: usize,
+
+    /// Timed iterations per library at every point
+    #[arg(long, default_value_t = 3)]
+    pub iterations: usize,
+
+    /// Untimed warm-up iterations before every point
+    #[arg(long, default_value_t = 1)]
+    pub warmup: usize,
+
+    /// CPU time per value at each step, calibrated into work units
+    #[arg(long, value_delimiter = ',', value_parser = parse_duration, default_value = "50ns,200ns,1us,5us,20us")]
+    pub costs: Vec<Duration>,
+
+    /// Values per spawned task at each step
+    #[arg(long, value_delimiter = ',', value_parser = parse_chunk_size, default_value = "1,4,16,64,256")]
+    pub chunk_sizes: Vec<usize>,
+
+    /// Parallel efficiency the crossover summary looks for
+    #[arg(long, default_value_t = 0.8)]
+    pub efficiency: f64,
+
+    /// Seed for the input data; random if omitted
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    #[command(flatten)]
+    pub selection: Selection,
+
+    /// Check every point's output against a sequential run; failing points are dropped
+    #[arg(long, value_enum, default_value_t = VerifyMode::Full)]
+    pub verify: VerifyMode,
+
+    /// How the report is rendered
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+    pub format: OutputFormat,
+
+    /// Write the report to this file instead of stdout
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+}
+
+// Data sizes to scale over: a log-scale range like `1e2..1e7` or a list like `100,5000,20000`
+#[derive(Clone, Debug)]
+pub enum SizeSpec {
+    Range(usize, usize),
+    List(Vec<usize>),
+}
+
+impl SizeSpec {
+    // Every size in order; a range gets `points_per_decade` sizes between each power of ten
+    pub fn sizes(&self, points_per_decade: usize) -> Vec<usize> {
+        match self {
+            SizeSpec::List(sizes) => sizes.clone(),
+            SizeSpec::Range(low, high) => {
+                let steps = points_per_decade.max(1) as f64;
+                let (log_low, log_high) = ((*low as f64).log10(), (*high as f64).log10());
+                let count = ((log_high - log_low) * steps).round() as usize;
+                let mut sizes: Vec<usize> = (0..=count)
+                    .map(|i| 10f64.powf(log_low + i as f64 / steps).round() as usize)
+                    .collect();
+                sizes.dedup();
+                sizes
+            }
+        }
+    }
+}
+
+pub fn parse_sizes(text: &str) -> Result<SizeSpec, String> {
+    let number = |s: &str| -> Result<usize, String> {
+        let value: f64 = s.trim().parse().map_err(|_| format!("invalid size `{}`", s))?;
+        if value < 1.0 {
+            return Err(format!("size `{}` must be at least 1", s));
+        }
+        Ok(value.round() as usize)
+    };
+    match text.split_once("..") {
+        Some((low, high)) => {
+            let (low, high) = (number(low)?, number(high)?);
+            if low > high {
+                return Err(format!("size range `{}` is backwards", text));
+            }
+            Ok(SizeSpec::Range(low, high))
+        }
+        None => Ok(SizeSpec::List(text.split(',').map(number).collect::<Result<_, _>>()?)),
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct ScaleArgs {
+    /// Data sizes, either a log-scale range such as `1e2..1e7` or a comma-separated list
+    #[arg(long, value_parser = parse_sizes, default_value = "1e2..1e5")]
+    pub sizes: SizeSpec,
+
+    /// Sizes per power of ten when --sizes is a range
+    #[arg(long, default_value_t = 1)]
+    pub points_per_decade: usize,
+
+    /// Timed iterations per library at every size
+    #[arg(long, default_value_t = 3)]
+    pub iterations: usize,
+
+    /// Untimed warm-up iterations before every size
+    #[arg(long, default_value_t = 1)]
+    pub warmup: usize,
+
+    /// Multiply-add rounds of CPU work per value
+    #[arg(long, default_value_t = DEFAULT_WORK_UNITS)]
+    pub work_units: u64,
+
+    /// Target CPU time per value, overriding --work-units (see `run --help`)
+    #[arg(long, value_parser = parse_duration)]
+    pub task_cost: Option<Duration>,
+
+    /// Values handed to each spawned task
+    #[arg(long, default_value_t = 1)]
+    pub chunk_size: usize,
+
+    /// Seed for the input data; random if omitted
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    #[command(flatten)]
+    pub selection: Selection,
+
+    /// Check every output against a sequential run; failing sizes are dropped
+    #[arg(long, value_enum, default_value_t = VerifyMode::Full)]
+    pub verify: VerifyMode,
+
+    /// How the report is rendered
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+    pub format: OutputFormat,
+
+    /// Write the report to this file instead of stdout
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct CollectArgs {
+    /// Collection strategies to compare; the mutex is always included, as the reference
+    #[arg(long, value_enum, value_delimiter = ',', default_values_t = Collection::ALL)]
+    pub collections: Vec<Collection>,
+
+    /// Number of values processed per iteration
+    #[arg(long, default_value_t = 10000)]
+    pub size: usize,
+
+    /// Timed iterations per library and strategy
+    #[arg(long, default_value_t = 5)]
+    pub iterations: usize,
+
+    /// Untimed warm-up iterations before every library and strategy
+    #[arg(long, default_value_t = 1)]
+    pub warmup: usize,
+
+    /// Multiply-add rounds of CPU work per value
+    #[arg(long, default_value_t = DEFAULT_WORK_UNITS)]
+    pub work_units: u64,
+
+    /// Target CPU time per value, overriding --work-units (see `run --help`)
+    #[arg(long, value_parser = parse_duration)]
+    pub task_cost: Option<Duration>,
+
+    /// Values handed to each spawned task
+    #[arg(long, default_value_t = 1)]
+    pub chunk_size: usize,
+
+    /// Seed for the input data; random if omitted
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    #[command(flatten)]
+    pub selection: Selection,
+
+    /// Check every output against a sequential run; failing strategies are dropped
+    #[arg(long, value_enum, default_value_t = VerifyMode::Full)]
+    pub verify: VerifyMode,
+
+    /// How the report is rendered
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+    pub format: OutputFormat,
+
+    /// Write the report to this file instead of stdout
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct LocksArgs {
+    /// Locks to compare
+    #[arg(long, value_enum, value_delimiter = ',', default_values_t = LockKind::ALL)]
+    pub locks: Vec<LockKind>,
+
+    /// Runtimes the items run under
+    #[arg(long, value_enum, value_delimiter = ',', default_values_t = LockRuntime::ALL)]
+    pub runtimes: Vec<LockRuntime>,
+
+    /// Contention levels: how many locks the output is split over (1 is the most contended)
+    #[arg(long, value_delimiter = ',', default_value = "1,8,64")]
+    pub shards: Vec<usize>,
+
+    /// Read-lock acquisitions per item before it stores its value; RwLocks let these overlap
+    #[arg(long, default_value_t = 0)]
+    pub reads: usize,
+
+    /// Multiply-add rounds done while holding the lock for each store
+    #[arg(long, default_value_t = 0)]
+    pub hold_units: u64,
+
+    /// Number of values processed per iteration
+    #[arg(long, default_value_t = 10000)]
+    pub size: usize,
+
+    /// Timed iterations per runtime, lock and shard count
+    #[arg(long, default_value_t = 5)]
+    pub iterations: usize,
+
+    /// Untimed warm-up iterations before each of them
+    #[arg(long, default_value_t = 1)]
+    pub warmup: usize,
+
+    /// Multiply-add rounds of CPU work per value, outside the lock
+    #[arg(long, default_value_t = DEFAULT_WORK_UNITS)]
+    pub work_units: u64,
+
+    /// Target CPU time per value, overriding --work-units (see `run --help`)
+    #[arg(long, value_parser = parse_duration)]
+    pub task_cost: Option<Duration>,
+
+    /// Values handed to each spawned task
+    #[arg(long, default_value_t = 1)]
+    pub chunk_size: usize,
+
+    /// Seed for the input data; random if omitted
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Check every output against a sequential run; failing locks are dropped
+    #[arg(long, value_enum, default_value_t = VerifyMode::Full)]
+    pub verify: VerifyMode,
+
+    /// How the report is rendered
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+    pub format: OutputFormat,
+
+    /// Write the report to this file instead of stdout
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+}
+
+// A channel bound: a positive capacity, or `unbounded`
+pub fn parse_capacity(text: &str) -> Result<Option<usize>, String> {
+    let text = text.trim();
+    if text.eq_ignore_ascii_case("unbounded") {
+        return Ok(None);
+    }
+    match text.parse::<usize>() {
+        Ok(0) | Err(_) => Err(format!("invalid capacity `{}`: use a number of at least 1 or `unbounded`", text)),
+        Ok(capacity) => Ok(Some(capacity)),
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct ChannelsArgs {
+    /// Channel libraries to compare
+    #[arg(long, value_enum, value_delimiter = ',', default_values_t = ChannelKind::ALL)]
+    pub channels: Vec<ChannelKind>,
+
+    /// Shapes to run: one or many senders, one or many receivers
+    #[arg(long, value_enum, value_delimiter = ',', default_values_t = Topology::ALL)]
+    pub topologies: Vec<Topology>,
+
+    /// Channel bounds, e.g. `1,64,1024,unbounded`
+    #[arg(long, value_delimiter = ',', value_parser = parse_capacity, default_value = "1,64,1024,unbounded")]
+    pub capacities: Vec<Option<usize>>,
+
+    /// Whether the ends block on threads, await on Tokio tasks, or send blocking and receive awaiting
+    #[arg(long, value_enum, value_delimiter = ',', default_values_t = Flavor::ALL)]
+    pub flavors: Vec<Flavor>,
+
+    /// Messages sent per run, split between the senders
+    #[arg(long, default_value_t = 100000)]
+    pub messages: usize,
+
+    /// Senders in the MPSC and MPMC shapes
+    #[arg(long, default_value_t = 4)]
+    pub producers: usize,
+
+    /// Receivers in the MPMC shape
+    #[arg(long, default_value_t = 4)]
+    pub consumers: usize,
+
+    /// Timed runs per combination; the median is reported
+    #[arg(long, default_value_t = 3)]
+    pub iterations: usize,
+
+    /// Messages bounced back and forth one at a time to measure latency; 0 skips it
+    #[arg(long, default_value_t = 10000)]
+    pub round_trips: usize,
+
+    /// How the report is rendered
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+    pub format: OutputFormat,
+
+    /// Write the report to this file instead of stdout
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct PipelineArgs {
+    /// What the stage workers run on
+    #[arg(long, value_enum, value_delimiter = ',', default_values_t = PipelineRuntime::ALL)]
+    pub runtimes: Vec<PipelineRuntime>,
+
+    /// Number of stages: parse, then transforms, then aggregate
+    #[arg(long, default_value_t = 3)]
+    pub depth: usize,
+
+    /// Items each queue between stages holds before the stage feeding it has to wait
+    #[arg(long, default_value_t = 64)]
+    pub capacity: usize,
+
+    /// Workers pulling from each stage's queue
+    #[arg(long, default_value_t = 1)]
+    pub stage_workers: usize,
+
+    /// Make this stage (1-based) do --slow-factor times the work of the others
+    #[arg(long)]
+    pub slow_stage: Option<usize>,
+
+    /// How much more work the slow stage does per item
+    #[arg(long, default_value_t = 10)]
+    pub slow_factor: u64,
+
+    /// Items fed through the pipeline per run
+    #[arg(long, default_value_t = 100000)]
+    pub items: usize,
+
+    /// Timed runs per runtime
+    #[arg(long, default_value_t = 3)]
+    pub iterations: usize,
+
+    /// Untimed warm-up runs per runtime
+    #[arg(long, default_value_t = 1)]
+    pub warmup: usize,
+
+    /// Multiply-add rounds of CPU work per item in each stage
+    #[arg(long, default_value_t = DEFAULT_WORK_UNITS)]
+    pub work_units: u64,
+
+    /// Target CPU time per item in each stage, overriding --work-units (see `run --help`)
+    #[arg(long, value_parser = parse_duration)]
+    pub task_cost: Option<Duration>,
+
+    /// How often the queue lengths are sampled
+    #[arg(long, value_parser = parse_duration, default_value = "1ms")]
+    pub sample_interval: Duration,
+
+    /// Seed for the input data; random if omitted
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// How the report is rendered
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+    pub format: OutputFormat,
+
+    /// Write the report to this file instead of stdout
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct ThreadsArgs {
+    /// Worker counts to run, e.g. `1,2,4,8`; powers of two up to the logical CPU count
+    /// if omitted. A single worker is always included as the reference.
+    #[arg(long, value_delimiter = ',')]
+    pub threads: Vec<usize>,
+
+    /// Number of values processed per iteration
+    #[arg(long, default_value_t = 10000)]
+    pub size: usize,
+
+    /// Timed iterations per library at every worker count
+    #[arg(long, default_value_t = 3)]
+    pub iterations: usize,
+
+    /// Untimed warm-up iterations before every worker count
+    #[arg(long, default_value_t = 1)]
+    pub warmup: usize,
+
+    /// Multiply-add rounds of CPU work per value
+    #[arg(long, default_value_t = DEFAULT_WORK_UNITS)]
+    pub work_units: u64,
+
+    /// Target CPU time per value, overriding --work-units (see `run --help`)
+    #[arg(long, value_parser = parse_duration)]
+    pub task_cost: Option<Duration>,
+
+    /// Values handed to each spawned task
+    #[arg(long, default_value_t = 1)]
+    pub chunk_size: usize,
+
+    /// Seed for the input data; random if omitted
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    #[command(flatten)]
+    pub selection: Selection,
+
+    /// Check every output against a sequential run; failing worker counts are dropped
+    #[arg(long, value_enum, default_value_t = VerifyMode::Full)]
+    pub verify: VerifyMode,
+
+    /// How the report is rendered
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+    pub format: OutputFormat,
+
+    /// Write the report to this file instead of stdout
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+}
+
+impl ThreadsArgs {
+    pub fn thread_counts(&self) -> Vec<usize> {
+        let mut counts = if self.threads.is_empty() {
+            let cpus = num_cpus::get();
+            let mut counts: Vec<usize> = std::iter::successors(Some(1), |n| Some(n * 2)).take_while(|&n| n < cpus).collect();
+            counts.push(cpus);
+            counts
+        } else {
+            self.threads.iter().map(|&n| n.max(1)).collect()
+        };
+        counts.push(1);
+        counts.sort_unstable();
+        counts.dedup();
+        counts
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct EchoArgs {
+    /// Concurrent client connections, each sending its next message once the last one is echoed
+    #[arg(long, default_value_t = 50)]
+    pub connections: usize,
+
+    /// Bytes per message
+    #[arg(long, default_value_t = 64)]
+    pub message_size: usize,
+
+    /// How long each server is measured for, e.g. `5s`
+    #[arg(long, value_parser = parse_duration, default_value = "5s")]
+    pub duration: Duration,
+
+    /// Unrecorded load before measuring starts
+    #[arg(long, value_parser = parse_duration, default_value = "1s")]
+    pub warmup: Duration,
+
+    #[command(flatten)]
+    pub selection: Selection,
+
+    /// How the report is rendered
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+    pub format: OutputFormat,
+
+    /// Write the report to this file instead of stdout
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct LoadArgs {
+    /// Offered rates in items per second, tried in increasing order until a library can't keep up
+    #[arg(long, value_delimiter = ',', default_value = "1000,2000,5000,10000,20000,50000,100000")]
+    pub rates: Vec<f64>,
+
+    /// How arrivals are spaced at each rate
+    #[arg(long, value_enum, default_value_t = Arrivals::Poisson)]
+    pub arrivals: Arrivals,
+
+    /// How long each rate is offered for
+    #[arg(long, value_parser = parse_duration, default_value = "1s")]
+    pub duration: Duration,
+
+    /// Multiply-add rounds of CPU work per item
+    #[arg(long, default_value_t = DEFAULT_WORK_UNITS)]
+    pub work_units: u64,
+
+    /// Target CPU time per item, overriding --work-units (see `run --help`)
+    #[arg(long, value_parser = parse_duration)]
+    pub task_cost: Option<Duration>,
+
+    /// Seed for arrival times and item values; random if omitted
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    #[command(flatten)]
+    pub selection: Selection,
+
+    /// How the report is rendered
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+    pub format: OutputFormat,
+
+    /// Write the report to this file instead of stdout
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct MeasureOneArgs {
+    pub library: String,
+    #[arg(long)]
+    pub size: usize,
+    #[arg(long)]
+    pub iterations: usize,
+    #[arg(long)]
+    pub warmup: usize,
+    #[arg(long)]
+    pub seed: u64,
+    #[arg(long)]
+    pub work_units: u64,
+    #[arg(long)]
+    pub ns_per_unit: f64,
+    #[arg(long)]
+    pub chunk_size: usize,
+    #[arg(long, value_enum)]
+    pub verify: VerifyMode,
+}
+
+#[derive(Args, Debug)]
+pub struct CompareArgs {
+    /// JSON report to compare against
+    pub baseline: PathBuf,
+
+    /// JSON report being evaluated
+    pub candidate: PathBuf,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations_need_a_unit() {
+        let cases = [
+            ("250ns", Ok(Duration::from_nanos(250))),
+            ("1.5us", Ok(Duration::from_nanos(1500))),
+            ("2µs", Ok(Duration::from_micros(2))),
+            (" 3ms ", Ok(Duration::from_millis(3))),
+            ("0.25s", Ok(Duration::from_millis(250))),
+            ("10", Err(())),
+            ("5min", Err(())),
+            ("ms", Err(())),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(parse_duration(text).map_err(|_| ()), expected, "parsing {:?}", text);
+        }
+    }
+
+    #[test]
+    fn size_specs_expand_to_sizes() {
+        let cases: [(&str, usize, Option<Vec<usize>>); 8] = [
+            ("100,5000,20000", 4, Some(vec![100, 5000, 20000])),
+            ("1e2..1e3", 4, Some(vec![100, 178, 316, 562, 1000])),
+            // More steps than integers in the range: repeats are dropped
+            ("1..10", 20, Some((1..=10).collect())),
+            ("100..100", 5, Some(vec![100])),
+            ("1e3..1e2", 4, None),
+            ("0..100", 4, None),
+            ("0.4", 4, None),
+            ("ten", 4, None),
+        ];
+        for (text, points_per_decade, expected) in cases {
+            let sizes = parse_sizes(text).ok().map(|spec| spec.sizes(points_per_decade));
+            assert_eq!(sizes, expected, "parsing {:?}", text);
+        }
+    }
+
+    #[test]
+    fn chunk_sizes_fit_in_a_u32() {
+        let cases = [("1", Ok(1)), ("256", Ok(256)), ("4294967295", Ok(u32::MAX as usize)), ("0", Err(())),
+                     ("4294967296", Err(())), ("-1", Err(())), ("many", Err(()))];
+        for (text, expected) in cases {
+            assert_eq!(parse_chunk_size(text).map_err(|_| ()), expected, "parsing {:?}", text);
+        }
+    }
+
+    #[test]
+    fn capacities_are_positive_or_unbounded() {
+        let cases = [("1", Ok(Some(1))), (" 1024 ", Ok(Some(1024))), ("unbounded", Ok(None)), ("Unbounded", Ok(None)),
+                     ("0", Err(())), ("-4", Err(())), ("", Err(())), ("infinite", Err(()))];
+        for (text, expected) in cases {
+            assert_eq!(parse_capacity(text).map_err(|_| ()), expected, "parsing {:?}", text);
+        }
+    }
+}
@@ -0,0 +1,246 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::ops::Range;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+use thread_local::ThreadLocal;
+
+// How spawned tasks hand their processed values back
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum Collection {
+    // One shared Vec behind a Mutex, locked for every value
+    #[default]
+    Mutex,
+    // One AtomicU32 per value
+    Atomics,
+    // Each task returns its chunk, gathered from the join handles
+    JoinHandles,
+    // Every value sent over a channel and drained at the end
+    Channel,
+    // Each task fills its own slice of the output, copied in under one lock when it finishes
+    Slices,
+    // Values appended to a buffer owned by the worker thread, handed back as each task finishes
+    ThreadBuffers,
+}
+
+impl Collection {
+    pub const ALL: [Collection; 6] = [
+        Collection::Mutex,
+        Collection::Atomics,
+        Collection::JoinHandles,
+        Collection::Channel,
+        Collection::Slices,
+        Collection::ThreadBuffers,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Collection::Mutex => "mutex",
+            Collection::Atomics => "atomics",
+            Collection::JoinHandles => "join-handles",
+            Collection::Channel => "channel",
+            Collection::Slices => "slices",
+            Collection::ThreadBuffers => "thread-buffers",
+        }
+    }
+}
+
+// One worker thread's values, pushed without a lock
+type ThreadBuffer = RefCell<Vec<(usize, u32)>>;
+
+enum Storage {
+    Mutex(Mutex<Vec<u32>>),
+    Atomics(Vec<AtomicU32>),
+    JoinHandles(Mutex<Vec<(usize, Vec<u32>)>>),
+    Channel(flume::Sender<(usize, u32)>, flume::Receiver<(usize, u32)>),
+    Slices(Mutex<Vec<u32>>),
+    // Each thread's buffer, and the buffers handed back as tasks finish
+    ThreadBuffers(Box<ThreadLocal<ThreadBuffer>>, Mutex<Vec<Vec<(usize, u32)>>>),
+}
+
+// Where the tasks of one run put their processed values, shared between them
+pub struct Results {
+    len: usize,
+    storage: Storage,
+}
+
+// What a task gives back through its join handle: its chunk with
+// `Collection::JoinHandles`, nothing otherwise
+pub struct Returned(Option<(usize, Vec<u32>)>);
+
+impl Results {
+    // Room for `len` values, collected with `collection`
+    pub fn new(len: usize, collection: Collection) -> Self {
+        let storage = match collection {
+            Collection::Mutex => Storage::Mutex(Mutex::new(vec![0; len])),
+            Collection::Atomics => Storage::Atomics((0..len).map(|_| AtomicU32::new(0)).collect()),
+            Collection::JoinHandles => Storage::JoinHandles(Mutex::new(Vec::new())),
+            Collection::Channel => {
+                let (sender, receiver) = flume::unbounded();
+                Storage::Channel(sender, receiver)
+            }
+            Collection::Slices => Storage::Slices(Mutex::new(vec![0; len])),
+            Collection::ThreadBuffers => Storage::ThreadBuffers(Box::default(), Mutex::new(Vec::new())),
+        };
+        Results { len, storage }
+    }
+
+    // Start collecting the values of the task that processes `range`
+    pub fn task(&self, range: Range<usize>) -> TaskSink<'_> {
+        assert!(range.start <= range.end && range.end <= self.len);
+        let local = match self.storage {
+            Storage::Slices(_) => vec![0; range.len()],
+            _ => Vec::new(),
+        };
+        TaskSink { results: self, start: range.start, local }
+    }
+
+    // Hand over what a task returned through its join handle
+    pub fn returned(&self, returned: Returned) {
+        if let (Storage::JoinHandles(chunks), Some(chunk)) = (&self.storage, returned.0) {
+            chunks.lock().unwrap().push(chunk);
+        }
+    }
+
+    // The processed values in input order, once every task has finished
+    pub fn take(&self) -> Vec<u32> {
+        match &self.storage {
+            Storage::Mutex(values) | Storage::Slices(values) => std::mem::take(&mut *values.lock().unwrap()),
+            Storage::Atomics(values) => values.iter().map(|v| v.load(Ordering::Relaxed)).collect(),
+            Storage::JoinHandles(chunks) => {
+                let mut chunks = std::mem::take(&mut *chunks.lock().unwrap());
+                chunks.sort_unstable_by_key(|(start, _)| *start);
+                chunks.into_iter().flat_map(|(_, values)| values).collect()
+            }
+            Storage::Channel(_, receiver) => {
+                let mut values = vec![0; self.len];
+                for (idx, value) in receiver.try_iter() {
+                    values[idx] = value;
+                }
+                values
+            }
+            Storage::ThreadBuffers(_, handed_back) => {
+                let mut values = vec![0; self.len];
+                for buffer in handed_back.lock().unwrap().drain(..) {
+                    for (idx, value) in buffer {
+                        values[idx] = value;
+                    }
+                }
+                values
+            }
+        }
+    }
+}
+
+// One task's end of a `Results`
+pub struct TaskSink<'a> {
+    results: &'a Results,
+    start: usize,
+    // This task's values: pushed in order for `Collection::JoinHandles`, or a
+    // private copy of its part of the output for `Collection::Slices`
+    local: Vec<u32>,
+}
+
+impl TaskSink<'_> {
+    pub fn put(&mut self, idx: usize, value: u32) {
+        match &self.results.storage {
+            Storage::Mutex(values) => {
+                let mut results_guard = values.lock().unwrap();
+                results_guard[idx] = value;
+            }
+            Storage::Atomics(values) => values[idx].store(value, Ordering::Relaxed),
+            Storage::JoinHandles(_) => self.local.push(value),
+            Storage::Channel(sender, _) => sender.send((idx, value)).unwrap(),
+            Storage::Slices(_) => self.local[idx - self.start] = value,
+            Storage::ThreadBuffers(buffers, _) => buffers.get_or_default().borrow_mut().push((idx, value)),
+        }
+    }
+
+    // Done with this task; return the result from the task so it reaches `Results::returned`
+    pub fn finish(self) -> Returned {
+        match &self.results.storage {
+            Storage::JoinHandles(_) => return Returned(Some((self.start, self.local))),
+            // One lock per task, to copy its slice into the output
+            Storage::Slices(values) => {
+                values.lock().unwrap()[self.start..self.start + self.local.len()].copy_from_slice(&self.local);
+            }
+            // One lock per task, to hand its thread's buffer to `take`. Another task
+            // that ran on the same thread may have left values in it too; they carry
+            // their own indices, so whichever task hands them over is fine.
+            Storage::ThreadBuffers(buffers, handed_back) => {
+                let buffer = buffers.get_or_default().take();
+                if !buffer.is_empty() {
+                    handed_back.lock().unwrap().push(buffer);
+                }
+            }
+            _ => {}
+        }
+        Returned(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapter::chunk_ranges;
+
+    fn expected() -> Vec<u32> {
+        (0..10).map(|idx| idx * 7 + 1).collect()
+    }
+
+    // Chunks of 3 over 10 values leave a short last chunk, and the tasks are
+    // started last chunk first on their own threads
+    #[test]
+    fn every_strategy_keeps_input_order_with_ragged_chunks() {
+        let expected = expected();
+        for collection in Collection::ALL {
+            let results = Results::new(expected.len(), collection);
+            let mut ranges: Vec<_> = chunk_ranges(expected.len(), 3).collect();
+            ranges.reverse();
+            std::thread::scope(|scope| {
+                let handles: Vec<_> = ranges
+                    .into_iter()
+                    .map(|range| {
+                        let (results, expected) = (&results, &expected);
+                        scope.spawn(move || {
+                            let mut sink = results.task(range.clone());
+                            for idx in range {
+                                sink.put(idx, expected[idx]);
+                            }
+                            sink.finish()
+                        })
+                    })
+                    .collect();
+                for handle in handles {
+                    results.returned(handle.join().unwrap());
+                }
+            });
+            assert_eq!(results.take(), expected, "{}", collection.as_str());
+        }
+    }
+
+    // Async tasks on one worker thread take turns, so several sinks can be live on
+    // the same thread at once
+    #[test]
+    fn sinks_sharing_a_thread_keep_their_values() {
+        let expected = expected();
+        for collection in Collection::ALL {
+            let results = Results::new(expected.len(), collection);
+            let mut sinks: Vec<_> = chunk_ranges(expected.len(), 3).map(|range| (range.clone(), results.task(range))).collect();
+            for offset in 0..3 {
+                for (range, sink) in &mut sinks {
+                    let idx = range.start + offset;
+                    if idx < range.end {
+                        sink.put(idx, expected[idx]);
+                    }
+                }
+            }
+            for (_, sink) in sinks.into_iter().rev() {
+                results.returned(sink.finish());
+            }
+            assert_eq!(results.take(), expected, "{}", collection.as_str());
+        }
+    }
+}
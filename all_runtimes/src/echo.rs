@@ -0,0 +1,198 @@
+use futures::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use hdrhistogram::Histogram;
+use serde::{Deserialize, Serialize};
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::time::{Duration, Instant};
+
+use crate::adapter::registry;
+use crate::cli::EchoArgs;
+use crate::results::{nanos, Category, RunMetadata};
+
+// Largest round trip the histogram can hold; anything slower is clamped to it
+const MAX_LATENCY_NS: u64 = 60_000_000_000;
+
+// Requests answered within one power-of-two latency band
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LatencyBucket {
+    // Round trips at or below this, and above the previous bucket's bound
+    #[serde(with = "nanos")]
+    pub upper: Duration,
+    pub count: u64,
+}
+
+// One runtime's echo server under load
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EchoResult {
+    pub category: Category,
+    pub library: String,
+    // Round trips completed inside the measured window
+    pub requests: u64,
+    // Connections that failed, were dropped while being accepted, or got back
+    // something other than what they sent
+    pub errors: u64,
+    pub requests_per_sec: f64,
+    #[serde(with = "nanos")]
+    pub p50: Duration,
+    #[serde(with = "nanos")]
+    pub p99: Duration,
+    #[serde(with = "nanos")]
+    pub p999: Duration,
+    #[serde(with = "nanos")]
+    pub max: Duration,
+    pub histogram: Vec<LatencyBucket>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EchoBenchmark {
+    pub metadata: RunMetadata,
+    pub connections: usize,
+    pub message_size: usize,
+    #[serde(with = "nanos")]
+    pub duration: Duration,
+    #[serde(with = "nanos")]
+    pub warmup: Duration,
+    pub results: Vec<EchoResult>,
+}
+
+// Pause after a failed accept before trying again, so a run of errors doesn't
+// spin the accept loop
+pub const ACCEPT_BACKOFF: Duration = Duration::from_millis(1);
+
+// Whether a failed accept only lost that one connection and the listener can
+// carry on. Anything else ends the server with the error.
+pub fn accept_is_transient(err: &io::Error) -> bool {
+    matches!(err.kind(), io::ErrorKind::ConnectionAborted | io::ErrorKind::ConnectionReset | io::ErrorKind::Interrupted)
+}
+
+// Write back everything read from `stream` until the peer hangs up. Shared by
+// the runtimes built on futures-io (async-std, smol).
+pub async fn echo_connection<S: AsyncRead + AsyncWrite + Unpin>(mut stream: S) {
+    let mut buf = [0u8; 4096];
+    loop {
+        match stream.read(&mut buf).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => {
+                if stream.write_all(&buf[..n]).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+// Start every selected runtime's echo server in turn and load it from plain
+// blocking client threads, so the load generator is the same for all of them.
+// Libraries without an echo server are left out.
+pub fn run_echo(args: &EchoArgs) -> io::Result<EchoBenchmark> {
+    let adapters = registry().into_iter().filter(|adapter| args.selection.includes(adapter.as_ref()));
+
+    let mut results = Vec::new();
+    for mut adapter in adapters {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        listener.set_nonblocking(true)?;
+        let addr = listener.local_addr()?;
+        let (stop, stopped) = flume::bounded(1);
+
+        adapter.build();
+        let Some(serve) = adapter.echo_server() else {
+            adapter.teardown();
+            continue;
+        };
+        eprintln!("Loading {} echo server with {} connections...", adapter.name(), args.connections);
+        let (served, (histogram, errors)) = std::thread::scope(|scope| {
+            let load = scope.spawn(move || {
+                let outcome = generate_load(addr, args);
+                let _ = stop.send(());
+                outcome
+            });
+            let served = serve(listener, stopped);
+            (served, load.join().unwrap())
+        });
+        adapter.teardown();
+        let dropped = served?;
+
+        let quantile = |q: f64| Duration::from_nanos(histogram.value_at_quantile(q));
+        results.push(EchoResult {
+            category: adapter.category(),
+            library: adapter.name().to_string(),
+            requests: histogram.len(),
+            errors: errors + dropped,
+            requests_per_sec: histogram.len() as f64 / args.duration.as_secs_f64(),
+            p50: quantile(0.5),
+            p99: quantile(0.99),
+            p999: quantile(0.999),
+            max: Duration::from_nanos(histogram.max()),
+            histogram: histogram
+                .iter_log(1000, 2.0)
+                .filter(|step| step.count_since_last_iteration() > 0)
+                .map(|step| LatencyBucket {
+                    upper: Duration::from_nanos(step.value_iterated_to()),
+                    count: step.count_since_last_iteration(),
+                })
+                .collect(),
+        });
+    }
+
+    Ok(EchoBenchmark {
+        metadata: RunMetadata::collect(),
+        connections: args.connections,
+        message_size: args.message_size,
+        duration: args.duration,
+        warmup: args.warmup,
+        results,
+    })
+}
+
+// Closed-loop clients, one thread per connection, each sending a message and
+// waiting for it to come back. Round trips finishing after the warm-up and
+// before the deadline are recorded.
+fn generate_load(addr: SocketAddr, args: &EchoArgs) -> (Histogram<u64>, u64) {
+    let start = Instant::now();
+    let measure_from = start + args.warmup;
+    let deadline = measure_from + args.duration;
+
+    let clients: Vec<_> = (0..args.connections.max(1))
+        .map(|client| {
+            let message: Vec<u8> = (0..args.message_size.max(1)).map(|i| (client + i) as u8).collect();
+            std::thread::spawn(move || {
+                let mut histogram = Histogram::<u64>::new_with_bounds(1, MAX_LATENCY_NS, 3).unwrap();
+                let Ok(mut stream) = TcpStream::connect(addr) else {
+                    return (histogram, 1);
+                };
+                if stream.set_nodelay(true).is_err() {
+                    return (histogram, 1);
+                }
+
+                let mut reply = vec![0u8; message.len()];
+                loop {
+                    let sent = Instant::now();
+                    if sent >= deadline {
+                        break;
+                    }
+                    let round_trip = stream.write_all(&message).and_then(|_| stream.read_exact(&mut reply));
+                    if round_trip.is_err() || reply != message {
+                        return (histogram, 1);
+                    }
+                    if sent >= measure_from {
+                        histogram.saturating_record(sent.elapsed().as_nanos() as u64);
+                    }
+                }
+                (histogram, 0)
+            })
+        })
+        .collect();
+
+    let mut histogram = Histogram::<u64>::new_with_bounds(1, MAX_LATENCY_NS, 3).unwrap();
+    let mut errors = 0;
+    for client in clients {
+        // A client that panicked lost its connection like any other failure
+        let Ok((own, failed)) = client.join() else {
+            errors += 1;
+            continue;
+        };
+        histogram.add(own).unwrap();
+        errors += failed;
+    }
+    (histogram, errors)
+}
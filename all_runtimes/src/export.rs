@@ -0,0 +1,580 @@
+use clap::ValueEnum;
+use serde::Serialize;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::channels::ChannelMatrix;
+use crate::echo::EchoBenchmark;
+use crate::locks::LockComparison;
+use crate::openloop::OpenLoop;
+use crate::pipeline::PipelineBenchmark;
+use crate::results::{BenchmarkRun, Category};
+use crate::sweep::{CollectionMatrix, Scaling, Sweep, ThreadScaling};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Table,
+    Json,
+    Csv,
+}
+
+// Where a report goes: the given file, or stdout
+pub fn open_output(path: Option<&Path>) -> Result<Box<dyn Write>, Box<dyn Error>> {
+    Ok(match path {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(io::stdout().lock()),
+    })
+}
+
+// Write one subcommand's report in `format`: a table through `table`, pretty JSON
+// straight from the model, or CSV through `csv`. `what` names the report in the
+// note printed once it went to a file.
+pub fn emit<T: Serialize>(value: &T, format: OutputFormat, output: Option<&Path>, what: &str,
+                          table: fn(&T, &mut dyn Write) -> io::Result<()>,
+                          csv: fn(&T, &mut dyn Write) -> io::Result<()>) -> Result<(), Box<dyn Error>> {
+    let mut out = open_output(output)?;
+    match format {
+        OutputFormat::Table => table(value, &mut out)?,
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut out, value)?;
+            writeln!(out)?;
+        }
+        OutputFormat::Csv => csv(value, &mut out)?,
+    }
+    out.flush()?;
+
+    if let Some(path) = output {
+        eprintln!("Wrote {:?} {} to {}", format, what, path.display());
+    }
+    Ok(())
+}
+
+// The whole run (metadata, workload, every result with its samples) as pretty JSON
+pub fn write_json(run: &BenchmarkRun, out: &mut dyn Write) -> io::Result<()> {
+    serde_json::to_writer_pretty(&mut *out, run)?;
+    writeln!(out)
+}
+
+// One CSV row per sample, with the run parameters and per-library statistics
+// repeated on every row so the file can be filtered or diffed line by line
+#[derive(Serialize)]
+struct CsvRow<'a> {
+    started_at_unix: u64,
+    workload: &'a str,
+    // Empty for CPU-bound runs
+    latency: String,
+    collection: &'static str,
+    data_size: usize,
+    iterations: usize,
+    work_units: u64,
+    ns_per_unit: f64,
+    seed: u64,
+    category: Category,
+    library: &'a str,
+    threads: usize,
+    unit: &'static str,
+    best_time: u64,
+    avg_time: u64,
+    median: u64,
+    p90: u64,
+    p99: u64,
+    max: u64,
+    std_dev: u64,
+    coefficient_of_variation: f64,
+    median_ci95_low: u64,
+    median_ci95_high: u64,
+    verified: bool,
+    // Empty unless the run had --task-timing
+    queue_wait_p50: Option<u64>,
+    queue_wait_p99: Option<u64>,
+    execution_p50: Option<u64>,
+    execution_p99: Option<u64>,
+    phase: &'static str,
+    sample: usize,
+    time: u64,
+    startup_time: u64,
+    workload_time: u64,
+    shutdown_time: u64,
+}
+
+pub fn write_csv(run: &BenchmarkRun, out: &mut dyn Write) -> io::Result<()> {
+    let mut writer = csv::Writer::from_writer(out);
+
+    let ns = |d: Duration| d.as_nanos() as u64;
+
+    for result in &run.results {
+        let stats = &result.stats;
+        for (sample, time) in result.all_times.iter().enumerate() {
+            writer.serialize(CsvRow {
+                started_at_unix: run.metadata.started_at_unix,
+                workload: &result.workload.name,
+                latency: result.workload.latency.map(|l| l.to_string()).unwrap_or_default(),
+                collection: result.workload.collection.as_str(),
+                data_size: result.workload.data_size,
+                iterations: result.workload.iterations,
+                work_units: result.workload.cost.units,
+                ns_per_unit: result.workload.cost.ns_per_unit,
+                seed: result.workload.seed,
+                category: result.category,
+                library: &result.library,
+                threads: result.threads,
+                unit: "ns",
+                best_time: ns(result.best_time),
+                avg_time: ns(result.avg_time),
+                median: ns(stats.median),
+                p90: ns(stats.p90),
+                p99: ns(stats.p99),
+                max: ns(stats.max),
+                std_dev: ns(stats.std_dev),
+                coefficient_of_variation: stats.coefficient_of_variation,
+                median_ci95_low: ns(stats.median_ci95.0),
+                median_ci95_high: ns(stats.median_ci95.1),
+                verified: result.verification.passed,
+                queue_wait_p50: result.task_times.as_ref().map(|t| ns(t.queue_wait.p50)),
+                queue_wait_p99: result.task_times.as_ref().map(|t| ns(t.queue_wait.p99)),
+                execution_p50: result.task_times.as_ref().map(|t| ns(t.execution.p50)),
+                execution_p99: result.task_times.as_ref().map(|t| ns(t.execution.p99)),
+                phase: result.workload.phase.as_str(),
+                sample,
+                time: ns(*time),
+                startup_time: result.phases.startup.get(sample).copied().map_or(0, ns),
+                workload_time: result.phases.workload.get(sample).copied().map_or(0, ns),
+                shutdown_time: result.phases.shutdown.get(sample).copied().map_or(0, ns),
+            })?;
+        }
+    }
+
+    writer.flush()
+}
+
+// One CSV row per sweep point
+#[derive(Serialize)]
+struct SweepRow<'a> {
+    started_at_unix: u64,
+    data_size: usize,
+    iterations: usize,
+    seed: u64,
+    category: Category,
+    library: &'a str,
+    unit: &'static str,
+    work_units: u64,
+    item_time: u64,
+    chunk_size: Option<usize>,
+    task_time: u64,
+    median: u64,
+    speedup: f64,
+    efficiency: f64,
+}
+
+pub fn write_sweep_csv(sweep: &Sweep, out: &mut dyn Write) -> io::Result<()> {
+    let mut writer = csv::Writer::from_writer(out);
+
+    let ns = |d: Duration| d.as_nanos() as u64;
+
+    for point in &sweep.points {
+        writer.serialize(SweepRow {
+            started_at_unix: sweep.metadata.started_at_unix,
+            data_size: sweep.data_size,
+            iterations: sweep.iterations,
+            seed: sweep.seed,
+            category: point.category,
+            library: &point.library,
+            unit: "ns",
+            work_units: point.work_units,
+            item_time: ns(point.item_time),
+            chunk_size: point.chunk_size,
+            task_time: ns(point.task_time),
+            median: ns(point.median),
+            speedup: point.speedup,
+            efficiency: point.efficiency,
+        })?;
+    }
+
+    writer.flush()
+}
+
+// One CSV row per library and data size
+#[derive(Serialize)]
+struct ScalingRow<'a> {
+    started_at_unix: u64,
+    iterations: usize,
+    seed: u64,
+    work_units: u64,
+    chunk_size: usize,
+    category: Category,
+    library: &'a str,
+    unit: &'static str,
+    data_size: usize,
+    median: u64,
+    throughput: f64,
+}
+
+pub fn write_scaling_csv(scaling: &Scaling, out: &mut dyn Write) -> io::Result<()> {
+    let mut writer = csv::Writer::from_writer(out);
+
+    for point in &scaling.points {
+        writer.serialize(ScalingRow {
+            started_at_unix: scaling.metadata.started_at_unix,
+            iterations: scaling.iterations,
+            seed: scaling.seed,
+            work_units: scaling.cost.units,
+            chunk_size: scaling.chunk_size,
+            category: point.category,
+            library: &point.library,
+            unit: "ns",
+            data_size: point.data_size,
+            median: point.median.as_nanos() as u64,
+            throughput: point.throughput,
+        })?;
+    }
+
+    writer.flush()
+}
+
+// One CSV row per library and worker count, with the library's Amdahl fit repeated
+#[derive(Serialize)]
+struct ThreadRow<'a> {
+    started_at_unix: u64,
+    data_size: usize,
+    iterations: usize,
+    seed: u64,
+    work_units: u64,
+    chunk_size: usize,
+    category: Category,
+    library: &'a str,
+    unit: &'static str,
+    threads: usize,
+    median: u64,
+    speedup: f64,
+    efficiency: f64,
+    serial_fraction: Option<f64>,
+}
+
+pub fn write_thread_scaling_csv(scaling: &ThreadScaling, out: &mut dyn Write) -> io::Result<()> {
+    let mut writer = csv::Writer::from_writer(out);
+
+    for point in &scaling.points {
+        writer.serialize(ThreadRow {
+            started_at_unix: scaling.metadata.started_at_unix,
+            data_size: scaling.data_size,
+            iterations: scaling.iterations,
+            seed: scaling.seed,
+            work_units: scaling.cost.units,
+            chunk_size: scaling.chunk_size,
+            category: point.category,
+            library: &point.library,
+            unit: "ns",
+            threads: point.threads,
+            median: point.median.as_nanos() as u64,
+            speedup: point.speedup,
+            efficiency: point.efficiency,
+            serial_fraction: scaling.fits.iter().find(|f| f.library == point.library).map(|f| f.serial_fraction),
+        })?;
+    }
+
+    writer.flush()
+}
+
+// One CSV row per library and collection strategy, with the library's lock overhead repeated
+#[derive(Serialize)]
+struct CollectionRow<'a> {
+    started_at_unix: u64,
+    data_size: usize,
+    iterations: usize,
+    seed: u64,
+    work_units: u64,
+    chunk_size: usize,
+    category: Category,
+    library: &'a str,
+    collection: &'static str,
+    unit: &'static str,
+    median: u64,
+    vs_mutex: Option<f64>,
+    lock_overhead_share: Option<f64>,
+}
+
+pub fn write_collection_csv(matrix: &CollectionMatrix, out: &mut dyn Write) -> io::Result<()> {
+    let mut writer = csv::Writer::from_writer(out);
+
+    for point in &matrix.points {
+        writer.serialize(CollectionRow {
+            started_at_unix: matrix.metadata.started_at_unix,
+            data_size: matrix.data_size,
+            iterations: matrix.iterations,
+            seed: matrix.seed,
+            work_units: matrix.cost.units,
+            chunk_size: matrix.chunk_size,
+            category: point.category,
+            library: &point.library,
+            collection: point.collection.as_str(),
+            unit: "ns",
+            median: point.median.as_nanos() as u64,
+            vs_mutex: point.vs_mutex,
+            lock_overhead_share: matrix.overheads.iter().find(|o| o.library == point.library).map(|o| o.overhead_share),
+        })?;
+    }
+
+    writer.flush()
+}
+
+// One CSV row per runtime, lock and shard count
+#[derive(Serialize)]
+struct LockRow {
+    started_at_unix: u64,
+    data_size: usize,
+    iterations: usize,
+    seed: u64,
+    work_units: u64,
+    chunk_size: usize,
+    reads: usize,
+    hold_units: u64,
+    runtime: &'static str,
+    lock: &'static str,
+    shards: usize,
+    unit: &'static str,
+    median: u64,
+    acquisitions_per_sec: f64,
+    vs_std_mutex: Option<f64>,
+}
+
+pub fn write_locks_csv(comparison: &LockComparison, out: &mut dyn Write) -> io::Result<()> {
+    let mut writer = csv::Writer::from_writer(out);
+
+    for point in &comparison.points {
+        writer.serialize(LockRow {
+            started_at_unix: comparison.metadata.started_at_unix,
+            data_size: comparison.data_size,
+            iterations: comparison.iterations,
+            seed: comparison.seed,
+            work_units: comparison.cost.units,
+            chunk_size: comparison.chunk_size,
+            reads: comparison.reads,
+            hold_units: comparison.hold_units,
+            runtime: point.runtime.as_str(),
+            lock: point.lock.as_str(),
+            shards: point.shards,
+            unit: "ns",
+            median: point.median.as_nanos() as u64,
+            acquisitions_per_sec: point.acquisitions_per_sec,
+            vs_std_mutex: point.vs_std_mutex,
+        })?;
+    }
+
+    writer.flush()
+}
+
+// One CSV row per channel, shape, bound and flavor, with the round trip of that bound and flavor
+#[derive(Serialize)]
+struct ChannelRow {
+    started_at_unix: u64,
+    messages: usize,
+    iterations: usize,
+    channel: &'static str,
+    topology: &'static str,
+    // Empty for unbounded
+    capacity: Option<usize>,
+    flavor: &'static str,
+    producers: usize,
+    consumers: usize,
+    unit: &'static str,
+    median: u64,
+    messages_per_sec: f64,
+    lost: u64,
+    round_trip_p50: Option<u64>,
+    round_trip_p99: Option<u64>,
+}
+
+pub fn write_channels_csv(matrix: &ChannelMatrix, out: &mut dyn Write) -> io::Result<()> {
+    let mut writer = csv::Writer::from_writer(out);
+
+    for point in &matrix.points {
+        let trip = matrix.round_trips.iter()
+            .find(|t| t.channel == point.channel && t.capacity == point.capacity && t.flavor == point.flavor);
+        writer.serialize(ChannelRow {
+            started_at_unix: matrix.metadata.started_at_unix,
+            messages: matrix.messages,
+            iterations: matrix.iterations,
+            channel: point.channel.as_str(),
+            topology: point.topology.as_str(),
+            capacity: point.capacity,
+            flavor: point.flavor.as_str(),
+            producers: point.producers,
+            consumers: point.consumers,
+            unit: "ns",
+            median: point.median.as_nanos() as u64,
+            messages_per_sec: point.messages_per_sec,
+            lost: point.lost,
+            round_trip_p50: trip.map(|t| t.p50.as_nanos() as u64),
+            round_trip_p99: trip.map(|t| t.p99.as_nanos() as u64),
+        })?;
+    }
+
+    writer.flush()
+}
+
+// One CSV row per runtime and queue, with that runtime's throughput and latency
+#[derive(Serialize)]
+struct PipelineRow<'a> {
+    started_at_unix: u64,
+    items: usize,
+    iterations: usize,
+    seed: u64,
+    work_units: u64,
+    depth: usize,
+    capacity: usize,
+    stage_workers: usize,
+    slow_stage: Option<&'a str>,
+    slow_factor: u64,
+    runtime: &'static str,
+    unit: &'static str,
+    median: u64,
+    items_per_sec: f64,
+    p50: u64,
+    p99: u64,
+    max: u64,
+    max_in_flight: usize,
+    in_flight_bound: usize,
+    backpressure_held: bool,
+    queue_into: &'a str,
+    mean_occupancy: f64,
+    max_occupancy: usize,
+    full_share: f64,
+}
+
+pub fn write_pipeline_csv(benchmark: &PipelineBenchmark, out: &mut dyn Write) -> io::Result<()> {
+    let mut writer = csv::Writer::from_writer(out);
+
+    for result in &benchmark.results {
+        for queue in &result.queues {
+            writer.serialize(PipelineRow {
+                started_at_unix: benchmark.metadata.started_at_unix,
+                items: benchmark.items,
+                iterations: benchmark.iterations,
+                seed: benchmark.seed,
+                work_units: benchmark.cost.units,
+                depth: benchmark.stages.len(),
+                capacity: benchmark.capacity,
+                stage_workers: benchmark.stage_workers,
+                slow_stage: benchmark.slow_stage.map(|stage| benchmark.stages[stage].as_str()),
+                slow_factor: benchmark.slow_factor,
+                runtime: result.runtime.as_str(),
+                unit: "ns",
+                median: result.median.as_nanos() as u64,
+                items_per_sec: result.items_per_sec,
+                p50: result.p50.as_nanos() as u64,
+                p99: result.p99.as_nanos() as u64,
+                max: result.max.as_nanos() as u64,
+                max_in_flight: result.max_in_flight,
+                in_flight_bound: benchmark.in_flight_bound,
+                backpressure_held: result.backpressure_held,
+                queue_into: &queue.stage,
+                mean_occupancy: queue.mean,
+                max_occupancy: queue.max,
+                full_share: queue.full_share,
+            })?;
+        }
+    }
+
+    writer.flush()
+}
+
+// One CSV row per echo server
+#[derive(Serialize)]
+struct EchoRow<'a> {
+    started_at_unix: u64,
+    connections: usize,
+    message_size: usize,
+    duration: u64,
+    category: Category,
+    library: &'a str,
+    unit: &'static str,
+    requests: u64,
+    errors: u64,
+    requests_per_sec: f64,
+    p50: u64,
+    p99: u64,
+    p999: u64,
+    max: u64,
+}
+
+pub fn write_echo_csv(benchmark: &EchoBenchmark, out: &mut dyn Write) -> io::Result<()> {
+    let mut writer = csv::Writer::from_writer(out);
+
+    let ns = |d: Duration| d.as_nanos() as u64;
+
+    for result in &benchmark.results {
+        writer.serialize(EchoRow {
+            started_at_unix: benchmark.metadata.started_at_unix,
+            connections: benchmark.connections,
+            message_size: benchmark.message_size,
+            duration: ns(benchmark.duration),
+            category: result.category,
+            library: &result.library,
+            unit: "ns",
+            requests: result.requests,
+            errors: result.errors,
+            requests_per_sec: result.requests_per_sec,
+            p50: ns(result.p50),
+            p99: ns(result.p99),
+            p999: ns(result.p999),
+            max: ns(result.max),
+        })?;
+    }
+
+    writer.flush()
+}
+
+// One CSV row per library and offered rate
+#[derive(Serialize)]
+struct LoadRow<'a> {
+    started_at_unix: u64,
+    arrivals: &'static str,
+    duration: u64,
+    seed: u64,
+    work_units: u64,
+    category: Category,
+    library: &'a str,
+    unit: &'static str,
+    offered_rate: f64,
+    achieved_rate: f64,
+    items: usize,
+    p50: u64,
+    p99: u64,
+    p999: u64,
+    max: u64,
+    uncorrected_p99: u64,
+    sustained: bool,
+}
+
+pub fn write_open_loop_csv(open_loop: &OpenLoop, out: &mut dyn Write) -> io::Result<()> {
+    let mut writer = csv::Writer::from_writer(out);
+
+    let ns = |d: Duration| d.as_nanos() as u64;
+
+    for point in &open_loop.points {
+        writer.serialize(LoadRow {
+            started_at_unix: open_loop.metadata.started_at_unix,
+            arrivals: open_loop.arrivals.as_str(),
+            duration: ns(open_loop.duration),
+            seed: open_loop.seed,
+            work_units: open_loop.cost.units,
+            category: point.category,
+            library: &point.library,
+            unit: "ns",
+            offered_rate: point.offered_rate,
+            achieved_rate: point.achieved_rate,
+            items: point.items,
+            p50: ns(point.p50),
+            p99: ns(point.p99),
+            p999: ns(point.p999),
+            max: ns(point.max),
+            uncorrected_p99: ns(point.uncorrected_p99),
+            sustained: point.sustained,
+        })?;
+    }
+
+    writer.flush()
+}
@@ -1,248 +1,317 @@
-use rand::{thread_rng, Rng};
+use std::ops::Range;
 use std::sync::{Arc, Mutex};
-use std::time::{Duration, Instant};
-use flume;
+use std::time::Duration;
 use nalgebra as na;
-use num_cpus;
+
+use crate::adapter::{
+    chunk_ranges, process_range, process_range_async, run_category, take_results, Datasets, RuntimeAdapter,
+};
+use crate::cli::Selection;
+use crate::collect::{Collection, Results};
+use crate::report::Progress;
+use crate::results::{BenchmarkResult, Category, Workload};
 
 // Import the processing function from the async module
-use crate::async_check::process_value;
-
-// Results structure to collect benchmark data
-pub struct HybridBenchmarkResult {
-    pub library: String,
-    pub best_time: Duration,
-    pub avg_time: Duration,
-    pub all_times: Vec<Duration>,
+use crate::async_check::{process_value, tokio_runtime};
+use crate::parallel_check::rayon_pool;
+use crate::timing::{self, Spawned};
+use crate::trace;
+
+const CATEGORY: Category = Category::Hybrid;
+
+pub fn adapters() -> Vec<Box<dyn RuntimeAdapter>> {
+    vec![
+        Box::new(ActixAdapter::default()),
+        Box::new(TokioRayonAdapter::default()),
+        Box::new(AsyncStdCrossbeamAdapter),
+        Box::new(FlumeAdapter::default()),
+        Box::new(NalgebraTokioAdapter::default()),
+        Box::new(AsyncGraphQLPatternAdapter::default()),
+        Box::new(WgpuPatternAdapter::default()),
+    ]
 }
 
-// Main function to benchmark hybrid libraries
-pub fn benchmark_hybrid_libraries(data_size: usize, iterations: usize) -> Vec<HybridBenchmarkResult> {
-    println!("Starting hybrid library benchmarks...");
-    
-    let mut results = Vec::new();
-    
-    // Benchmark actix-rt
-    let mut actix_times = Vec::with_capacity(iterations);
-    let mut actix_best = Duration::from_secs(u64::MAX);
-    
-    for _ in 0..iterations {
-        // Generate random data
-        let mut rng = thread_rng();
-        let data: Vec<u32> = (0..data_size).map(|_| rng.gen_range(0..10000)).collect();
-        let data_arc = Arc::new(data);
-        
-        let start = Instant::now();
-        let system = actix_rt::System::new();
+// actix-rt system, one task per chunk
+#[derive(Default)]
+pub struct ActixAdapter {
+    system: Option<actix_rt::SystemRunner>,
+}
+
+impl RuntimeAdapter for ActixAdapter {
+    fn name(&self) -> &'static str {
+        "Actix"
+    }
+
+    fn category(&self) -> Category {
+        CATEGORY
+    }
+
+    // An actix-rt system runs every task on the thread that owns it
+    fn threads(&self) -> usize {
+        1
+    }
+
+    fn build(&mut self) {
+        self.system = Some(actix_rt::System::new());
+    }
+
+    fn run(&mut self, data: Arc<Vec<u32>>, delays: Option<Arc<[Duration]>>, workload: &Workload) -> Option<Vec<u32>> {
+        let cost = workload.cost;
+        let system = self.system.as_ref().expect("Actix system not built");
         system.block_on(async {
-            let results = Arc::new(Mutex::new(vec![0; data_arc.len()]));
+            let results = Arc::new(Results::new(data.len(), workload.collection));
             let mut handles = Vec::new();
-            
-            for (idx, &value) in data_arc.iter().enumerate() {
+
+            for range in chunk_ranges(data.len(), workload.chunk_size) {
+                let data = data.clone();
+                let delays = delays.clone();
                 let results_clone = results.clone();
+                let spawned = timing::spawned();
                 let handle = actix_rt::spawn(async move {
-                    let processed = process_value(value);
-                    let mut results = results_clone.lock().unwrap();
-                    results[idx] = processed;
+                    let _task = spawned.start();
+                    process_range_async(&data, delays.as_deref(), range, cost, &results_clone, actix_rt::time::sleep).await
                 });
                 handles.push(handle);
             }
-            
+
             for handle in handles {
-                let _ = handle.await;
+                results.returned(handle.await.unwrap());
             }
-        });
-        
-        let duration = start.elapsed();
-        if duration < actix_best {
-            actix_best = duration;
+
+            Some(results.take())
+        })
+    }
+
+    fn supports_collection(&self, _collection: Collection) -> bool {
+        true
+    }
+
+    fn teardown(&mut self) {
+        self.system = None;
+    }
+}
+
+// Tokio for task management, Rayon for the per-chunk processing
+#[derive(Default)]
+pub struct TokioRayonAdapter {
+    // Used for both the Tokio workers and the Rayon pool
+    workers: Option<usize>,
+    runtime: Option<tokio::runtime::Runtime>,
+    pool: Option<Arc<rayon::ThreadPool>>,
+}
+
+impl RuntimeAdapter for TokioRayonAdapter {
+    fn name(&self) -> &'static str {
+        "Tokio+Rayon"
+    }
+
+    fn category(&self) -> Category {
+        CATEGORY
+    }
+
+    fn threads(&self) -> usize {
+        self.workers.unwrap_or_else(num_cpus::get)
+    }
+
+    fn set_workers(&mut self, workers: usize) -> bool {
+        self.workers = Some(workers);
+        true
+    }
+
+    fn build(&mut self) {
+        self.runtime = Some(tokio_runtime(self.workers));
+        self.pool = Some(Arc::new(rayon_pool(self.workers)));
+    }
+
+    fn run(&mut self, data: Arc<Vec<u32>>, delays: Option<Arc<[Duration]>>, workload: &Workload) -> Option<Vec<u32>> {
+        if delays.is_some() {
+            return None;
         }
-        actix_times.push(duration);
-    }
-    
-    let actix_avg = actix_times.iter().sum::<Duration>() / actix_times.len() as u32;
-    results.push(HybridBenchmarkResult {
-        library: "Actix".to_string(),
-        best_time: actix_best,
-        avg_time: actix_avg,
-        all_times: actix_times,
-    });
-    
-    // Benchmark tokio + rayon hybrid approach
-    let mut tokio_rayon_times = Vec::with_capacity(iterations);
-    let mut tokio_rayon_best = Duration::from_secs(u64::MAX);
-    
-    for _ in 0..iterations {
-        let mut rng = thread_rng();
-        let data: Vec<u32> = (0..data_size).map(|_| rng.gen_range(0..10000)).collect();
-        let data_arc = Arc::new(data);
-        
-        let start = Instant::now();
-        let runtime = tokio::runtime::Runtime::new().unwrap();
-        
+        let (cost, chunk_size) = (workload.cost, workload.chunk_size);
+        let workers = self.threads();
+        let runtime = self.runtime.as_ref().expect("Tokio runtime not built");
+        let pool = self.pool.as_ref().expect("Rayon pool not built");
         runtime.block_on(async {
-            let results = Arc::new(Mutex::new(vec![0; data_arc.len()]));
-            
-            // Use tokio for task management but process in parallel using rayon
-            let chunks: Vec<_> = data_arc
-                .chunks(data_arc.len() / num_cpus::get().max(1))
-                .collect();
-                
+            let results = Arc::new(Results::new(data.len(), workload.collection));
+
+            // Use tokio for task management (one task per worker-sized slice) but
+            // process each slice in parallel using rayon
+            let slice_size = (data.len() / workers.max(1)).max(1);
+
             let mut handles = Vec::new();
-            
-            for (chunk_idx, chunk) in chunks.iter().enumerate() {
-                let chunk_data = chunk.to_vec();
+
+            for slice in chunk_ranges(data.len(), slice_size) {
+                let data = data.clone();
                 let results_clone = results.clone();
-                
+                let pool = pool.clone();
+
                 let handle = tokio::spawn(async move {
-                    // Process this chunk with rayon
-                    let offset = chunk_idx * chunk_data.len();
-                    
-                    rayon::scope(|s| {
-                        for (i, &value) in chunk_data.iter().enumerate() {
-                            let results = results_clone.clone();
-                            let idx = offset + i;
+                    // Process this slice with rayon
+                    pool.scope(|s| {
+                        for start in slice.clone().step_by(chunk_size.max(1)) {
+                            let range = start..(start + chunk_size.max(1)).min(slice.end);
+                            let data = &data;
+                            let results = &results_clone;
+                            let spawned = timing::spawned();
                             s.spawn(move |_| {
-                                let processed = process_value(value);
-                                let mut results_guard = results.lock().unwrap();
-                                results_guard[idx] = processed;
+                                let _task = spawned.start();
+                                process_range(data, None, range, cost, results);
                             });
                         }
                     });
                 });
-                
+
                 handles.push(handle);
             }
-            
+
             for handle in handles {
-                let _ = handle.await.unwrap();
+                handle.await.unwrap();
             }
-        });
-        
-        let duration = start.elapsed();
-        if duration < tokio_rayon_best {
-            tokio_rayon_best = duration;
+
+            Some(results.take())
+        })
+    }
+
+    fn teardown(&mut self) {
+        self.runtime = None;
+        self.pool = None;
+    }
+}
+
+// async-std for task management, Crossbeam scoped threads within each slice
+pub struct AsyncStdCrossbeamAdapter;
+
+impl RuntimeAdapter for AsyncStdCrossbeamAdapter {
+    fn name(&self) -> &'static str {
+        "async-std+Crossbeam"
+    }
+
+    fn category(&self) -> Category {
+        CATEGORY
+    }
+
+    fn run(&mut self, data: Arc<Vec<u32>>, delays: Option<Arc<[Duration]>>, workload: &Workload) -> Option<Vec<u32>> {
+        if delays.is_some() {
+            return None;
         }
-        tokio_rayon_times.push(duration);
-    }
-    
-    let tokio_rayon_avg = tokio_rayon_times.iter().sum::<Duration>() / tokio_rayon_times.len() as u32;
-    results.push(HybridBenchmarkResult {
-        library: "Tokio+Rayon".to_string(),
-        best_time: tokio_rayon_best,
-        avg_time: tokio_rayon_avg,
-        all_times: tokio_rayon_times,
-    });
-    
-    // Benchmark async-std + crossbeam
-    let mut async_std_crossbeam_times = Vec::with_capacity(iterations);
-    let mut async_std_crossbeam_best = Duration::from_secs(u64::MAX);
-    
-    for _ in 0..iterations {
-        let mut rng = thread_rng();
-        let data: Vec<u32> = (0..data_size).map(|_| rng.gen_range(0..10000)).collect();
-        let data_arc = Arc::new(data);
-        
-        let start = Instant::now();
-        
+        let (cost, chunk_size) = (workload.cost, workload.chunk_size);
         async_std::task::block_on(async {
-            let results = Arc::new(Mutex::new(vec![0; data_arc.len()]));
-            
-            // Split data into chunks for processing
-            let chunks: Vec<_> = data_arc
-                .chunks(data_arc.len() / num_cpus::get().max(1))
-                .collect();
-                
+            let results = Arc::new(Results::new(data.len(), workload.collection));
+
+            // Split data into one slice per CPU for processing
+            let slice_size = (data.len() / num_cpus::get().max(1)).max(1);
+
             let mut handles = Vec::new();
-            
-            for (chunk_idx, chunk) in chunks.iter().enumerate() {
-                let chunk_data = chunk.to_vec();
+
+            for slice in chunk_ranges(data.len(), slice_size) {
+                let data = data.clone();
                 let results_clone = results.clone();
-                
+
                 let handle = async_std::task::spawn(async move {
-                    // Process this chunk with crossbeam
-                    let offset = chunk_idx * chunk_data.len();
-                    
+                    // Process this slice with crossbeam, one scoped thread per chunk
                     crossbeam::scope(|s| {
-                        for (i, &value) in chunk_data.iter().enumerate() {
-                            let results = results_clone.clone();
-                            let idx = offset + i;
+                        for start in slice.clone().step_by(chunk_size.max(1)) {
+                            let range = start..(start + chunk_size.max(1)).min(slice.end);
+                            let data = &data;
+                            let results = &results_clone;
+                            let spawned = timing::spawned();
                             s.spawn(move |_| {
-                                let processed = process_value(value);
-                                let mut results_guard = results.lock().unwrap();
-                                results_guard[idx] = processed;
+                                let _task = spawned.start();
+                                process_range(data, None, range, cost, results);
                             });
                         }
-                    }).unwrap();
+                    })
+                    .unwrap();
                 });
-                
+
                 handles.push(handle);
             }
-            
+
             for handle in handles {
                 handle.await;
             }
-        });
-        
-        let duration = start.elapsed();
-        if duration < async_std_crossbeam_best {
-            async_std_crossbeam_best = duration;
-        }
-        async_std_crossbeam_times.push(duration);
-    }
-    let async_std_crossbeam_avg = async_std_crossbeam_times.iter().sum::<Duration>() / async_std_crossbeam_times.len() as u32;
-    results.push(HybridBenchmarkResult {
-        library: "async-std+Crossbeam".to_string(),
-        best_time: async_std_crossbeam_best,
-        avg_time: async_std_crossbeam_avg,
-        all_times: async_std_crossbeam_times,
-    });
-    
-    // Benchmark using flume (MPMC channels)
-    let mut flume_times = Vec::with_capacity(iterations);
-    let mut flume_best = Duration::from_secs(u64::MAX);
-    
-    for _ in 0..iterations {
-        let mut rng = thread_rng();
-        let data: Vec<u32> = (0..data_size).map(|_| rng.gen_range(0..10000)).collect();
-        let data_arc = Arc::new(data);
-        
-        let start = Instant::now();
-        
+
+            Some(results.take())
+        })
+    }
+}
+
+// Worker threads pulling from a flume MPMC channel
+#[derive(Default)]
+pub struct FlumeAdapter {
+    workers: Option<usize>,
+}
+
+impl RuntimeAdapter for FlumeAdapter {
+    fn name(&self) -> &'static str {
+        "Flume"
+    }
+
+    fn category(&self) -> Category {
+        CATEGORY
+    }
+
+    fn threads(&self) -> usize {
+        self.workers.unwrap_or_else(num_cpus::get)
+    }
+
+    fn set_workers(&mut self, workers: usize) -> bool {
+        self.workers = Some(workers);
+        true
+    }
+
+    // Values always travel back over its own result channel to a collector thread,
+    // which is the library's model, so it only runs under the default
+    fn supports_collection(&self, collection: Collection) -> bool {
+        collection == Collection::Mutex
+    }
+
+    fn run(&mut self, data: Arc<Vec<u32>>, delays: Option<Arc<[Duration]>>, workload: &Workload) -> Option<Vec<u32>> {
+        let cost = workload.cost;
         // Create the channels
-        let (work_sender, work_receiver) = flume::unbounded();
+        let (work_sender, work_receiver) = flume::unbounded::<(Range<usize>, Spawned)>();
         let (result_sender, result_receiver) = flume::unbounded();
-        let results = Arc::new(Mutex::new(vec![0; data_arc.len()]));
-        
+        let results = Arc::new(Mutex::new(vec![0; data.len()]));
+
         // Spawn worker threads
-        let num_threads = num_cpus::get();
+        let num_threads = self.threads();
         let mut handles = Vec::new();
-        
+
         for _ in 0..num_threads {
             let receiver = work_receiver.clone();
             let sender = result_sender.clone();
+            let data = data.clone();
+            let delays = delays.clone();
             let handle = std::thread::spawn(move || {
-                while let Ok((idx, value)) = receiver.recv() {
-                    let processed = process_value(value);
-                    sender.send((idx, processed)).unwrap();
+                while let Ok((range, spawned)) = receiver.recv() {
+                    let _task = spawned.start();
+                    let mut trace = trace::task(range.start);
+                    for idx in range {
+                        // Simulated I/O blocks the worker before the value is processed
+                        if let Some(delays) = &delays {
+                            std::thread::sleep(delays[idx]);
+                        }
+                        let processed = trace.item(idx, || process_value(data[idx], cost));
+                        sender.send((idx, processed)).unwrap();
+                    }
                 }
             });
             handles.push(handle);
         }
-        
-        // Send work
-        for (idx, &value) in data_arc.iter().enumerate() {
-            work_sender.send((idx, value)).unwrap();
+
+        // Send work, one message per chunk
+        for range in chunk_ranges(data.len(), workload.chunk_size) {
+            work_sender.send((range, timing::spawned())).unwrap();
         }
-        
+
         // Signal that there's no more work
         drop(work_sender);
         drop(result_sender);
-        
+
         // Collect results
         let results_ref = results.clone();
         let collector_handle = std::thread::spawn(move || {
-            let mut remaining = data_arc.len();
+            let mut remaining = data.len();
             while remaining > 0 {
                 if let Ok((idx, result)) = result_receiver.recv() {
                     let mut results = results_ref.lock().unwrap();
@@ -253,211 +322,289 @@ pub fn benchmark_hybrid_libraries(data_size: usize, iterations: usize) -> Vec<Hy
                 }
             }
         });
-        
+
         // Wait for all workers to finish
         for handle in handles {
             handle.join().unwrap();
         }
-        
+
         // Wait for collector
         collector_handle.join().unwrap();
-        
-        let duration = start.elapsed();
-        if duration < flume_best {
-            flume_best = duration;
+
+        Some(take_results(&results))
+    }
+}
+
+// Tokio tasks walking row bands of a nalgebra matrix
+#[derive(Default)]
+pub struct NalgebraTokioAdapter {
+    workers: Option<usize>,
+    runtime: Option<tokio::runtime::Runtime>,
+}
+
+impl RuntimeAdapter for NalgebraTokioAdapter {
+    fn name(&self) -> &'static str {
+        "Nalgebra+Tokio"
+    }
+
+    fn category(&self) -> Category {
+        CATEGORY
+    }
+
+    // Work is always split into one band of matrix rows per worker
+    fn honours_chunk_size(&self) -> bool {
+        false
+    }
+
+    fn threads(&self) -> usize {
+        self.workers.unwrap_or_else(num_cpus::get)
+    }
+
+    fn set_workers(&mut self, workers: usize) -> bool {
+        self.workers = Some(workers);
+        true
+    }
+
+    fn supports_collection(&self, _collection: Collection) -> bool {
+        true
+    }
+
+    fn build(&mut self) {
+        self.runtime = Some(tokio_runtime(self.workers));
+    }
+
+    fn run(&mut self, data: Arc<Vec<u32>>, delays: Option<Arc<[Duration]>>, workload: &Workload) -> Option<Vec<u32>> {
+        if delays.is_some() {
+            return None;
         }
-        flume_times.push(duration);
-    }
-    
-    let flume_avg = flume_times.iter().sum::<Duration>() / flume_times.len() as u32;
-    results.push(HybridBenchmarkResult {
-        library: "Flume".to_string(),
-        best_time: flume_best,
-        avg_time: flume_avg,
-        all_times: flume_times,
-    });
-    
-    // Benchmark using nalgebra for matrix operations
-    let mut nalgebra_times = Vec::with_capacity(iterations);
-    let mut nalgebra_best = Duration::from_secs(u64::MAX);
-    
-    for _ in 0..iterations {
-        let mut rng = thread_rng();
-        
-        // For nalgebra, let's create a square matrix of approximately the right size
-        let matrix_size = (data_size as f64).sqrt() as usize;
-        let matrix_size_squared = matrix_size * matrix_size;
-        
-        let values: Vec<f32> = (0..matrix_size_squared)
-            .map(|_| rng.gen_range(0..10000) as f32)
-            .collect();
-        
+        let cost = workload.cost;
+        // Lay the data out row-major in a roughly square matrix, zero-padding the last
+        // row so every value is processed
+        let cols = (data.len() as f64).sqrt().ceil().max(1.0) as usize;
+        let rows = data.len().div_ceil(cols);
+
+        let mut values: Vec<f32> = data.iter().map(|&value| value as f32).collect();
+        values.resize(rows * cols, 0.0);
+
         // Create a nalgebra matrix
-        let matrix = na::DMatrix::<f32>::from_vec(matrix_size, matrix_size, values);
-        
-        let start = Instant::now();
-        
+        let matrix = na::DMatrix::<f32>::from_row_slice(rows, cols, &values);
+
         // Perform parallel computation with nalgebra
-        let results = Arc::new(Mutex::new(vec![0; matrix_size_squared]));
-        
+        let len = data.len();
+        let results = Arc::new(Results::new(len, workload.collection));
+
         // Use tokio runtime for task management with nalgebra
-        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let bands = self.threads().max(1);
+        let runtime = self.runtime.as_ref().expect("Tokio runtime not built");
         runtime.block_on(async {
-            let chunk_size = matrix_size / num_cpus::get().max(1);
+            let chunk_size = rows / bands;
             let mut handles = Vec::new();
-            
-            for i in 0..num_cpus::get().max(1) {
+
+            for i in 0..bands {
                 let start_row = i * chunk_size;
-                let end_row = if i == num_cpus::get().max(1) - 1 {
-                    matrix_size
+                let end_row = if i == bands - 1 {
+                    rows
                 } else {
                     (i + 1) * chunk_size
                 };
-                
+
                 let matrix_slice = matrix.clone();
                 let results_clone = results.clone();
-                
+
+                let spawned = timing::spawned();
                 let handle = tokio::spawn(async move {
+                    let _task = spawned.start();
+                    let mut trace = trace::task(start_row * cols);
+                    let mut sink = results_clone.task(start_row * cols..(end_row * cols).min(len));
                     for row in start_row..end_row {
-                        for col in 0..matrix_size {
+                        for col in 0..cols {
+                            let idx = row * cols + col;
+                            if idx >= len {
+                                break;
+                            }
                             let value = matrix_slice[(row, col)] as u32;
-                            let processed = process_value(value);
-                            let idx = row * matrix_size + col;
-                            let mut results_guard = results_clone.lock().unwrap();
-                            results_guard[idx] = processed;
+                            let processed = trace.item(idx, || process_value(value, cost));
+                            sink.put(idx, processed);
                         }
                     }
+                    sink.finish()
                 });
-                
+
                 handles.push(handle);
             }
-            
+
             for handle in handles {
-                let _ = handle.await.unwrap();
+                results.returned(handle.await.unwrap());
             }
-        });
-        
-        let duration = start.elapsed();
-        if duration < nalgebra_best {
-            nalgebra_best = duration;
+
+            Some(results.take())
+        })
+    }
+
+    fn teardown(&mut self) {
+        self.runtime = None;
+    }
+}
+
+// Semaphore-bounded futures joined together, similar to how async-graphql
+// handles parallel execution. Note: We're not actually using async-graphql.
+#[derive(Default)]
+pub struct AsyncGraphQLPatternAdapter {
+    workers: Option<usize>,
+    runtime: Option<tokio::runtime::Runtime>,
+}
+
+impl RuntimeAdapter for AsyncGraphQLPatternAdapter {
+    fn name(&self) -> &'static str {
+        "AsyncGraphQL-pattern"
+    }
+
+    fn category(&self) -> Category {
+        CATEGORY
+    }
+
+    fn threads(&self) -> usize {
+        self.workers.unwrap_or_else(num_cpus::get)
+    }
+
+    fn set_workers(&mut self, workers: usize) -> bool {
+        self.workers = Some(workers);
+        true
+    }
+
+    fn supports_collection(&self, _collection: Collection) -> bool {
+        true
+    }
+
+    fn build(&mut self) {
+        self.runtime = Some(tokio_runtime(self.workers));
+    }
+
+    fn run(&mut self, data: Arc<Vec<u32>>, delays: Option<Arc<[Duration]>>, workload: &Workload) -> Option<Vec<u32>> {
+        if delays.is_some() {
+            return None;
         }
-        nalgebra_times.push(duration);
-    }
-    
-    let nalgebra_avg = nalgebra_times.iter().sum::<Duration>() / nalgebra_times.len() as u32;
-    results.push(HybridBenchmarkResult {
-        library: "Nalgebra+Tokio".to_string(),
-        best_time: nalgebra_best,
-        avg_time: nalgebra_avg,
-        all_times: nalgebra_times,
-    });
-    
-    // Benchmark with async-graphql-inspired worker pool
-    // Note: We're not actually using async-graphql, just implementing a similar pattern
-    let mut async_graphql_pattern_times = Vec::with_capacity(iterations);
-    let mut async_graphql_pattern_best = Duration::from_secs(u64::MAX);
-    
-    for _ in 0..iterations {
-        let mut rng = thread_rng();
-        let data: Vec<u32> = (0..data_size).map(|_| rng.gen_range(0..10000)).collect();
-        let data_arc = Arc::new(data);
-        
-        let start = Instant::now();
-        
-        // Similar to how async-graphql handles parallel execution
-        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let cost = workload.cost;
+        let permits = self.threads();
+        let runtime = self.runtime.as_ref().expect("Tokio runtime not built");
         runtime.block_on(async {
-            let results = Arc::new(Mutex::new(vec![0; data_arc.len()]));
-            let semaphore = Arc::new(tokio::sync::Semaphore::new(num_cpus::get()));
-            
+            let results = Arc::new(Results::new(data.len(), workload.collection));
+            let semaphore = Arc::new(tokio::sync::Semaphore::new(permits));
+
             // Process in batches of futures
             let mut all_futures = Vec::new();
-            
-            for (idx, &value) in data_arc.iter().enumerate() {
+
+            for range in chunk_ranges(data.len(), workload.chunk_size) {
+                let data = &data;
                 let results_clone = results.clone();
                 let semaphore_clone = semaphore.clone();
-                
+
+                let spawned = timing::spawned();
                 all_futures.push(async move {
                     let _permit = semaphore_clone.acquire().await.unwrap();
-                    
+                    // Waiting for a permit counts as queueing
+                    let _task = spawned.start();
+
                     // Simulate some CPU-intensive work
-                    let processed = process_value(value);
-                    
-                    let mut results_guard = results_clone.lock().unwrap();
-                    results_guard[idx] = processed;
+                    process_range(data, None, range, cost, &results_clone)
                 });
             }
-            
+
             // Execute all tasks in a way similar to async-graphql's parallel execution model
-            futures::future::join_all(all_futures).await;
-        });
-        
-        let duration = start.elapsed();
-        if duration < async_graphql_pattern_best {
-            async_graphql_pattern_best = duration;
-        }
-        async_graphql_pattern_times.push(duration);
-    }
-    
-    let async_graphql_pattern_avg = async_graphql_pattern_times.iter().sum::<Duration>() / async_graphql_pattern_times.len() as u32;
-    results.push(HybridBenchmarkResult {
-        library: "AsyncGraphQL-pattern".to_string(),
-        best_time: async_graphql_pattern_best,
-        avg_time: async_graphql_pattern_avg,
-        all_times: async_graphql_pattern_times,
-    });
-    
-// Replace the problematic WGPU pattern benchmark with this corrected version:
-let mut wgpu_pattern_times = Vec::with_capacity(iterations);
-let mut wgpu_pattern_best = Duration::from_secs(u64::MAX);
-
-for _ in 0..iterations {
-    let mut rng = thread_rng();
-    let data: Vec<u32> = (0..data_size).map(|_| rng.gen_range(0..10000)).collect();
-    let data_arc = Arc::new(data);
-    
-    let start = Instant::now();
-    
-    // Simulate wgpu-like batch processing approach
-    const WORKGROUP_SIZE: usize = 256; // Common workgroup size for GPU computation
-    let results = Arc::new(Mutex::new(vec![0; data_arc.len()]));
-    
-    // Process data in batches similar to how GPU compute shaders would
-    rayon::scope(|s| {
-        for chunk_idx in 0..(data_arc.len() + WORKGROUP_SIZE - 1) / WORKGROUP_SIZE {
-            let start_idx = chunk_idx * WORKGROUP_SIZE;
-            let end_idx = (start_idx + WORKGROUP_SIZE).min(data_arc.len());
-            let data_arc_clone = data_arc.clone(); // Clone for each workgroup
-            let results_clone = results.clone(); // Clone for each workgroup
-            
-            s.spawn(move |_| {
-                // Process all items in this "workgroup" in parallel
-                for i in start_idx..end_idx {
-                    let processed = process_value(data_arc_clone[i]);
-                    let mut results_guard = results_clone.lock().unwrap();
-                    results_guard[i] = processed;
-                }
-            });
+            for returned in futures::future::join_all(all_futures).await {
+                results.returned(returned);
+            }
+
+            Some(results.take())
+        })
+    }
+
+    fn teardown(&mut self) {
+        self.runtime = None;
+    }
+}
+
+// Simulate wgpu-like batch processing: Rayon tasks over fixed-size workgroups
+#[derive(Default)]
+pub struct WgpuPatternAdapter {
+    workers: Option<usize>,
+    pool: Option<rayon::ThreadPool>,
+}
+
+impl RuntimeAdapter for WgpuPatternAdapter {
+    fn name(&self) -> &'static str {
+        "WGPU-pattern"
+    }
+
+    fn category(&self) -> Category {
+        CATEGORY
+    }
+
+    // Work is always split into fixed-size workgroups
+    fn honours_chunk_size(&self) -> bool {
+        false
+    }
+
+    fn threads(&self) -> usize {
+        self.workers.unwrap_or_else(num_cpus::get)
+    }
+
+    fn set_workers(&mut self, workers: usize) -> bool {
+        self.workers = Some(workers);
+        true
+    }
+
+    fn build(&mut self) {
+        self.pool = Some(rayon_pool(self.workers));
+    }
+
+    fn run(&mut self, data: Arc<Vec<u32>>, delays: Option<Arc<[Duration]>>, workload: &Workload) -> Option<Vec<u32>> {
+        if delays.is_some() {
+            return None;
         }
-    });
-    
-    let duration = start.elapsed();
-    if duration < wgpu_pattern_best {
-        wgpu_pattern_best = duration;
+        const WORKGROUP_SIZE: usize = 256; // Common workgroup size for GPU computation
+        let cost = workload.cost;
+        let pool = self.pool.as_ref().expect("Rayon pool not built");
+        let results = Arc::new(Results::new(data.len(), workload.collection));
+
+        // Process data in batches similar to how GPU compute shaders would
+        pool.scope(|s| {
+            for chunk_idx in 0..data.len().div_ceil(WORKGROUP_SIZE) {
+                let start_idx = chunk_idx * WORKGROUP_SIZE;
+                let end_idx = (start_idx + WORKGROUP_SIZE).min(data.len());
+                let data_clone = data.clone(); // Clone for each workgroup
+                let results_clone = results.clone(); // Clone for each workgroup
+
+                let spawned = timing::spawned();
+                s.spawn(move |_| {
+                    let _task = spawned.start();
+                    let mut trace = trace::task(start_idx);
+                    let mut sink = results_clone.task(start_idx..end_idx);
+                    // Process all items in this "workgroup" in parallel
+                    for i in start_idx..end_idx {
+                        let processed = trace.item(i, || process_value(data_clone[i], cost));
+                        sink.put(i, processed);
+                    }
+                    sink.finish();
+                });
+            }
+        });
+
+        Some(results.take())
+    }
+
+    fn teardown(&mut self) {
+        self.pool = None;
     }
-    wgpu_pattern_times.push(duration);
 }
 
-let wgpu_pattern_avg = wgpu_pattern_times.iter().sum::<Duration>() / wgpu_pattern_times.len() as u32;
-results.push(HybridBenchmarkResult {
-    library: "WGPU-pattern".to_string(),
-    best_time: wgpu_pattern_best,
-    avg_time: wgpu_pattern_avg,
-    all_times: wgpu_pattern_times,
-});
+// Main function to benchmark hybrid libraries
+pub fn benchmark_hybrid_libraries(workload: &Workload, datasets: &Datasets, selection: &Selection,
+                                  progress: Progress) -> Vec<BenchmarkResult> {
+    progress.line("Starting hybrid library benchmarks...");
+
+    let results = run_category(CATEGORY, workload, datasets, selection);
 
-    
-    println!("Hybrid library benchmarks completed.");
+    progress.line("Hybrid library benchmarks completed.");
     results
 }
-    
\ No newline at end of file
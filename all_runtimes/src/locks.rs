@@ -0,0 +1,409 @@
+use clap::ValueEnum;
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::hint::black_box;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::adapter::{chunk_ranges, Datasets};
+use crate::async_check::{process_value, smol_executor, tokio_runtime, work};
+use crate::cli::LocksArgs;
+use crate::parallel_check::rayon_pool;
+use crate::results::{nanos, RunMetadata, WorkCost};
+use crate::stats;
+
+// The lock every item goes through to store its value
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum LockKind {
+    StdMutex,
+    ParkingLotMutex,
+    TokioMutex,
+    AsyncLockMutex,
+    StdRwLock,
+    ParkingLotRwLock,
+    TokioRwLock,
+    AsyncLockRwLock,
+}
+
+impl LockKind {
+    pub const ALL: [LockKind; 8] = [
+        LockKind::StdMutex,
+        LockKind::ParkingLotMutex,
+        LockKind::TokioMutex,
+        LockKind::AsyncLockMutex,
+        LockKind::StdRwLock,
+        LockKind::ParkingLotRwLock,
+        LockKind::TokioRwLock,
+        LockKind::AsyncLockRwLock,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LockKind::StdMutex => "std Mutex",
+            LockKind::ParkingLotMutex => "parking_lot Mutex",
+            LockKind::TokioMutex => "tokio Mutex",
+            LockKind::AsyncLockMutex => "async-lock Mutex",
+            LockKind::StdRwLock => "std RwLock",
+            LockKind::ParkingLotRwLock => "parking_lot RwLock",
+            LockKind::TokioRwLock => "tokio RwLock",
+            LockKind::AsyncLockRwLock => "async-lock RwLock",
+        }
+    }
+
+    // Whether waiting for it suspends the task rather than blocking the thread
+    pub fn is_async(&self) -> bool {
+        matches!(self, LockKind::TokioMutex | LockKind::AsyncLockMutex | LockKind::TokioRwLock | LockKind::AsyncLockRwLock)
+    }
+}
+
+// Where the items run: one task per chunk on an async runtime, or one Rayon job per
+// chunk, which takes the async locks through their blocking methods
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum LockRuntime {
+    Tokio,
+    Smol,
+    AsyncStd,
+    Rayon,
+}
+
+impl LockRuntime {
+    pub const ALL: [LockRuntime; 4] = [LockRuntime::Tokio, LockRuntime::Smol, LockRuntime::AsyncStd, LockRuntime::Rayon];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LockRuntime::Tokio => "Tokio",
+            LockRuntime::Smol => "smol",
+            LockRuntime::AsyncStd => "async-std",
+            LockRuntime::Rayon => "Rayon",
+        }
+    }
+}
+
+// The output split over `shards` locks, item `idx` living in shard `idx % shards`.
+// Fewer shards means more tasks wanting the same lock.
+enum Locks {
+    StdMutex(Vec<std::sync::Mutex<Vec<u32>>>),
+    ParkingLotMutex(Vec<parking_lot::Mutex<Vec<u32>>>),
+    TokioMutex(Vec<tokio::sync::Mutex<Vec<u32>>>),
+    AsyncLockMutex(Vec<async_lock::Mutex<Vec<u32>>>),
+    StdRwLock(Vec<std::sync::RwLock<Vec<u32>>>),
+    ParkingLotRwLock(Vec<parking_lot::RwLock<Vec<u32>>>),
+    TokioRwLock(Vec<tokio::sync::RwLock<Vec<u32>>>),
+    AsyncLockRwLock(Vec<async_lock::RwLock<Vec<u32>>>),
+}
+
+impl Locks {
+    fn new(kind: LockKind, len: usize, shards: usize) -> Self {
+        let slots = len.div_ceil(shards);
+        let values = || vec![0; slots];
+        match kind {
+            LockKind::StdMutex => Locks::StdMutex((0..shards).map(|_| std::sync::Mutex::new(values())).collect()),
+            LockKind::ParkingLotMutex => Locks::ParkingLotMutex((0..shards).map(|_| parking_lot::Mutex::new(values())).collect()),
+            LockKind::TokioMutex => Locks::TokioMutex((0..shards).map(|_| tokio::sync::Mutex::new(values())).collect()),
+            LockKind::AsyncLockMutex => Locks::AsyncLockMutex((0..shards).map(|_| async_lock::Mutex::new(values())).collect()),
+            LockKind::StdRwLock => Locks::StdRwLock((0..shards).map(|_| std::sync::RwLock::new(values())).collect()),
+            LockKind::ParkingLotRwLock => Locks::ParkingLotRwLock((0..shards).map(|_| parking_lot::RwLock::new(values())).collect()),
+            LockKind::TokioRwLock => Locks::TokioRwLock((0..shards).map(|_| tokio::sync::RwLock::new(values())).collect()),
+            LockKind::AsyncLockRwLock => Locks::AsyncLockRwLock((0..shards).map(|_| async_lock::RwLock::new(values())).collect()),
+        }
+    }
+
+    // Look at a slot under the lock, shared for RwLocks
+    fn read_blocking(&self, shard: usize, slot: usize) -> u32 {
+        match self {
+            Locks::StdMutex(locks) => locks[shard].lock().unwrap()[slot],
+            Locks::ParkingLotMutex(locks) => locks[shard].lock()[slot],
+            Locks::TokioMutex(locks) => locks[shard].blocking_lock()[slot],
+            Locks::AsyncLockMutex(locks) => locks[shard].lock_blocking()[slot],
+            Locks::StdRwLock(locks) => locks[shard].read().unwrap()[slot],
+            Locks::ParkingLotRwLock(locks) => locks[shard].read()[slot],
+            Locks::TokioRwLock(locks) => locks[shard].blocking_read()[slot],
+            Locks::AsyncLockRwLock(locks) => locks[shard].read_blocking()[slot],
+        }
+    }
+
+    // Store a value under the lock, after `hold_units` of work while holding it
+    fn write_blocking(&self, shard: usize, slot: usize, value: u32, hold_units: u64) {
+        let store = |values: &mut Vec<u32>| {
+            black_box(work(value, hold_units));
+            values[slot] = value;
+        };
+        match self {
+            Locks::StdMutex(locks) => store(&mut locks[shard].lock().unwrap()),
+            Locks::ParkingLotMutex(locks) => store(&mut locks[shard].lock()),
+            Locks::TokioMutex(locks) => store(&mut locks[shard].blocking_lock()),
+            Locks::AsyncLockMutex(locks) => store(&mut locks[shard].lock_blocking()),
+            Locks::StdRwLock(locks) => store(&mut locks[shard].write().unwrap()),
+            Locks::ParkingLotRwLock(locks) => store(&mut locks[shard].write()),
+            Locks::TokioRwLock(locks) => store(&mut locks[shard].blocking_write()),
+            Locks::AsyncLockRwLock(locks) => store(&mut locks[shard].write_blocking()),
+        }
+    }
+
+    // Same as `read_blocking`, awaiting the async locks; the sync ones are taken as
+    // they would be in async code, blocking the worker thread while they wait
+    async fn read(&self, shard: usize, slot: usize) -> u32 {
+        match self {
+            Locks::TokioMutex(locks) => locks[shard].lock().await[slot],
+            Locks::AsyncLockMutex(locks) => locks[shard].lock().await[slot],
+            Locks::TokioRwLock(locks) => locks[shard].read().await[slot],
+            Locks::AsyncLockRwLock(locks) => locks[shard].read().await[slot],
+            _ => self.read_blocking(shard, slot),
+        }
+    }
+
+    async fn write(&self, shard: usize, slot: usize, value: u32, hold_units: u64) {
+        let store = |values: &mut Vec<u32>| {
+            black_box(work(value, hold_units));
+            values[slot] = value;
+        };
+        match self {
+            Locks::TokioMutex(locks) => store(&mut *locks[shard].lock().await),
+            Locks::AsyncLockMutex(locks) => store(&mut *locks[shard].lock().await),
+            Locks::TokioRwLock(locks) => store(&mut *locks[shard].write().await),
+            Locks::AsyncLockRwLock(locks) => store(&mut *locks[shard].write().await),
+            _ => self.write_blocking(shard, slot, value, hold_units),
+        }
+    }
+
+    // Every stored value in input order, once all tasks are done
+    fn into_values(self, len: usize) -> Vec<u32> {
+        let shards: Vec<Vec<u32>> = match self {
+            Locks::StdMutex(locks) => locks.into_iter().map(|l| l.into_inner().unwrap()).collect(),
+            Locks::ParkingLotMutex(locks) => locks.into_iter().map(|l| l.into_inner()).collect(),
+            Locks::TokioMutex(locks) => locks.into_iter().map(|l| l.into_inner()).collect(),
+            Locks::AsyncLockMutex(locks) => locks.into_iter().map(|l| l.into_inner()).collect(),
+            Locks::StdRwLock(locks) => locks.into_iter().map(|l| l.into_inner().unwrap()).collect(),
+            Locks::ParkingLotRwLock(locks) => locks.into_iter().map(|l| l.into_inner()).collect(),
+            Locks::TokioRwLock(locks) => locks.into_iter().map(|l| l.into_inner()).collect(),
+            Locks::AsyncLockRwLock(locks) => locks.into_iter().map(|l| l.into_inner()).collect(),
+        };
+        (0..len).map(|idx| shards[idx % shards.len()][idx / shards.len()]).collect()
+    }
+}
+
+// What every item does: its CPU work outside any lock, `reads` looks at its slot,
+// then one write holding the lock for `hold_units` of work
+#[derive(Clone, Copy)]
+struct HotPath {
+    cost: WorkCost,
+    shards: usize,
+    reads: usize,
+    hold_units: u64,
+}
+
+impl HotPath {
+    fn item_blocking(&self, locks: &Locks, data: &[u32], idx: usize) {
+        let processed = process_value(data[idx], self.cost);
+        let (shard, slot) = (idx % self.shards, idx / self.shards);
+        for _ in 0..self.reads {
+            black_box(locks.read_blocking(shard, slot));
+        }
+        locks.write_blocking(shard, slot, processed, self.hold_units);
+    }
+
+    async fn item(&self, locks: &Locks, data: &[u32], idx: usize) {
+        let processed = process_value(data[idx], self.cost);
+        let (shard, slot) = (idx % self.shards, idx / self.shards);
+        for _ in 0..self.reads {
+            black_box(locks.read(shard, slot).await);
+        }
+        locks.write(shard, slot, processed, self.hold_units).await;
+    }
+}
+
+// One lock under one runtime at one contention level
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LockPoint {
+    pub runtime: LockRuntime,
+    pub lock: LockKind,
+    pub shards: usize,
+    #[serde(with = "nanos")]
+    pub median: Duration,
+    // Lock acquisitions, reads and writes, per second of the median run
+    pub acquisitions_per_sec: f64,
+    // Against std's Mutex under the same runtime and shard count
+    pub vs_std_mutex: Option<f64>,
+}
+
+// The fastest lock under one runtime at one contention level
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FastestLock {
+    pub runtime: LockRuntime,
+    pub shards: usize,
+    pub lock: LockKind,
+    #[serde(with = "nanos")]
+    pub median: Duration,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LockComparison {
+    pub metadata: RunMetadata,
+    pub data_size: usize,
+    pub iterations: usize,
+    pub seed: u64,
+    pub cost: WorkCost,
+    pub chunk_size: usize,
+    pub reads: usize,
+    pub hold_units: u64,
+    pub runtimes: Vec<LockRuntime>,
+    pub locks: Vec<LockKind>,
+    pub shard_counts: Vec<usize>,
+    pub points: Vec<LockPoint>,
+    pub fastest: Vec<FastestLock>,
+}
+
+// Run the same items under every selected runtime, lock and shard count
+pub fn run_locks(args: &LocksArgs, cost: WorkCost) -> LockComparison {
+    let seed = args.seed.unwrap_or_else(rand::random);
+    let datasets = Datasets::generate(seed, args.size, args.iterations, cost);
+    let mut shard_counts: Vec<usize> = args.shards.iter().map(|&n| n.clamp(1, args.size)).collect();
+    shard_counts.sort_unstable();
+    shard_counts.dedup();
+    let mut points = Vec::new();
+
+    for &runtime in &args.runtimes {
+        // Built once per runtime and shared by every lock, like a long-lived service.
+        // smol gets one thread per CPU like the others, not its single-threaded default.
+        let tokio = (runtime == LockRuntime::Tokio).then(|| tokio_runtime(None));
+        let smol = (runtime == LockRuntime::Smol).then(|| smol_executor(num_cpus::get()));
+        let pool = (runtime == LockRuntime::Rayon).then(|| rayon_pool(None));
+
+        for &shards in &shard_counts {
+            let path = HotPath { cost, shards, reads: args.reads, hold_units: args.hold_units };
+            for &lock in &args.locks {
+                eprintln!("Measuring {} under {} with {} shard(s)...", lock.as_str(), runtime.as_str(), shards);
+                let run = |i: usize| -> (Duration, Vec<u32>) {
+                    let data = datasets.get(i);
+                    let locks = Arc::new(Locks::new(lock, data.len(), shards));
+                    let start = Instant::now();
+                    match runtime {
+                        LockRuntime::Tokio => tokio.as_ref().unwrap().block_on(run_tasks(
+                            |task| {
+                                let handle = tokio::spawn(task);
+                                Box::pin(async move { handle.await.unwrap() })
+                            },
+                            &locks, &data, path, args.chunk_size)),
+                        LockRuntime::Smol => smol::block_on(run_tasks(
+                            |task| Box::pin(smol.as_ref().unwrap().executor.spawn(task)),
+                            &locks, &data, path, args.chunk_size)),
+                        LockRuntime::AsyncStd => async_std::task::block_on(run_tasks(
+                            |task| Box::pin(async_std::task::spawn(task)),
+                            &locks, &data, path, args.chunk_size)),
+                        LockRuntime::Rayon => pool.as_ref().unwrap().scope(|s| {
+                            for range in chunk_ranges(data.len(), args.chunk_size) {
+                                let (locks, data) = (&locks, &data);
+                                s.spawn(move |_| {
+                                    for idx in range {
+                                        path.item_blocking(locks, data, idx);
+                                    }
+                                });
+                            }
+                        }),
+                    }
+                    let elapsed = start.elapsed();
+                    let locks = Arc::into_inner(locks).expect("a task outlived its run");
+                    (elapsed, locks.into_values(data.len()))
+                };
+
+                for i in 0..args.warmup {
+                    run(i);
+                }
+                let mut times = Vec::new();
+                let mut failure = None;
+                for i in 0..args.iterations {
+                    let (elapsed, output) = run(i);
+                    times.push(elapsed);
+                    failure = failure.or_else(|| datasets.check(i, &output, args.verify));
+                }
+                if let Some(detail) = failure {
+                    eprintln!("{} under {} produced wrong output, skipped: {}", lock.as_str(), runtime.as_str(), detail);
+                    continue;
+                }
+
+                let median = stats::median(&times);
+                let acquisitions = (args.size * (args.reads + 1)) as f64;
+                points.push(LockPoint {
+                    runtime,
+                    lock,
+                    shards,
+                    median,
+                    acquisitions_per_sec: acquisitions / median.as_secs_f64().max(1e-9),
+                    vs_std_mutex: None,
+                });
+            }
+        }
+
+        if let Some(smol) = smol {
+            smol.shutdown();
+        }
+    }
+
+    for i in 0..points.len() {
+        let reference = points.iter()
+            .find(|p| p.runtime == points[i].runtime && p.shards == points[i].shards && p.lock == LockKind::StdMutex)
+            .map(|p| p.median);
+        points[i].vs_std_mutex = reference.map(|r| points[i].median.as_nanos() as f64 / r.as_nanos().max(1) as f64);
+    }
+
+    let fastest = fastest_locks(&points);
+    LockComparison {
+        metadata: RunMetadata::collect(),
+        data_size: args.size,
+        iterations: args.iterations,
+        seed,
+        cost,
+        chunk_size: args.chunk_size.max(1),
+        reads: args.reads,
+        hold_units: args.hold_units,
+        runtimes: args.runtimes.clone(),
+        locks: args.locks.clone(),
+        shard_counts,
+        points,
+        fastest,
+    }
+}
+
+// Spawn one task per chunk through `spawn`, which hands back something to await
+// the task with, then wait for all of them
+async fn run_tasks<S>(spawn: S, locks: &Arc<Locks>, data: &Arc<Vec<u32>>, path: HotPath, chunk_size: usize)
+where
+    S: Fn(BoxFuture<'static, ()>) -> BoxFuture<'static, ()>,
+{
+    let handles: Vec<_> = chunk_ranges(data.len(), chunk_size)
+        .map(|range| {
+            let locks = locks.clone();
+            let data = data.clone();
+            spawn(Box::pin(async move {
+                for idx in range {
+                    path.item(&locks, &data, idx).await;
+                }
+            }))
+        })
+        .collect();
+    for handle in handles {
+        handle.await;
+    }
+}
+
+fn fastest_locks(points: &[LockPoint]) -> Vec<FastestLock> {
+    let mut fastest: Vec<FastestLock> = Vec::new();
+    for point in points {
+        match fastest.iter_mut().find(|f| f.runtime == point.runtime && f.shards == point.shards) {
+            Some(best) if point.median < best.median => {
+                best.lock = point.lock;
+                best.median = point.median;
+            }
+            Some(_) => {}
+            None => fastest.push(FastestLock {
+                runtime: point.runtime,
+                shards: point.shards,
+                lock: point.lock,
+                median: point.median,
+            }),
+        }
+    }
+    fastest
+}
@@ -1,180 +1,341 @@
+mod adapter;
 mod async_check;
+mod channels;
+mod cli;
+mod collect;
+mod echo;
+mod export;
+mod locks;
+mod openloop;
+mod pipeline;
+mod report;
+mod results;
+mod significance;
+mod stats;
+mod sweep;
+mod timing;
+mod trace;
 mod parallel_check; 
+mod sequential_check;
 mod hybrid_check;
+mod actor_check;
 
-use crate::async_check::{benchmark_async_libraries, AsyncBenchmarkResult};
-use crate::parallel_check::{benchmark_parallel_libraries, ParallelBenchmarkResult};
-use crate::hybrid_check::{benchmark_hybrid_libraries, HybridBenchmarkResult};
+use clap::Parser;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::Path;
 use std::time::Duration;
 
-// Generic benchmark result for unified processing
-#[derive(Clone)]  // Add Clone trait
-struct BenchmarkResult {
-    category: String,
-    library: String,
-    best_time: Duration,
-    avg_time: Duration,
-    all_times: Vec<Duration>,
-}
-
-fn main() {
-    const DATA_SIZE: usize = 10000;
-    const ITERATIONS: usize = 5;
-    
-    println!("=== RUST CONCURRENCY LIBRARIES BENCHMARK ===");
-    println!("Benchmarking with {} data points, {} iterations each", DATA_SIZE, ITERATIONS);
-    println!("--------------------------------------------------------");
-    
-    // Run all the benchmarks
-    let async_results = async_std::task::block_on(
-        benchmark_async_libraries(DATA_SIZE, ITERATIONS)
-    );
-    
-    let parallel_results = benchmark_parallel_libraries(DATA_SIZE, ITERATIONS);
-    let hybrid_results = benchmark_hybrid_libraries(DATA_SIZE, ITERATIONS);
-    
-    // Combine all results for analysis
-    let mut all_results: Vec<BenchmarkResult> = Vec::new();
-    
-    // Process async results
-    for result in async_results {
-        all_results.push(BenchmarkResult {
-            category: "Asynchronous".to_string(),
-            library: result.library,
-            best_time: result.best_time,
-            avg_time: result.avg_time,
-            all_times: result.all_times,
-        });
-    }
-    
-    // Process parallel results
-    for result in parallel_results {
-        all_results.push(BenchmarkResult {
-            category: "Parallel".to_string(),
-            library: result.library,
-            best_time: result.best_time,
-            avg_time: result.avg_time,
-            all_times: result.all_times,
-        });
-    }
-    
-    // Process hybrid results
-    for result in hybrid_results {
-        all_results.push(BenchmarkResult {
-            category: "Hybrid".to_string(),
-            library: result.library,
-            best_time: result.best_time,
-            avg_time: result.avg_time,
-            all_times: result.all_times,
-        });
-    }
-    
-    // Find overall best performer
-    all_results.sort_by_key(|r| r.best_time);
-    let overall_best = &all_results[0];
-    
-    println!("\n=== OVERALL RESULTS ===");
-    println!("Best overall performer: {} ({}) with {:?}", 
-             overall_best.library, overall_best.category, overall_best.best_time);
-    println!("--------------------------------------------------------");
-    
-    // Calculate percentages relative to the best performer
-    let best_time_nanos = overall_best.best_time.as_nanos() as f64;
-    
-    // Output average times
-    println!("\n=== AVERAGE TIMES ===");
-    println!("{:<20} {:<20} {:<15} {:<15}", "Category", "Library", "Avg Time", "vs Best (%)");
-    println!("{:-<75}", "");
-    
-    let mut sorted_by_avg = all_results.clone();
-    sorted_by_avg.sort_by_key(|r| r.avg_time);
-    
-    for result in &sorted_by_avg {
-        let percent_slower = ((result.avg_time.as_nanos() as f64 / best_time_nanos) - 1.0) * 100.0;
-        println!("{:<20} {:<20} {:<15?} {:<15.2}%", 
-                 result.category, result.library, result.avg_time, percent_slower);
-    }
-        
-        // Output best times
-        println!("\n=== BEST TIMES ===");
-        println!("{:<20} {:<20} {:<15} {:<15}", "Category", "Library", "Best Time", "vs Best (%)");
-        println!("{:-<75}", "");
-        
-        for result in &all_results {  // already sorted by best_time
-            let percent_slower = ((result.best_time.as_nanos() as f64 / best_time_nanos) - 1.0) * 100.0;
-            println!("{:<20} {:<20} {:<15?} {:<15.2}%", 
-                     result.category, result.library, result.best_time, percent_slower);
-        }
-        
-        // Group results by category
-        println!("\n=== RESULTS BY CATEGORY ===");
-        
-        // Asynchronous category
-        println!("\n--- ASYNCHRONOUS LIBRARIES ---");
-        println!("{:<20} {:<15} {:<15} {:<15}", "Library", "Best Time", "Avg Time", "vs Category Best (%)");
-        println!("{:-<70}", "");
-        
-        let mut async_libs: Vec<&BenchmarkResult> = all_results.iter()
-            .filter(|r| r.category == "Asynchronous")
-            .collect();
-        async_libs.sort_by_key(|r| r.best_time);
-        
-        let async_best_time = if !async_libs.is_empty() {
-            async_libs[0].best_time.as_nanos() as f64
-        } else {
-            0.0
-        };
-        
-        for result in async_libs {
-            let percent_vs_category_best = ((result.best_time.as_nanos() as f64 / async_best_time) - 1.0) * 100.0;
-            println!("{:<20} {:<15?} {:<15?} {:<15.2}%", 
-                     result.library, result.best_time, result.avg_time, percent_vs_category_best);
-        }
-        
-        // Parallel category
-        println!("\n--- PARALLEL LIBRARIES ---");
-        println!("{:<20} {:<15} {:<15} {:<15}", "Library", "Best Time", "Avg Time", "vs Category Best (%)");
-        println!("{:-<70}", "");
-        
-        let mut parallel_libs: Vec<&BenchmarkResult> = all_results.iter()
-            .filter(|r| r.category == "Parallel")
-            .collect();
-        parallel_libs.sort_by_key(|r| r.best_time);
-        
-        let parallel_best_time = if !parallel_libs.is_empty() {
-            parallel_libs[0].best_time.as_nanos() as f64
-        } else {
-            0.0
-        };
-        
-        for result in parallel_libs {
-            let percent_vs_category_best = ((result.best_time.as_nanos() as f64 / parallel_best_time) - 1.0) * 100.0;
-            println!("{:<20} {:<15?} {:<15?} {:<15.2}%", 
-                     result.library, result.best_time, result.avg_time, percent_vs_category_best);
+use crate::adapter::{registry, Datasets};
+use crate::async_check::{benchmark_async_libraries, calibrate};
+use crate::parallel_check::benchmark_parallel_libraries;
+use crate::hybrid_check::benchmark_hybrid_libraries;
+use crate::actor_check::benchmark_actor_libraries;
+use crate::sequential_check::benchmark_sequential_baseline;
+use crate::cli::{
+    ChannelsArgs, Cli, CollectArgs, Command, CompareArgs, EchoArgs, LoadArgs, LocksArgs, PipelineArgs, RunArgs, ScaleArgs, Selection, SweepArgs,
+    ThreadsArgs,
+};
+use crate::channels::run_channels;
+use crate::echo::run_echo;
+use crate::locks::run_locks;
+use crate::openloop::{run_open_loop, MAX_ARRIVALS};
+use crate::pipeline::run_pipeline;
+use crate::export::{
+    emit, open_output, write_channels_csv, write_collection_csv, write_csv, write_echo_csv, write_json,
+    write_locks_csv, write_open_loop_csv, write_pipeline_csv, write_scaling_csv, write_sweep_csv,
+    write_thread_scaling_csv, OutputFormat,
+};
+use crate::report::{
+    write_channels, write_collection, write_comparison, write_echo, write_header, write_library_list,
+    write_locks, write_open_loop, write_pipeline, write_scaling, write_sweep, write_tables,
+    write_thread_scaling, Progress,
+};
+use crate::results::{BenchmarkRun, Category, WorkCost, Workload};
+use crate::significance::compare_all;
+use crate::sweep::{measure_one, run_collection, run_scaling, run_sweep, run_thread_scaling};
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let cli = Cli::parse();
+    
+    match cli.command.unwrap_or_else(|| Command::Run(RunArgs::default())) {
+        Command::Run(args) => run(args),
+        Command::List(selection) => list(&selection),
+        Command::Compare(args) => compare(&args),
+        Command::Calibrate => calibration(),
+        Command::Sweep(args) => sweep(&args),
+        Command::Scale(args) => scale(&args),
+        Command::Threads(args) => threads(&args),
+        Command::Collect(args) => collection(&args),
+        Command::Locks(args) => locks(&args),
+        Command::Channels(args) => channels(&args),
+        Command::Pipeline(args) => pipeline(&args),
+        Command::Echo(args) => echo(&args),
+        Command::Load(args) => load(&args),
+        Command::MeasureOne(args) => {
+            let result = measure_one(&args)?;
+            serde_json::to_writer(io::stdout().lock(), &result)?;
+            Ok(())
         }
-        
-        // Hybrid category
-        println!("\n--- HYBRID LIBRARIES ---");
-        println!("{:<20} {:<15} {:<15} {:<15}", "Library", "Best Time", "Avg Time", "vs Category Best (%)");
-        println!("{:-<70}", "");
-        
-        let mut hybrid_libs: Vec<&BenchmarkResult> = all_results.iter()
-            .filter(|r| r.category == "Hybrid")
-            .collect();
-        hybrid_libs.sort_by_key(|r| r.best_time);
-        
-        let hybrid_best_time = if !hybrid_libs.is_empty() {
-            hybrid_libs[0].best_time.as_nanos() as f64
-        } else {
-            0.0
-        };
-        
-        for result in hybrid_libs {
-            let percent_vs_category_best = ((result.best_time.as_nanos() as f64 / hybrid_best_time) - 1.0) * 100.0;
-            println!("{:<20} {:<15?} {:<15?} {:<15.2}%", 
-                     result.library, result.best_time, result.avg_time, percent_vs_category_best);
+    }
+}
+
+fn check_selection(selection: &Selection) -> Result<(), Box<dyn Error>> {
+    let unknown = selection.unknown_names(&registry());
+    if !unknown.is_empty() {
+        return Err(format!("unknown library name(s): {} (see `list`)", unknown.join(", ")).into());
+    }
+    Ok(())
+}
+
+// Work out the per-value cost, before anything is generated or timed
+fn work_cost(work_units: u64, task_cost: Option<Duration>) -> Result<WorkCost, Box<dyn Error>> {
+    if work_units == 0 {
+        return Err("--work-units must be greater than zero".into());
+    }
+    let ns_per_unit = calibrate();
+    let units = match task_cost {
+        Some(target) => ((target.as_nanos() as f64 / ns_per_unit).round() as u64).max(1),
+        None => work_units,
+    };
+    Ok(WorkCost { units, ns_per_unit })
+}
+
+fn run(args: RunArgs) -> Result<(), Box<dyn Error>> {
+    check_selection(&args.selection)?;
+    if args.size == 0 || args.iterations == 0 {
+        return Err("--size and --iterations must be greater than zero".into());
+    }
+    if !(args.alpha > 0.0 && args.alpha < 1.0) {
+        return Err("--alpha must be between 0 and 1".into());
+    }
+    
+    let cost = work_cost(args.work_units, args.task_cost)?;
+    timing::enable(args.task_timing);
+    trace::enable(args.trace.is_some());
+    
+    let seed = args.seed.unwrap_or_else(rand::random);
+    let mut workload = Workload::cpu_bound(args.size, args.iterations, args.warmup(), args.phase, seed, args.verify, cost);
+    workload.chunk_size = args.chunk_size.max(1);
+    workload.collection = args.collection;
+    if let Some(latency) = args.latency() {
+        workload = workload.simulated_io(latency);
+    }
+    let mut run = BenchmarkRun::new(workload);
+    
+    // Show the header up front when the tables are going to the terminal anyway
+    let live_table = args.format == OutputFormat::Table && args.output.is_none();
+    if live_table {
+        write_header(&run, &mut io::stdout())?;
+    }
+    let progress = if live_table { Progress::Stdout } else { Progress::Stderr };
+    
+    // Run all the selected benchmarks on the same inputs
+    let mut datasets = Datasets::generate(seed, args.size, args.iterations, cost);
+    if let Some(latency) = &run.workload.latency {
+        datasets = datasets.with_delays(seed, latency);
+    }
+    let selection = &args.selection;
+    run.results.extend(benchmark_sequential_baseline(&run.workload, &datasets, selection, progress));
+    if selection.includes_category(Category::Asynchronous) {
+        run.results.extend(benchmark_async_libraries(&run.workload, &datasets, selection, progress));
+    }
+    if selection.includes_category(Category::Parallel) {
+        run.results.extend(benchmark_parallel_libraries(&run.workload, &datasets, selection, progress));
+    }
+    if selection.includes_category(Category::Hybrid) {
+        run.results.extend(benchmark_hybrid_libraries(&run.workload, &datasets, selection, progress));
+    }
+    if selection.includes_category(Category::Actor) {
+        run.results.extend(benchmark_actor_libraries(&run.workload, &datasets, selection, progress));
+    }
+    // Libraries that produced wrong output aren't compared against anything
+    let verified: Vec<_> = run.results.iter().filter(|r| r.verification.passed).cloned().collect();
+    run.comparisons = compare_all(&verified, args.test, args.alpha);
+    
+    let mut out = open_output(args.output.as_deref())?;
+    
+    match args.format {
+        OutputFormat::Table => {
+            if !live_table {
+                write_header(&run, &mut out)?;
+            }
+            write_tables(&run, args.rank_by, &mut out)?;
         }
-        
-        println!("\n=== BENCHMARK COMPLETE ===");
-        println!("Note: These results are specific to CPU-bound workloads. Different workload types (e.g., I/O-bound) may yield different results.");
-    }
\ No newline at end of file
+        OutputFormat::Json => write_json(&run, &mut out)?,
+        OutputFormat::Csv => write_csv(&run, &mut out)?,
+    }
+    out.flush()?;
+    
+    if let Some(path) = &args.output {
+        eprintln!("Wrote {:?} report to {}", args.format, path.display());
+    }
+    if let Some(path) = &args.trace {
+        let mut trace_out = BufWriter::new(File::create(path)?);
+        trace::write_chrome_trace(&mut trace_out)?;
+        trace_out.flush()?;
+        eprintln!("Wrote Chrome trace to {}", path.display());
+    }
+    
+    Ok(())
+}
+
+fn list(selection: &Selection) -> Result<(), Box<dyn Error>> {
+    check_selection(selection)?;
+    let adapters: Vec<_> = registry()
+        .into_iter()
+        .filter(|adapter| selection.includes(adapter.as_ref()))
+        .collect();
+    write_library_list(&adapters, &mut io::stdout())?;
+    Ok(())
+}
+
+fn sweep(args: &SweepArgs) -> Result<(), Box<dyn Error>> {
+    check_selection(&args.selection)?;
+    if args.size == 0 || args.iterations == 0 || args.costs.is_empty() || args.chunk_sizes.is_empty() {
+        return Err("--size, --iterations, --costs and --chunk-sizes must not be zero or empty".into());
+    }
+    if !(args.efficiency > 0.0 && args.efficiency <= 1.0) {
+        return Err("--efficiency must be greater than 0 and at most 1".into());
+    }
+    
+    let sweep = run_sweep(args);
+    
+    emit(&sweep, args.format, args.output.as_deref(), "sweep", write_sweep, write_sweep_csv)
+}
+
+fn scale(args: &ScaleArgs) -> Result<(), Box<dyn Error>> {
+    check_selection(&args.selection)?;
+    if args.iterations == 0 {
+        return Err("--iterations must be greater than zero".into());
+    }
+    
+    let cost = work_cost(args.work_units, args.task_cost)?;
+    let scaling = run_scaling(args, cost);
+    
+    emit(&scaling, args.format, args.output.as_deref(), "scaling report", write_scaling, write_scaling_csv)
+}
+
+fn threads(args: &ThreadsArgs) -> Result<(), Box<dyn Error>> {
+    check_selection(&args.selection)?;
+    if args.size == 0 || args.iterations == 0 {
+        return Err("--size and --iterations must be greater than zero".into());
+    }
+    
+    let cost = work_cost(args.work_units, args.task_cost)?;
+    let scaling = run_thread_scaling(args, cost);
+    
+    emit(&scaling, args.format, args.output.as_deref(), "thread scaling report",
+         write_thread_scaling, write_thread_scaling_csv)
+}
+
+fn collection(args: &CollectArgs) -> Result<(), Box<dyn Error>> {
+    check_selection(&args.selection)?;
+    if args.size == 0 || args.iterations == 0 {
+        return Err("--size and --iterations must be greater than zero".into());
+    }
+    
+    let cost = work_cost(args.work_units, args.task_cost)?;
+    let matrix = run_collection(args, cost);
+    
+    emit(&matrix, args.format, args.output.as_deref(), "collection report", write_collection, write_collection_csv)
+}
+
+fn locks(args: &LocksArgs) -> Result<(), Box<dyn Error>> {
+    if args.size == 0 || args.iterations == 0 {
+        return Err("--size and --iterations must be greater than zero".into());
+    }
+    if args.locks.is_empty() || args.runtimes.is_empty() || args.shards.is_empty() {
+        return Err("--locks, --runtimes and --shards must not be empty".into());
+    }
+    
+    let cost = work_cost(args.work_units, args.task_cost)?;
+    let comparison = run_locks(args, cost);
+    
+    emit(&comparison, args.format, args.output.as_deref(), "lock comparison", write_locks, write_locks_csv)
+}
+
+fn channels(args: &ChannelsArgs) -> Result<(), Box<dyn Error>> {
+    if args.messages == 0 || args.iterations == 0 || args.producers == 0 || args.consumers == 0 {
+        return Err("--messages, --iterations, --producers and --consumers must be greater than zero".into());
+    }
+    if args.channels.is_empty() || args.topologies.is_empty() || args.capacities.is_empty() || args.flavors.is_empty() {
+        return Err("--channels, --topologies, --capacities and --flavors must not be empty".into());
+    }
+    
+    let matrix = run_channels(args);
+    
+    emit(&matrix, args.format, args.output.as_deref(), "channel matrix", write_channels, write_channels_csv)
+}
+
+fn pipeline(args: &PipelineArgs) -> Result<(), Box<dyn Error>> {
+    if args.items == 0 || args.iterations == 0 || args.depth == 0 {
+        return Err("--items, --iterations and --depth must be greater than zero".into());
+    }
+    if args.capacity == 0 || args.stage_workers == 0 || args.slow_factor == 0 || args.sample_interval.is_zero() {
+        return Err("--capacity, --stage-workers, --slow-factor and --sample-interval must be greater than zero".into());
+    }
+    if args.slow_stage.is_some_and(|stage| stage == 0 || stage > args.depth) {
+        return Err(format!("--slow-stage must be between 1 and --depth ({})", args.depth).into());
+    }
+    if args.runtimes.is_empty() {
+        return Err("--runtimes must not be empty".into());
+    }
+    
+    let cost = work_cost(args.work_units, args.task_cost)?;
+    let benchmark = run_pipeline(args, cost);
+    
+    emit(&benchmark, args.format, args.output.as_deref(), "pipeline report", write_pipeline, write_pipeline_csv)
+}
+
+fn echo(args: &EchoArgs) -> Result<(), Box<dyn Error>> {
+    check_selection(&args.selection)?;
+    if args.connections == 0 || args.message_size == 0 || args.duration.is_zero() {
+        return Err("--connections, --message-size and --duration must be greater than zero".into());
+    }
+    
+    let benchmark = run_echo(args)?;
+    
+    emit(&benchmark, args.format, args.output.as_deref(), "echo report", write_echo, write_echo_csv)
+}
+
+fn load(args: &LoadArgs) -> Result<(), Box<dyn Error>> {
+    check_selection(&args.selection)?;
+    if args.rates.is_empty() || args.rates.iter().any(|&rate| rate.is_nan() || rate <= 0.0) || args.duration.is_zero() {
+        return Err("--rates must all be positive and --duration greater than zero".into());
+    }
+    if args.rates.iter().any(|&rate| rate * args.duration.as_secs_f64() > MAX_ARRIVALS) {
+        return Err(format!("--rates times --duration must not exceed {} arrivals per rate", MAX_ARRIVALS).into());
+    }
+    
+    let cost = work_cost(args.work_units, args.task_cost)?;
+    let open_loop = run_open_loop(args, cost);
+    
+    emit(&open_loop, args.format, args.output.as_deref(), "load report", write_open_loop, write_open_loop_csv)
+}
+
+fn calibration() -> Result<(), Box<dyn Error>> {
+    let ns_per_unit = calibrate();
+    println!("One work unit takes {:.3}ns on this machine", ns_per_unit);
+    println!("{:<15} {:<15}", "Task cost", "--work-units");
+    println!("{:-<30}", "");
+    for target_ns in [50.0, 1e3, 1e4, 1e5, 1e6, 1e7] {
+        let units = ((target_ns / ns_per_unit).round() as u64).max(1);
+        println!("{:<15?} {:<15}", Duration::from_nanos(target_ns as u64), units);
+    }
+    Ok(())
+}
+
+fn load_run(path: &Path) -> Result<BenchmarkRun, Box<dyn Error>> {
+    let file = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    serde_json::from_reader(BufReader::new(file))
+        .map_err(|e| format!("{}: {}", path.display(), e).into())
+}
+
+fn compare(args: &CompareArgs) -> Result<(), Box<dyn Error>> {
+    let baseline = load_run(&args.baseline)?;
+    let candidate = load_run(&args.candidate)?;
+    write_comparison(&baseline, &candidate, &mut io::stdout())?;
+    Ok(())
+}
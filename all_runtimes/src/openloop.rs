@@ -0,0 +1,233 @@
+use clap::ValueEnum;
+use hdrhistogram::Histogram;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use crate::adapter::{registry, Job};
+use crate::async_check::process_value;
+use crate::cli::LoadArgs;
+use crate::results::{nanos, Category, RunMetadata, WorkCost};
+
+// Largest latency the histograms can hold; anything slower is clamped to it
+const MAX_LATENCY_NS: u64 = 600_000_000_000;
+
+// Share of the offered rate a library has to complete for the rate to count as sustained
+pub const SUSTAINED_SHARE: f64 = 0.95;
+
+// Most arrivals one rate may schedule. The whole schedule is drawn up front, so
+// this keeps a typo in --rates or --duration from exhausting memory.
+pub const MAX_ARRIVALS: f64 = 5_000_000.0;
+
+// How arrivals are spaced at a given rate
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum Arrivals {
+    // Evenly spaced, 1 / rate apart
+    Constant,
+    // Exponentially distributed gaps with mean 1 / rate
+    Poisson,
+}
+
+impl Arrivals {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Arrivals::Constant => "constant",
+            Arrivals::Poisson => "poisson",
+        }
+    }
+}
+
+// One library at one offered rate. Latencies run from when an item was meant to
+// arrive, not when the generator got round to submitting it, so a generator that
+// falls behind doesn't hide queueing (coordinated omission).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LoadPoint {
+    pub category: Category,
+    pub library: String,
+    pub offered_rate: f64,
+    // Items completed per second, up to the last completion. Items a library
+    // dropped instead of running don't count.
+    pub achieved_rate: f64,
+    pub items: usize,
+    #[serde(with = "nanos")]
+    pub p50: Duration,
+    #[serde(with = "nanos")]
+    pub p99: Duration,
+    #[serde(with = "nanos")]
+    pub p999: Duration,
+    #[serde(with = "nanos")]
+    pub max: Duration,
+    // p99 measured from actual submission, as a closed-loop tool would report it
+    #[serde(with = "nanos")]
+    pub uncorrected_p99: Duration,
+    pub sustained: bool,
+}
+
+// The highest rate a library kept up with, and the first it didn't
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LoadSaturation {
+    pub category: Category,
+    pub library: String,
+    pub max_sustained_rate: Option<f64>,
+    pub saturated_at: Option<f64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OpenLoop {
+    pub metadata: RunMetadata,
+    pub arrivals: Arrivals,
+    #[serde(with = "nanos")]
+    pub duration: Duration,
+    pub seed: u64,
+    pub cost: WorkCost,
+    pub rates: Vec<f64>,
+    pub points: Vec<LoadPoint>,
+    pub saturation: Vec<LoadSaturation>,
+    // Libraries that can't take work from outside a run, left out
+    pub unsupported: Vec<String>,
+}
+
+// Offer every selected library each rate in turn, stopping at the first rate it
+// can't sustain
+pub fn run_open_loop(args: &LoadArgs, cost: WorkCost) -> OpenLoop {
+    let seed = args.seed.unwrap_or_else(rand::random);
+    let mut rates = args.rates.clone();
+    rates.sort_by(f64::total_cmp);
+
+    let mut points = Vec::new();
+    let mut saturation = Vec::new();
+    let mut unsupported = Vec::new();
+
+    let adapters = registry()
+        .into_iter()
+        .filter(|adapter| adapter.category() != Category::Sequential && args.selection.includes(adapter.as_ref()));
+
+    for mut adapter in adapters {
+        let mut summary = LoadSaturation {
+            category: adapter.category(),
+            library: adapter.name().to_string(),
+            max_sustained_rate: None,
+            saturated_at: None,
+        };
+
+        let mut supported = true;
+        for &rate in &rates {
+            adapter.build();
+            let Some(submit) = adapter.submitter() else {
+                adapter.teardown();
+                supported = false;
+                break;
+            };
+            eprintln!("Offering {} {:.0} items/s...", adapter.name(), rate);
+
+            let schedule = schedule(rate, args.duration, args.arrivals, seed);
+            let (corrected, uncorrected, last_done) = offer(&schedule, submit.as_ref(), cost, seed);
+            let completed = corrected.len();
+            drop(submit);
+            adapter.teardown();
+
+            // Judged against the items actually drawn for the window, not the nominal
+            // rate, so Poisson noise in the arrival count doesn't decide it
+            let achieved_rate = completed as f64 / last_done.as_secs_f64().max(1e-9);
+            let drawn_rate = schedule.len() as f64 / args.duration.as_secs_f64();
+            let sustained = achieved_rate >= drawn_rate * SUSTAINED_SHARE;
+            let quantile = |q: f64| Duration::from_nanos(corrected.value_at_quantile(q));
+            points.push(LoadPoint {
+                category: adapter.category(),
+                library: adapter.name().to_string(),
+                offered_rate: rate,
+                achieved_rate,
+                items: schedule.len(),
+                p50: quantile(0.5),
+                p99: quantile(0.99),
+                p999: quantile(0.999),
+                max: Duration::from_nanos(corrected.max()),
+                uncorrected_p99: Duration::from_nanos(uncorrected.value_at_quantile(0.99)),
+                sustained,
+            });
+
+            if !sustained {
+                summary.saturated_at = Some(rate);
+                break;
+            }
+            summary.max_sustained_rate = Some(rate);
+        }
+
+        if supported {
+            saturation.push(summary);
+        } else {
+            unsupported.push(summary.library);
+        }
+    }
+
+    OpenLoop {
+        metadata: RunMetadata::collect(),
+        arrivals: args.arrivals,
+        duration: args.duration,
+        seed,
+        cost,
+        rates,
+        points,
+        saturation,
+        unsupported,
+    }
+}
+
+// When each item is meant to arrive, relative to the start of the step
+fn schedule(rate: f64, duration: Duration, arrivals: Arrivals, seed: u64) -> Vec<Duration> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let gap = 1.0 / rate;
+    let mut at = 0.0;
+    let mut schedule = Vec::new();
+    while at < duration.as_secs_f64() {
+        schedule.push(Duration::from_secs_f64(at));
+        at += match arrivals {
+            Arrivals::Constant => gap,
+            Arrivals::Poisson => -gap * (1.0 - rng.random::<f64>()).ln(),
+        };
+    }
+    schedule
+}
+
+// Submit every item at its scheduled time from this thread, then wait for all of
+// them to finish, or for the library to drop the rest. Returns latency from the
+// intended and from the actual submission time, one entry per completed item,
+// and when the last item finished.
+fn offer(schedule: &[Duration], submit: &dyn Fn(Job), cost: WorkCost,
+         seed: u64) -> (Histogram<u64>, Histogram<u64>, Duration) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let (done, completions) = flume::unbounded();
+    let mut submitted = Vec::with_capacity(schedule.len());
+
+    let start = Instant::now();
+    for (idx, &offset) in schedule.iter().enumerate() {
+        let intended = start + offset;
+        let now = Instant::now();
+        if intended > now {
+            std::thread::sleep(intended - now);
+        }
+        submitted.push(Instant::now());
+
+        let value: u32 = rng.random_range(0..10000);
+        let done = done.clone();
+        submit(Box::new(move || {
+            black_box(process_value(value, cost));
+            let _ = done.send((idx, Instant::now()));
+        }));
+    }
+    drop(done);
+
+    let mut corrected = Histogram::<u64>::new_with_bounds(1, MAX_LATENCY_NS, 3).unwrap();
+    let mut uncorrected = Histogram::<u64>::new_with_bounds(1, MAX_LATENCY_NS, 3).unwrap();
+    let mut last_done = Duration::ZERO;
+    for (idx, finished) in completions.iter().take(schedule.len()) {
+        let intended = start + schedule[idx];
+        corrected.saturating_record((finished - intended).as_nanos() as u64);
+        uncorrected.saturating_record((finished - submitted[idx]).as_nanos() as u64);
+        last_done = last_done.max(finished - start);
+    }
+    (corrected, uncorrected, last_done)
+}
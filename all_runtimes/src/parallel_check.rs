@@ -1,143 +1,185 @@
-use rand::{thread_rng, Rng};
-use std::sync::{Arc, Mutex};
-use std::time::{Duration, Instant};
-
-// Import the processing function from the async module
-use crate::async_check::process_value;
-
-// Results structure to collect benchmark data
-pub struct ParallelBenchmarkResult {
-    pub library: String,
-    pub best_time: Duration,
-    pub avg_time: Duration,
-    pub all_times: Vec<Duration>,
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::adapter::{
+    chunk_ranges, process_range, run_category, Datasets, RuntimeAdapter, Submitter,
+};
+use crate::cli::Selection;
+use crate::collect::{Collection, Results};
+use crate::report::Progress;
+use crate::results::{BenchmarkResult, Category, Workload};
+use crate::timing;
+
+const CATEGORY: Category = Category::Parallel;
+
+pub fn adapters() -> Vec<Box<dyn RuntimeAdapter>> {
+    vec![
+        Box::new(RayonAdapter::default()),
+        Box::new(StdThreadAdapter),
+        Box::new(CrossbeamAdapter),
+    ]
 }
 
-// Main function to benchmark parallel libraries
-pub fn benchmark_parallel_libraries(data_size: usize, iterations: usize) -> Vec<ParallelBenchmarkResult> {
-    println!("Starting parallel library benchmarks...");
-    
-    let mut results = Vec::new();
-    
-    // Benchmark Rayon
-    let mut rayon_times = Vec::with_capacity(iterations);
-    let mut rayon_best = Duration::from_secs(u64::MAX);
-
-    for _ in 0..iterations {
-        // Generate random data
-        let mut rng = thread_rng();
-        let data: Vec<u32> = (0..data_size).map(|_| rng.gen_range(0..10000)).collect();
-        let data_arc = Arc::new(data);
-        
-        let start = Instant::now();
-        let results = Arc::new(Mutex::new(vec![0; data_arc.len()]));
-        
-        rayon::scope(|s| {
-            for (idx, &value) in data_arc.iter().enumerate() {
-                let results = results.clone();
+// Rayon pool with `workers` threads, or Rayon's default of one per CPU
+pub fn rayon_pool(workers: Option<usize>) -> rayon::ThreadPool {
+    let mut builder = rayon::ThreadPoolBuilder::new();
+    if let Some(workers) = workers {
+        builder = builder.num_threads(workers);
+    }
+    builder.build().unwrap()
+}
+
+// Dedicated Rayon pool, one scoped task per chunk. The pool is built per
+// iteration rather than using the global one so its startup cost is measured.
+#[derive(Default)]
+pub struct RayonAdapter {
+    workers: Option<usize>,
+    pool: Option<rayon::ThreadPool>,
+}
+
+impl RuntimeAdapter for RayonAdapter {
+    fn name(&self) -> &'static str {
+        "Rayon"
+    }
+
+    fn category(&self) -> Category {
+        CATEGORY
+    }
+
+    fn threads(&self) -> usize {
+        self.workers.unwrap_or_else(num_cpus::get)
+    }
+
+    fn set_workers(&mut self, workers: usize) -> bool {
+        self.workers = Some(workers);
+        true
+    }
+
+    fn build(&mut self) {
+        self.pool = Some(rayon_pool(self.workers));
+    }
+
+    // With delays, each wait holds a pool thread, so at most `threads()` values wait at once
+    fn run(&mut self, data: Arc<Vec<u32>>, delays: Option<Arc<[Duration]>>, workload: &Workload) -> Option<Vec<u32>> {
+        let cost = workload.cost;
+        let pool = self.pool.as_ref().expect("Rayon pool not built");
+        let results = Arc::new(Results::new(data.len(), workload.collection));
+
+        pool.scope(|s| {
+            for range in chunk_ranges(data.len(), workload.chunk_size) {
+                let data = &data;
+                let delays = &delays;
+                let results = &results;
+                let spawned = timing::spawned();
                 s.spawn(move |_| {
-                    let processed = process_value(value);
-                    let mut results_guard = results.lock().unwrap();
-                    results_guard[idx] = processed;
+                    let _task = spawned.start();
+                    process_range(data, delays.as_deref(), range, cost, results);
                 });
             }
         });
-        
-        let duration = start.elapsed();
-        if duration < rayon_best {
-            rayon_best = duration;
-        }
-        rayon_times.push(duration);
-    }
-    
-    let rayon_avg = rayon_times.iter().sum::<Duration>() / rayon_times.len() as u32;
-    results.push(ParallelBenchmarkResult {
-        library: "Rayon".to_string(),
-        best_time: rayon_best,
-        avg_time: rayon_avg,
-        all_times: rayon_times,
-    });
-    
-    // Benchmark std::thread
-    let mut std_thread_times = Vec::with_capacity(iterations);
-    let mut std_thread_best = Duration::from_secs(u64::MAX);
-    
-    for _ in 0..iterations {
-        let mut rng = thread_rng();
-        let data: Vec<u32> = (0..data_size).map(|_| rng.gen_range(0..10000)).collect();
-        let data_arc = Arc::new(data);
-        
-        let start = Instant::now();
-        let results = Arc::new(Mutex::new(vec![0; data_arc.len()]));
+
+        Some(results.take())
+    }
+
+    fn submitter(&self) -> Option<Submitter<'_>> {
+        let pool = self.pool.as_ref()?;
+        Some(Box::new(move |job| pool.spawn(job)))
+    }
+
+    fn teardown(&mut self) {
+        self.pool = None;
+    }
+}
+
+// One OS thread per chunk
+pub struct StdThreadAdapter;
+
+impl RuntimeAdapter for StdThreadAdapter {
+    fn name(&self) -> &'static str {
+        "std::thread"
+    }
+
+    fn category(&self) -> Category {
+        CATEGORY
+    }
+
+    fn run(&mut self, data: Arc<Vec<u32>>, delays: Option<Arc<[Duration]>>, workload: &Workload) -> Option<Vec<u32>> {
+        let cost = workload.cost;
+        let results = Arc::new(Results::new(data.len(), workload.collection));
         let mut handles = Vec::new();
-        
-        for (idx, &value) in data_arc.iter().enumerate() {
+
+        for range in chunk_ranges(data.len(), workload.chunk_size) {
+            let data = data.clone();
+            let delays = delays.clone();
             let results_clone = results.clone();
+            let spawned = timing::spawned();
             let handle = std::thread::spawn(move || {
-                let processed = process_value(value);
-                let mut results = results_clone.lock().unwrap();
-                results[idx] = processed;
+                let _task = spawned.start();
+                process_range(&data, delays.as_deref(), range, cost, &results_clone)
             });
             handles.push(handle);
         }
-        
+
         for handle in handles {
-            let _ = handle.join().unwrap();
+            results.returned(handle.join().unwrap());
         }
-        
-        let duration = start.elapsed();
-        if duration < std_thread_best {
-            std_thread_best = duration;
-        }
-        std_thread_times.push(duration);
-    }
-    
-    let std_thread_avg = std_thread_times.iter().sum::<Duration>() / std_thread_times.len() as u32;
-    results.push(ParallelBenchmarkResult {
-        library: "std::thread".to_string(),
-        best_time: std_thread_best,
-        avg_time: std_thread_avg,
-        all_times: std_thread_times,
-    });
-    
-    // Benchmark crossbeam
-    let mut crossbeam_times = Vec::with_capacity(iterations);
-    let mut crossbeam_best = Duration::from_secs(u64::MAX);
-    
-    for _ in 0..iterations {
-        let mut rng = thread_rng();
-        let data: Vec<u32> = (0..data_size).map(|_| rng.gen_range(0..10000)).collect();
-        let data_arc = Arc::new(data);
-        
-        let start = Instant::now();
-        let results = Arc::new(Mutex::new(vec![0; data_arc.len()]));
-        
+
+        Some(results.take())
+    }
+
+    fn supports_collection(&self, _collection: Collection) -> bool {
+        true
+    }
+
+    // A fresh thread per job
+    fn submitter(&self) -> Option<Submitter<'_>> {
+        Some(Box::new(|job| {
+            std::thread::spawn(job);
+        }))
+    }
+}
+
+// One crossbeam scoped thread per chunk
+pub struct CrossbeamAdapter;
+
+impl RuntimeAdapter for CrossbeamAdapter {
+    fn name(&self) -> &'static str {
+        "Crossbeam"
+    }
+
+    fn category(&self) -> Category {
+        CATEGORY
+    }
+
+    fn run(&mut self, data: Arc<Vec<u32>>, delays: Option<Arc<[Duration]>>, workload: &Workload) -> Option<Vec<u32>> {
+        let cost = workload.cost;
+        let results = Arc::new(Results::new(data.len(), workload.collection));
+
         crossbeam::scope(|scope| {
-            for (idx, &value) in data_arc.iter().enumerate() {
-                let results = results.clone();
+            for range in chunk_ranges(data.len(), workload.chunk_size) {
+                let data = &data;
+                let delays = &delays;
+                let results = &results;
+                let spawned = timing::spawned();
                 scope.spawn(move |_| {
-                    let processed = process_value(value);
-                    let mut results_guard = results.lock().unwrap();
-                    results_guard[idx] = processed;
+                    let _task = spawned.start();
+                    process_range(data, delays.as_deref(), range, cost, results);
                 });
             }
-        }).unwrap();
-        
-        let duration = start.elapsed();
-        if duration < crossbeam_best {
-            crossbeam_best = duration;
-        }
-        crossbeam_times.push(duration);
-    }
-    
-    let crossbeam_avg = crossbeam_times.iter().sum::<Duration>() / crossbeam_times.len() as u32;
-    results.push(ParallelBenchmarkResult {
-        library: "Crossbeam".to_string(),
-        best_time: crossbeam_best,
-        avg_time: crossbeam_avg,
-        all_times: crossbeam_times,
-    });
-    
-    println!("Parallel library benchmarks completed.");
+        })
+        .unwrap();
+
+        Some(results.take())
+    }
+}
+
+// Main function to benchmark parallel libraries
+pub fn benchmark_parallel_libraries(workload: &Workload, datasets: &Datasets, selection: &Selection,
+                                    progress: Progress) -> Vec<BenchmarkResult> {
+    progress.line("Starting parallel library benchmarks...");
+
+    let results = run_category(CATEGORY, workload, datasets, selection);
+
+    progress.line("Parallel library benchmarks completed.");
     results
-}
\ No newline at end of file
+}
@@ -0,0 +1,479 @@
+use clap::ValueEnum;
+use hdrhistogram::Histogram;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::adapter::Datasets;
+use crate::async_check::{smol_executor, tokio_runtime, work, SmolExecutor};
+use crate::cli::PipelineArgs;
+use crate::parallel_check::rayon_pool;
+use crate::results::{nanos, RunMetadata, WorkCost};
+
+// What the stages run on
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum PipelineRuntime {
+    // One OS thread per stage worker
+    Threads,
+    // One Tokio task per stage worker
+    Tokio,
+    // One task per stage worker on a smol executor with a thread per CPU
+    Smol,
+    // One job per stage worker on a Rayon pool with a thread for each
+    Rayon,
+}
+
+impl PipelineRuntime {
+    pub const ALL: [PipelineRuntime; 4] =
+        [PipelineRuntime::Threads, PipelineRuntime::Tokio, PipelineRuntime::Smol, PipelineRuntime::Rayon];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PipelineRuntime::Threads => "threads",
+            PipelineRuntime::Tokio => "Tokio",
+            PipelineRuntime::Smol => "smol",
+            PipelineRuntime::Rayon => "Rayon",
+        }
+    }
+}
+
+// Name of stage `stage` in a pipeline of `depth`: parse, transform(s), aggregate
+pub fn stage_name(stage: usize, depth: usize) -> String {
+    if stage + 1 == depth {
+        "aggregate".to_string()
+    } else if stage == 0 {
+        "parse".to_string()
+    } else if depth == 3 {
+        "transform".to_string()
+    } else {
+        format!("transform {}", stage)
+    }
+}
+
+// Items the source has handed in and the aggregate stage hasn't taken out yet,
+// and the most there were at once, over one run
+#[derive(Default)]
+struct InFlight {
+    now: AtomicUsize,
+    max: AtomicUsize,
+}
+
+// What travels down the pipeline
+struct Item {
+    value: u32,
+    // When the source started handing it in
+    entered: Instant,
+}
+
+// One worker's view of its stage
+struct Stage {
+    units: u64,
+    input: flume::Receiver<Item>,
+    // None for the aggregate stage
+    output: Option<flume::Sender<Item>>,
+    in_flight: Arc<InFlight>,
+}
+
+// What the aggregate stage's workers saw; empty for every other stage
+struct Tally {
+    items: u64,
+    sum: u64,
+    latencies: Histogram<u64>,
+}
+
+impl Tally {
+    fn new() -> Self {
+        Tally { items: 0, sum: 0, latencies: Histogram::new(3).unwrap() }
+    }
+
+    fn merge(&mut self, other: Tally) {
+        self.items += other.items;
+        self.sum += other.sum;
+        self.latencies.add(other.latencies).unwrap();
+    }
+}
+
+impl Stage {
+    // Pass one item on, or fold it into the tally at the end of the pipeline.
+    // Returns it when the next stage wants it.
+    fn process(&self, mut item: Item, tally: &mut Tally) -> Option<Item> {
+        item.value = work(item.value, self.units);
+        if self.output.is_some() {
+            return Some(item);
+        }
+        tally.items += 1;
+        tally.sum += item.value as u64;
+        tally.latencies.record(item.entered.elapsed().as_nanos() as u64).unwrap();
+        self.in_flight.now.fetch_sub(1, Ordering::Relaxed);
+        None
+    }
+
+    fn run_blocking(self) -> Tally {
+        let mut tally = Tally::new();
+        while let Ok(item) = self.input.recv() {
+            if let Some(item) = self.process(item, &mut tally) {
+                // Blocks while the next stage's queue is full
+                self.output.as_ref().unwrap().send(item).unwrap();
+            }
+        }
+        tally
+    }
+
+    async fn run(self) -> Tally {
+        let mut tally = Tally::new();
+        while let Ok(item) = self.input.recv_async().await {
+            if let Some(item) = self.process(item, &mut tally) {
+                // Suspends while the next stage's queue is full
+                self.output.as_ref().unwrap().send_async(item).await.unwrap();
+            }
+        }
+        tally
+    }
+}
+
+// How full one queue was over a run. Queue `i` feeds stage `i`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct QueueStats {
+    pub stage: String,
+    pub mean: f64,
+    pub max: usize,
+    // Share of samples where it was at capacity, holding up the stage before it
+    pub full_share: f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OccupancySample {
+    #[serde(with = "nanos")]
+    pub at: Duration,
+    // Items waiting in each queue
+    pub queues: Vec<usize>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PipelineResult {
+    pub runtime: PipelineRuntime,
+    // From the first item handed in to the last one aggregated
+    #[serde(with = "nanos")]
+    pub median: Duration,
+    pub items_per_sec: f64,
+    // End to end, from the source to the aggregate stage, over every timed run
+    #[serde(with = "nanos")]
+    pub p50: Duration,
+    #[serde(with = "nanos")]
+    pub p99: Duration,
+    #[serde(with = "nanos")]
+    pub max: Duration,
+    // Of the median run
+    pub queues: Vec<QueueStats>,
+    pub timeline: Vec<OccupancySample>,
+    // Most items between source and aggregate at once, over every timed run
+    pub max_in_flight: usize,
+    // Whether that stayed within what the queues and workers can hold
+    pub backpressure_held: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PipelineBenchmark {
+    pub metadata: RunMetadata,
+    pub items: usize,
+    pub iterations: usize,
+    pub seed: u64,
+    pub cost: WorkCost,
+    pub stages: Vec<String>,
+    pub capacity: usize,
+    pub stage_workers: usize,
+    // Index into `stages` of the stage doing `slow_factor` times the work, if any
+    pub slow_stage: Option<usize>,
+    pub slow_factor: u64,
+    #[serde(with = "nanos")]
+    pub sample_interval: Duration,
+    // Every queue full plus one item in each worker's hands and one in the source's
+    pub in_flight_bound: usize,
+    pub results: Vec<PipelineResult>,
+}
+
+// One pass of every item through the pipeline
+struct Run {
+    elapsed: Duration,
+    tally: Tally,
+    samples: Vec<OccupancySample>,
+    max_in_flight: usize,
+}
+
+// Feed every item through the pipeline under each selected runtime. Every stage
+// does the configured work per item; the slow one, if any, `slow_factor` times it.
+pub fn run_pipeline(args: &PipelineArgs, cost: WorkCost) -> PipelineBenchmark {
+    let seed = args.seed.unwrap_or_else(rand::random);
+    let datasets = Datasets::generate(seed, args.items, args.iterations, cost);
+    let slow_stage = args.slow_stage.map(|stage| stage - 1);
+    let units: Vec<u64> = (0..args.depth)
+        .map(|stage| if Some(stage) == slow_stage { cost.units * args.slow_factor } else { cost.units })
+        .collect();
+    let in_flight_bound = args.depth * (args.capacity + args.stage_workers) + 1;
+    let mut results = Vec::new();
+
+    for &runtime in &args.runtimes {
+        eprintln!("Running the pipeline on {}...", runtime.as_str());
+        let engine = match runtime {
+            PipelineRuntime::Threads => Engine::Threads,
+            PipelineRuntime::Tokio => Engine::Tokio(tokio_runtime(None)),
+            PipelineRuntime::Smol => Engine::Smol(smol_executor(num_cpus::get())),
+            // Every worker blocks on its queues, so each needs a thread of its own
+            PipelineRuntime::Rayon => Engine::Rayon(rayon_pool(Some(args.depth * args.stage_workers))),
+        };
+
+        for i in 0..args.warmup {
+            engine.run(&datasets.get(i), &units, args);
+        }
+        let mut runs = Vec::new();
+        let mut wrong = None;
+        for i in 0..args.iterations {
+            let data = datasets.get(i);
+            let run = engine.run(&data, &units, args);
+            let expected: u64 = data.iter().map(|&value| units.iter().fold(value, |v, &u| work(v, u)) as u64).sum();
+            if run.tally.items != data.len() as u64 || run.tally.sum != expected {
+                wrong.get_or_insert(format!("aggregated {} of {} items", run.tally.items, data.len()));
+            }
+            runs.push(run);
+        }
+        if let Engine::Smol(executor) = engine {
+            executor.shutdown();
+        }
+        if let Some(detail) = wrong {
+            eprintln!("Pipeline on {} lost or corrupted items, skipped: {}", runtime.as_str(), detail);
+            continue;
+        }
+
+        let mut latencies = Histogram::<u64>::new(3).unwrap();
+        for run in &runs {
+            latencies.add(&run.tally.latencies).unwrap();
+        }
+        let max_in_flight = runs.iter().map(|run| run.max_in_flight).max().unwrap_or(0);
+        runs.sort_by_key(|run| run.elapsed);
+        let median_run = runs.swap_remove(runs.len() / 2);
+        if max_in_flight > in_flight_bound {
+            eprintln!("Pipeline on {} held {} items at once, more than its queues allow ({}): no backpressure",
+                      runtime.as_str(), max_in_flight, in_flight_bound);
+        }
+        results.push(PipelineResult {
+            runtime,
+            median: median_run.elapsed,
+            items_per_sec: args.items as f64 / median_run.elapsed.as_secs_f64().max(1e-9),
+            p50: Duration::from_nanos(latencies.value_at_quantile(0.5)),
+            p99: Duration::from_nanos(latencies.value_at_quantile(0.99)),
+            max: Duration::from_nanos(latencies.max()),
+            queues: queue_stats(&median_run.samples, args.depth, args.capacity),
+            timeline: median_run.samples,
+            max_in_flight,
+            backpressure_held: max_in_flight <= in_flight_bound,
+        });
+    }
+
+    PipelineBenchmark {
+        metadata: RunMetadata::collect(),
+        items: args.items,
+        iterations: args.iterations,
+        seed,
+        cost,
+        stages: (0..args.depth).map(|stage| stage_name(stage, args.depth)).collect(),
+        capacity: args.capacity,
+        stage_workers: args.stage_workers,
+        slow_stage,
+        slow_factor: args.slow_factor,
+        sample_interval: args.sample_interval,
+        in_flight_bound,
+        results,
+    }
+}
+
+// A runtime, built once and reused for every run
+enum Engine {
+    Threads,
+    Tokio(tokio::runtime::Runtime),
+    Smol(SmolExecutor),
+    Rayon(rayon::ThreadPool),
+}
+
+impl Engine {
+    // Start every stage worker, feed the items in from this thread as fast as the
+    // first queue takes them, and wait for the aggregate stage to drain
+    fn run(&self, data: &[u32], units: &[u64], args: &PipelineArgs) -> Run {
+        let depth = units.len();
+        let (senders, receivers): (Vec<_>, Vec<_>) = (0..depth).map(|_| flume::bounded::<Item>(args.capacity)).unzip();
+        let in_flight = Arc::new(InFlight::default());
+        let mut stages = Vec::new();
+        for (stage, &units) in units.iter().enumerate() {
+            for _ in 0..args.stage_workers {
+                stages.push(Stage {
+                    units,
+                    input: receivers[stage].clone(),
+                    output: senders.get(stage + 1).cloned(),
+                    in_flight: in_flight.clone(),
+                });
+            }
+        }
+        let source = senders[0].clone();
+        // Each queue now closes once the workers feeding it are done
+        drop(senders);
+
+        let done = Arc::new(AtomicBool::new(false));
+        let start = Instant::now();
+        let sampler = sample_queues(receivers, args.sample_interval, done.clone(), start);
+
+        let tallies: Vec<Tally> = match self {
+            Engine::Threads => {
+                let workers: Vec<JoinHandle<Tally>> =
+                    stages.into_iter().map(|stage| std::thread::spawn(move || stage.run_blocking())).collect();
+                feed(&source, data, &in_flight);
+                drop(source);
+                workers.into_iter().map(|worker| worker.join().unwrap()).collect()
+            }
+            Engine::Tokio(runtime) => {
+                let workers: Vec<_> = stages.into_iter().map(|stage| runtime.spawn(stage.run())).collect();
+                feed(&source, data, &in_flight);
+                drop(source);
+                runtime.block_on(async {
+                    let mut tallies = Vec::new();
+                    for worker in workers {
+                        tallies.push(worker.await.unwrap());
+                    }
+                    tallies
+                })
+            }
+            Engine::Smol(executor) => {
+                let workers: Vec<_> = stages.into_iter().map(|stage| executor.executor.spawn(stage.run())).collect();
+                feed(&source, data, &in_flight);
+                drop(source);
+                smol::block_on(async {
+                    let mut tallies = Vec::new();
+                    for worker in workers {
+                        tallies.push(worker.await);
+                    }
+                    tallies
+                })
+            }
+            Engine::Rayon(pool) => {
+                let (tally_sender, tally_receiver) = flume::unbounded();
+                let workers = stages.len();
+                for stage in stages {
+                    let tally_sender = tally_sender.clone();
+                    pool.spawn(move || tally_sender.send(stage.run_blocking()).unwrap());
+                }
+                feed(&source, data, &in_flight);
+                drop(source);
+                tally_receiver.iter().take(workers).collect()
+            }
+        };
+
+        let elapsed = start.elapsed();
+        done.store(true, Ordering::Relaxed);
+        let mut tally = Tally::new();
+        for other in tallies {
+            tally.merge(other);
+        }
+        let max_in_flight = in_flight.max.load(Ordering::Relaxed);
+        Run { elapsed, tally, samples: sampler.join().unwrap(), max_in_flight }
+    }
+}
+
+// Hand every item to the first stage, counting it in flight from before the send
+// so an item stuck waiting for room counts too
+fn feed(source: &flume::Sender<Item>, data: &[u32], in_flight: &InFlight) {
+    for &value in data {
+        let now = in_flight.now.fetch_add(1, Ordering::Relaxed) + 1;
+        in_flight.max.fetch_max(now, Ordering::Relaxed);
+        source.send(Item { value, entered: Instant::now() }).unwrap();
+    }
+}
+
+// Look at how many items wait in each queue every `interval` until `done`. Holding
+// receivers doesn't keep a queue open, so the stages still see it close.
+fn sample_queues(queues: Vec<flume::Receiver<Item>>, interval: Duration, done: Arc<AtomicBool>,
+                 start: Instant) -> JoinHandle<Vec<OccupancySample>> {
+    std::thread::spawn(move || {
+        let mut samples = Vec::new();
+        while !done.load(Ordering::Relaxed) {
+            samples.push(OccupancySample { at: start.elapsed(), queues: queues.iter().map(|q| q.len()).collect() });
+            std::thread::sleep(interval);
+        }
+        samples
+    })
+}
+
+fn queue_stats(samples: &[OccupancySample], depth: usize, capacity: usize) -> Vec<QueueStats> {
+    let count = samples.len().max(1) as f64;
+    (0..depth)
+        .map(|stage| {
+            let lengths = samples.iter().map(|s| s.queues[stage]);
+            QueueStats {
+                stage: stage_name(stage, depth),
+                mean: lengths.clone().sum::<usize>() as f64 / count,
+                max: lengths.clone().max().unwrap_or(0),
+                full_share: lengths.filter(|&len| len >= capacity).count() as f64 / count,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+    use crate::cli::{Cli, Command};
+
+    // A short pipeline whose first stage is far slower than the rest, behind small queues
+    fn slow_first_stage() -> PipelineArgs {
+        let cli = Cli::parse_from([
+            "all_runtimes", "pipeline", "--depth", "3", "--capacity", "2", "--stage-workers", "2",
+            "--slow-stage", "1", "--slow-factor", "200", "--items", "500", "--work-units", "10",
+        ]);
+        match cli.command {
+            Some(Command::Pipeline(args)) => args,
+            other => panic!("parsed {:?} instead of a pipeline", other),
+        }
+    }
+
+    // The source outruns the slow stage, so only full queues can hold it back
+    fn assert_backpressure(engine: Engine) {
+        let args = slow_first_stage();
+        assert_eq!(args.slow_stage, Some(1));
+        let units: Vec<u64> = (0..args.depth)
+            .map(|stage| if stage == 0 { args.work_units * args.slow_factor } else { args.work_units })
+            .collect();
+        let data: Vec<u32> = (0..args.items as u32).collect();
+
+        let run = engine.run(&data, &units, &args);
+
+        let bound = args.depth * (args.capacity + args.stage_workers) + 1;
+        assert!(run.max_in_flight <= bound, "{} items in flight, bound {}", run.max_in_flight, bound);
+        assert_eq!(run.tally.items, data.len() as u64);
+        let expected: u64 = data.iter().map(|&value| units.iter().fold(value, |v, &u| work(v, u)) as u64).sum();
+        assert_eq!(run.tally.sum, expected);
+    }
+
+    #[test]
+    fn threads_apply_backpressure() {
+        assert_backpressure(Engine::Threads);
+    }
+
+    #[test]
+    fn tokio_applies_backpressure() {
+        assert_backpressure(Engine::Tokio(tokio_runtime(None)));
+    }
+
+    #[test]
+    fn smol_applies_backpressure() {
+        assert_backpressure(Engine::Smol(smol_executor(2)));
+    }
+
+    // One pool thread per stage worker, as `run_pipeline` sizes it, since each one blocks on its queues
+    #[test]
+    fn rayon_applies_backpressure() {
+        let args = slow_first_stage();
+        assert_backpressure(Engine::Rayon(rayon_pool(Some(args.depth * args.stage_workers))));
+    }
+}
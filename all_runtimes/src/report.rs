@@ -0,0 +1,807 @@
+use clap::ValueEnum;
+use std::io::{self, Write};
+use std::time::Duration;
+
+use crate::adapter::RuntimeAdapter;
+use crate::channels::{capacity_str, ChannelMatrix, ChannelPoint};
+use crate::echo::{EchoBenchmark, EchoResult};
+use crate::locks::LockComparison;
+use crate::openloop::{OpenLoop, SUSTAINED_SHARE};
+use crate::pipeline::PipelineBenchmark;
+use crate::results::{BenchmarkResult, BenchmarkRun, Category, Phase, Warmup};
+use crate::significance::{self, SignificanceTest};
+use crate::stats;
+use crate::collect::Collection;
+use crate::sweep::{CollectionMatrix, Scaling, Sweep, ThreadScaling, SATURATION_SHARE};
+
+// Where `run`'s per-category progress lines go. Next to a live table they stay on
+// stdout, where the benchmark has always printed them; otherwise they go to
+// stderr so a JSON or CSV report on stdout stays parseable. The other
+// subcommands always report progress on stderr for the same reason.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Progress {
+    Stdout,
+    Stderr,
+}
+
+impl Progress {
+    pub fn line(self, line: &str) {
+        match self {
+            Progress::Stdout => println!("{}", line),
+            Progress::Stderr => eprintln!("{}", line),
+        }
+    }
+}
+
+pub fn write_header(run: &BenchmarkRun, out: &mut dyn Write) -> io::Result<()> {
+    writeln!(out, "=== RUST CONCURRENCY LIBRARIES BENCHMARK ===")?;
+    writeln!(out, "Benchmarking with {} data points, {} iterations each",
+             run.workload.data_size, run.workload.iterations)?;
+    writeln!(out, "Input seed: {} (replay with --seed {})", run.workload.seed, run.workload.seed)?;
+    writeln!(out, "Timed phase: {} (see PHASES for the others)", run.workload.phase.as_str())?;
+    match run.workload.warmup {
+        Warmup::Fixed { iterations } => writeln!(out, "Warm-up: {} untimed iteration(s) per library", iterations)?,
+        Warmup::Auto { max_iterations, window, cv_threshold } =>
+            writeln!(out, "Warm-up: until the last {} runs vary by < {:.1}% (at most {} iterations)",
+                     window, cv_threshold * 100.0, max_iterations)?,
+    }
+    if let Some(latency) = &run.workload.latency {
+        writeln!(out, "Simulated I/O: each value waits {} before its CPU work", latency)?;
+    }
+    let cost = run.workload.cost;
+    writeln!(out, "Work per value: {} units (~{:.2?} at {:.3}ns per unit)", cost.units, cost.item_time(), cost.ns_per_unit)?;
+    writeln!(out, "Values per task: {}", run.workload.chunk_size)?;
+    writeln!(out, "Result collection: {}", run.workload.collection.as_str())?;
+    writeln!(out, "Output verification: {}", run.workload.verify.as_str())?;
+    writeln!(out, "Machine: {}/{}, {} logical CPUs ({} physical)",
+             run.metadata.os, run.metadata.arch, run.metadata.logical_cpus, run.metadata.physical_cpus)?;
+    writeln!(out, "--------------------------------------------------------")?;
+    Ok(())
+}
+
+// Which per-library figure the ranking tables order by
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum RankBy {
+    Best,
+    Median,
+    Mean,
+}
+
+impl RankBy {
+    pub fn of(&self, result: &BenchmarkResult) -> Duration {
+        match self {
+            RankBy::Best => result.best_time,
+            RankBy::Median => result.stats.median,
+            RankBy::Mean => result.avg_time,
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            RankBy::Best => "Best Time",
+            RankBy::Median => "Median Time",
+            RankBy::Mean => "Avg Time",
+        }
+    }
+}
+
+// Marks a difference from `best` that the significance test could not confirm
+fn verdict(run: &BenchmarkRun, result: &BenchmarkResult, best: &BenchmarkResult) -> &'static str {
+    if result.library == best.library {
+        return "";
+    }
+    match significance::find(&run.comparisons, &result.library, &best.library) {
+        Some(comparison) if !comparison.significant => " (indistinguishable)",
+        _ => "",
+    }
+}
+
+// Speedup over the sequential baseline on the given figure, and that speedup
+// divided by the threads the library used. "-" where either is unknown.
+fn speedup(baseline: Option<&BenchmarkResult>, result: &BenchmarkResult,
+           figure: impl Fn(&BenchmarkResult) -> Duration) -> (String, String) {
+    let Some(baseline) = baseline else {
+        return ("-".to_string(), "-".to_string());
+    };
+    let speedup = figure(baseline).as_nanos() as f64 / figure(result).as_nanos().max(1) as f64;
+    let efficiency = match result.threads {
+        0 => "-".to_string(),
+        threads => format!("{:.1}%", speedup / threads as f64 * 100.0),
+    };
+    (format!("{:.2}x", speedup), efficiency)
+}
+
+fn test_name(test: SignificanceTest) -> &'static str {
+    match test {
+        SignificanceTest::MannWhitney => "Mann-Whitney U",
+        SignificanceTest::Welch => "Welch's t-test",
+    }
+}
+
+// The ranking tables printed after a run
+pub fn write_tables(run: &BenchmarkRun, rank_by: RankBy, out: &mut dyn Write) -> io::Result<()> {
+    if run.results.is_empty() {
+        writeln!(out, "\nNo libraries were benchmarked.")?;
+        return Ok(());
+    }
+
+    // Libraries whose output didn't match the sequential reference are listed, not ranked
+    let failed: Vec<&BenchmarkResult> = run.results.iter().filter(|r| !r.verification.passed).collect();
+    if !failed.is_empty() {
+        writeln!(out, "\n=== VERIFICATION FAILURES ({}) ===", run.workload.verify.as_str())?;
+        writeln!(out, "{:<20} {:<20} {:<12} First mismatch", "Category", "Library", "Failed")?;
+        writeln!(out, "{:-<105}", "")?;
+        for result in &failed {
+            let failed_runs = format!("{}/{}", result.verification.failed_iterations.len(), result.all_times.len());
+            writeln!(out, "{:<20} {:<20} {:<12} {}", result.category, result.library, failed_runs,
+                     result.verification.detail.as_deref().unwrap_or_default())?;
+        }
+    }
+
+    let mut all_results: Vec<BenchmarkResult> = run.results.iter().filter(|r| r.verification.passed).cloned().collect();
+    if all_results.is_empty() {
+        writeln!(out, "\nNo library produced correct output.")?;
+        return Ok(());
+    }
+
+    // Find overall best performer
+    all_results.sort_by_key(|r| rank_by.of(r));
+    let overall_best = &all_results[0];
+
+    writeln!(out, "\n=== OVERALL RESULTS (ranked by {}) ===", rank_by.label().to_lowercase())?;
+    writeln!(out, "Best overall performer: {} ({}) with {:?}",
+             overall_best.library, overall_best.category, rank_by.of(overall_best))?;
+    let tied: Vec<&str> = all_results.iter()
+        .filter(|r| !verdict(run, r, overall_best).is_empty())
+        .map(|r| r.library.as_str())
+        .collect();
+    if !tied.is_empty() {
+        writeln!(out, "Statistically indistinguishable from the best: {}", tied.join(", "))?;
+    }
+    writeln!(out, "--------------------------------------------------------")?;
+
+    // Calculate percentages relative to the best performer
+    let best_time_nanos = rank_by.of(overall_best).as_nanos() as f64;
+
+    // Speedups are relative to the single-threaded loop
+    let baseline = all_results.iter().find(|r| r.category == Category::Sequential).cloned();
+    let baseline = baseline.as_ref();
+
+    // Output average times
+    writeln!(out, "\n=== AVERAGE TIMES ===")?;
+    writeln!(out, "{:<20} {:<20} {:<15} {:<15}  {:<10} {:<12}", "Category", "Library", "Avg Time", "vs Best (%)", "Speedup", "Efficiency")?;
+    writeln!(out, "{:-<99}", "")?;
+
+    let mut sorted_by_avg = all_results.clone();
+    sorted_by_avg.sort_by_key(|r| r.avg_time);
+
+    for result in &sorted_by_avg {
+        let percent_slower = ((result.avg_time.as_nanos() as f64 / best_time_nanos) - 1.0) * 100.0;
+        let (speedup, efficiency) = speedup(baseline, result, |r| r.avg_time);
+        writeln!(out, "{:<20} {:<20} {:<15?} {:<15.2}% {:<10} {:<12}{}",
+                 result.category, result.library, result.avg_time, percent_slower, speedup, efficiency,
+                 verdict(run, result, overall_best))?;
+    }
+
+    // Output best times
+    writeln!(out, "\n=== BEST TIMES ===")?;
+    writeln!(out, "{:<20} {:<20} {:<15} {:<15}  {:<10} {:<12}", "Category", "Library", "Best Time", "vs Best (%)", "Speedup", "Efficiency")?;
+    writeln!(out, "{:-<99}", "")?;
+
+    let mut sorted_by_best = all_results.clone();
+    sorted_by_best.sort_by_key(|r| r.best_time);
+
+    for result in &sorted_by_best {
+        let percent_slower = ((result.best_time.as_nanos() as f64 / best_time_nanos) - 1.0) * 100.0;
+        let (speedup, efficiency) = speedup(baseline, result, |r| r.best_time);
+        writeln!(out, "{:<20} {:<20} {:<15?} {:<15.2}% {:<10} {:<12}{}",
+                 result.category, result.library, result.best_time, percent_slower, speedup, efficiency,
+                 verdict(run, result, overall_best))?;
+    }
+
+    // Output the median of each phase so runtime construction cost is visible on its own
+    writeln!(out, "\n=== PHASES (median) ===")?;
+    writeln!(out, "{:<20} {:<20} {:<15} {:<15} {:<15} {:<15}",
+             "Category", "Library", "Startup", "Workload", "Shutdown", "Total")?;
+    writeln!(out, "{:-<105}", "")?;
+
+    for result in &all_results {
+        let median = |phase: Phase| stats::median(&result.phases.samples(phase));
+        writeln!(out, "{:<20} {:<20} {:<15.2?} {:<15.2?} {:<15.2?} {:<15.2?}",
+                 result.category, result.library, median(Phase::Startup), median(Phase::Workload),
+                 median(Phase::Shutdown), median(Phase::Total))?;
+    }
+
+    // Output the spread of every library's samples
+    writeln!(out, "\n=== STATISTICS ===")?;
+    writeln!(out, "{:<20} {:<13} {:<13} {:<13} {:<13} {:<13} {:<13} {:<8} {:<29} {:<8}",
+             "Library", "Median", "P90", "P99", "Min", "Max", "Std Dev", "CV (%)", "Median 95% CI", "Warm-ups")?;
+    writeln!(out, "{:-<149}", "")?;
+
+    for result in &all_results {  // already sorted by the ranking figure
+        let stats = &result.stats;
+        let ci = format!("{:.2?} - {:.2?}", stats.median_ci95.0, stats.median_ci95.1);
+        writeln!(out, "{:<20} {:<13.2?} {:<13.2?} {:<13.2?} {:<13.2?} {:<13.2?} {:<13.2?} {:<8.2} {:<29} {:<8}",
+                 result.library, stats.median, stats.p90, stats.p99, stats.min, stats.max,
+                 stats.std_dev, stats.coefficient_of_variation * 100.0, ci, result.warmup_times.len())?;
+    }
+
+    // Whether a library loses its time waiting to be scheduled or running the work
+    let timed: Vec<&BenchmarkResult> = all_results.iter().filter(|r| r.task_times.is_some()).collect();
+    if !timed.is_empty() {
+        writeln!(out, "\n=== TASK TIMES (queue wait vs execution) ===")?;
+        writeln!(out, "{:<20} {:<9} {:<12} {:<12} {:<12} {:<12} {:<12} {:<12} {:<10}", "Library", "Tasks",
+                 "Wait p50", "Wait p99", "Wait max", "Exec p50", "Exec p99", "Exec max", "Wait (%)")?;
+        writeln!(out, "{:-<116}", "")?;
+        for result in timed {
+            let times = result.task_times.as_ref().unwrap();
+            let (wait, exec) = (&times.queue_wait, &times.execution);
+            writeln!(out, "{:<20} {:<9} {:<12.2?} {:<12.2?} {:<12.2?} {:<12.2?} {:<12.2?} {:<12.2?} {:<10.1}",
+                     result.library, times.tasks, wait.p50, wait.p99, wait.max, exec.p50, exec.p99, exec.max,
+                     times.wait_share() * 100.0)?;
+        }
+    }
+
+    // Group results by category
+    writeln!(out, "\n=== RESULTS BY CATEGORY ===")?;
+
+    for category in Category::ALL {
+        let category_libs: Vec<&BenchmarkResult> = all_results.iter()
+            .filter(|r| r.category == category)
+            .collect();
+        if category_libs.is_empty() {
+            continue;
+        }
+
+        writeln!(out, "\n--- {} LIBRARIES ---", category.as_str().to_uppercase())?;
+        writeln!(out, "{:<20} {:<15} {:<15} {:<15} {:<21} {:<10} {:<12}",
+                 "Library", "Best Time", "Avg Time", "Median Time", "vs Category Best (%)", "Speedup", "Efficiency")?;
+        writeln!(out, "{:-<114}", "")?;
+
+        // all_results is sorted by the ranking figure, so the first entry is the category best
+        let category_best = category_libs[0];
+        let category_best_time = rank_by.of(category_best).as_nanos() as f64;
+
+        for result in &category_libs {
+            let percent_vs_category_best = ((rank_by.of(result).as_nanos() as f64 / category_best_time) - 1.0) * 100.0;
+            let (speedup, efficiency) = speedup(baseline, result, |r| rank_by.of(r));
+            writeln!(out, "{:<20} {:<15?} {:<15?} {:<15?} {:<20.2}% {:<10} {:<12}{}",
+                     result.library, result.best_time, result.avg_time, result.stats.median, percent_vs_category_best,
+                     speedup, efficiency, verdict(run, result, category_best))?;
+        }
+
+        // Every pair within the category
+        match run.comparisons.first() {
+            Some(first) if category_libs.len() > 1 => writeln!(out, "\n{} pairwise ({}, alpha = {}):", category, test_name(first.test), first.alpha)?,
+            _ => {}
+        }
+        for (i, a) in category_libs.iter().enumerate() {
+            for b in &category_libs[i + 1..] {
+                if let Some(comparison) = significance::find(&run.comparisons, &a.library, &b.library) {
+                    let outcome = match &comparison.faster {
+                        Some(faster) => format!("{} faster", faster),
+                        None => "indistinguishable".to_string(),
+                    };
+                    writeln!(out, "  {:<20} vs {:<20} p = {:<8.4} {}", a.library, b.library, comparison.p_value, outcome)?;
+                }
+            }
+        }
+    }
+
+    writeln!(out, "\n=== BENCHMARK COMPLETE ===")?;
+    match &run.workload.latency {
+        Some(_) => writeln!(out, "Note: Async libraries wait on their runtime's timer, thread-based ones block a worker \
+                                  thread per wait; libraries without a simulated I/O variant were skipped.")?,
+        None => writeln!(out, "Note: These results are specific to CPU-bound workloads. Rerun with --workload io \
+                               to compare libraries on simulated I/O.")?,
+    }
+    Ok(())
+}
+
+// Every point of a granularity sweep, then where each library starts paying off
+pub fn write_sweep(sweep: &Sweep, out: &mut dyn Write) -> io::Result<()> {
+    writeln!(out, "=== GRANULARITY SWEEP ===")?;
+    writeln!(out, "{} data points, {} iterations per point, seed {}", sweep.data_size, sweep.iterations, sweep.seed)?;
+    writeln!(out, "One work unit takes {:.3}ns; task size = value cost x values per task", sweep.ns_per_unit)?;
+    writeln!(out, "Machine: {}/{}, {} logical CPUs ({} physical)",
+             sweep.metadata.os, sweep.metadata.arch, sweep.metadata.logical_cpus, sweep.metadata.physical_cpus)?;
+
+    writeln!(out, "\n{:<20} {:<20} {:<12} {:<8} {:<12} {:<13} {:<10} {:<10}",
+             "Category", "Library", "Value Cost", "Chunk", "Task Size", "Median", "Speedup", "Efficiency")?;
+    writeln!(out, "{:-<110}", "")?;
+    for point in &sweep.points {
+        let chunk = point.chunk_size.map_or("own".to_string(), |n| n.to_string());
+        writeln!(out, "{:<20} {:<20} {:<12.2?} {:<8} {:<12.2?} {:<13.2?} {:<10} {:<10}",
+                 point.category, point.library, point.item_time, chunk, point.task_time, point.median,
+                 format!("{:.2}x", point.speedup), format!("{:.1}%", point.efficiency * 100.0))?;
+    }
+
+    let at = |threshold: Option<Duration>| threshold.map_or("never".to_string(), |d| format!("{:.2?}", d));
+    writeln!(out, "\n=== CROSSOVER (smallest task size from which every larger one qualifies) ===")?;
+    writeln!(out, "{:<20} {:<20} {:<22} {:<22}",
+             "Category", "Library", "Matches Sequential", format!("{:.0}% Efficiency", sweep.efficiency_target * 100.0))?;
+    writeln!(out, "{:-<86}", "")?;
+    for crossover in &sweep.crossovers {
+        writeln!(out, "{:<20} {:<20} {:<22} {:<22}", crossover.category, crossover.library,
+                 at(crossover.matches_baseline), at(crossover.reaches_efficiency))?;
+    }
+    writeln!(out, "\n\"own\" chunk: the library splits the work itself, so its task size is the value cost.")?;
+    Ok(())
+}
+
+// Each library's time and throughput at every size, then where it levels off
+pub fn write_scaling(scaling: &Scaling, out: &mut dyn Write) -> io::Result<()> {
+    writeln!(out, "=== DATA SIZE SCALING ===")?;
+    writeln!(out, "{} iterations per size, seed {}, {} values per task", scaling.iterations, scaling.seed, scaling.chunk_size)?;
+    writeln!(out, "Work per value: {} units (~{:.2?})", scaling.cost.units, scaling.cost.item_time())?;
+    writeln!(out, "Machine: {}/{}, {} logical CPUs ({} physical)",
+             scaling.metadata.os, scaling.metadata.arch, scaling.metadata.logical_cpus, scaling.metadata.physical_cpus)?;
+
+    // Per-library series
+    for summary in &scaling.saturation {
+        writeln!(out, "\n--- {} ({}) ---", summary.library, summary.category)?;
+        writeln!(out, "{:<12} {:<15} {:<18} {:<12}", "Size", "Median Time", "Values/sec", "Per Value")?;
+        writeln!(out, "{:-<60}", "")?;
+        for point in scaling.points.iter().filter(|p| p.library == summary.library) {
+            let per_value = point.median / point.data_size.max(1) as u32;
+            writeln!(out, "{:<12} {:<15.2?} {:<18.0} {:<12.2?}", point.data_size, point.median, point.throughput, per_value)?;
+        }
+    }
+
+    // Throughput side by side, in millions of values per second
+    writeln!(out, "\n=== THROUGHPUT (M values/sec) ===")?;
+    write!(out, "{:<20}", "Library")?;
+    for size in &scaling.sizes {
+        write!(out, " {:>10}", size)?;
+    }
+    writeln!(out)?;
+    writeln!(out, "{:-<1$}", "", 20 + 11 * scaling.sizes.len())?;
+    for summary in &scaling.saturation {
+        write!(out, "{:<20}", summary.library)?;
+        for size in &scaling.sizes {
+            match scaling.points.iter().find(|p| p.library == summary.library && p.data_size == *size) {
+                Some(point) => write!(out, " {:>10.3}", point.throughput / 1e6)?,
+                None => write!(out, " {:>10}", "-")?,
+            }
+        }
+        writeln!(out)?;
+    }
+
+    writeln!(out, "\n=== SATURATION (first size within {:.0}% of peak throughput) ===", SATURATION_SHARE * 100.0)?;
+    writeln!(out, "{:<20} {:<20} {:<15} {:<12} {:<12}", "Category", "Library", "Peak M/sec", "Peak Size", "Saturates At")?;
+    writeln!(out, "{:-<83}", "")?;
+    for summary in &scaling.saturation {
+        writeln!(out, "{:<20} {:<20} {:<15.3} {:<12} {:<12}", summary.category, summary.library,
+                 summary.peak_throughput / 1e6, summary.peak_size, summary.saturates_at)?;
+    }
+    Ok(())
+}
+
+// Strong-scaling curve of every library, then its fitted serial fraction
+pub fn write_thread_scaling(scaling: &ThreadScaling, out: &mut dyn Write) -> io::Result<()> {
+    writeln!(out, "=== THREAD SCALING ===")?;
+    writeln!(out, "{} data points, {} iterations per worker count, seed {}, {} values per task",
+             scaling.data_size, scaling.iterations, scaling.seed, scaling.chunk_size)?;
+    writeln!(out, "Work per value: {} units (~{:.2?})", scaling.cost.units, scaling.cost.item_time())?;
+    writeln!(out, "Machine: {}/{}, {} logical CPUs ({} physical)",
+             scaling.metadata.os, scaling.metadata.arch, scaling.metadata.logical_cpus, scaling.metadata.physical_cpus)?;
+    if !scaling.fixed.is_empty() {
+        writeln!(out, "Worker count not configurable, skipped: {}", scaling.fixed.join(", "))?;
+    }
+
+    // Speedup over one worker, side by side
+    writeln!(out, "\n=== SPEEDUP vs 1 WORKER ===")?;
+    write!(out, "{:<20}", "Library")?;
+    for threads in &scaling.thread_counts {
+        write!(out, " {:>8}", threads)?;
+    }
+    writeln!(out)?;
+    writeln!(out, "{:-<1$}", "", 20 + 9 * scaling.thread_counts.len())?;
+    let mut libraries: Vec<&str> = Vec::new();
+    for point in &scaling.points {
+        if !libraries.contains(&point.library.as_str()) {
+            libraries.push(&point.library);
+        }
+    }
+    for library in &libraries {
+        write!(out, "{:<20}", library)?;
+        for threads in &scaling.thread_counts {
+            match scaling.points.iter().find(|p| p.library == *library && p.threads == *threads) {
+                Some(point) => write!(out, " {:>7.2}x", point.speedup)?,
+                None => write!(out, " {:>8}", "-")?,
+            }
+        }
+        writeln!(out)?;
+    }
+
+    writeln!(out, "\n=== STRONG SCALING ===")?;
+    writeln!(out, "{:<20} {:<20} {:<8} {:<13} {:<10} {:<10}", "Category", "Library", "Workers", "Median", "Speedup", "Efficiency")?;
+    writeln!(out, "{:-<86}", "")?;
+    for point in &scaling.points {
+        writeln!(out, "{:<20} {:<20} {:<8} {:<13.2?} {:<10} {:<10}", point.category, point.library, point.threads,
+                 point.median, format!("{:.2}x", point.speedup), format!("{:.1}%", point.efficiency * 100.0))?;
+    }
+
+    writeln!(out, "\n=== AMDAHL FIT (T(n) = T(1) * (s + (1 - s) / n)) ===")?;
+    writeln!(out, "{:<20} {:<20} {:<17} {:<13} {:<8}", "Category", "Library", "Serial Fraction", "Max Speedup", "R²")?;
+    writeln!(out, "{:-<80}", "")?;
+    for fit in &scaling.fits {
+        let max_speedup = fit.max_speedup.map_or("unbounded".to_string(), |s| format!("{:.2}x", s));
+        writeln!(out, "{:<20} {:<20} {:<17.4} {:<13} {:<8.3}", fit.category, fit.library, fit.serial_fraction,
+                 max_speedup, fit.r_squared)?;
+    }
+    if scaling.fits.is_empty() {
+        writeln!(out, "Needs at least two worker counts (try --threads 1,2,4).")?;
+    }
+    Ok(())
+}
+
+// Every library's time under each collection strategy, then how much of it the mutex costs
+pub fn write_collection(matrix: &CollectionMatrix, out: &mut dyn Write) -> io::Result<()> {
+    writeln!(out, "=== RESULT COLLECTION ===")?;
+    writeln!(out, "{} data points, {} iterations per strategy, seed {}, {} values per task",
+             matrix.data_size, matrix.iterations, matrix.seed, matrix.chunk_size)?;
+    writeln!(out, "Work per value: {} units (~{:.2?})", matrix.cost.units, matrix.cost.item_time())?;
+    writeln!(out, "Machine: {}/{}, {} logical CPUs ({} physical)",
+             matrix.metadata.os, matrix.metadata.arch, matrix.metadata.logical_cpus, matrix.metadata.physical_cpus)?;
+    for collection in &matrix.collections {
+        let skipped: Vec<&str> = matrix.unsupported.iter()
+            .filter(|(_, c)| c == collection)
+            .map(|(library, _)| library.as_str())
+            .collect();
+        if !skipped.is_empty() {
+            writeln!(out, "Can't collect through {}, skipped: {}", collection.as_str(), skipped.join(", "))?;
+        }
+    }
+
+    // Median time per strategy, side by side
+    writeln!(out, "\n=== MEDIAN BY STRATEGY ===")?;
+    write!(out, "{:<22}", "Library")?;
+    for collection in &matrix.collections {
+        write!(out, " {:>15}", collection.as_str())?;
+    }
+    writeln!(out)?;
+    writeln!(out, "{:-<1$}", "", 22 + 16 * matrix.collections.len())?;
+    let mut libraries: Vec<&str> = Vec::new();
+    for point in &matrix.points {
+        if !libraries.contains(&point.library.as_str()) {
+            libraries.push(&point.library);
+        }
+    }
+    for library in &libraries {
+        write!(out, "{:<22}", library)?;
+        for collection in &matrix.collections {
+            match matrix.points.iter().find(|p| p.library == *library && p.collection == *collection) {
+                Some(point) => write!(out, " {:>15}", format!("{:.2?}", point.median))?,
+                None => write!(out, " {:>15}", "-")?,
+            }
+        }
+        writeln!(out)?;
+    }
+
+    writeln!(out, "\n=== TIME vs MUTEX ===")?;
+    write!(out, "{:<22}", "Library")?;
+    for collection in matrix.collections.iter().filter(|&&c| c != Collection::Mutex) {
+        write!(out, " {:>15}", collection.as_str())?;
+    }
+    writeln!(out)?;
+    writeln!(out, "{:-<1$}", "", 22 + 16 * (matrix.collections.len() - 1))?;
+    for library in &libraries {
+        write!(out, "{:<22}", library)?;
+        for collection in matrix.collections.iter().filter(|&&c| c != Collection::Mutex) {
+            match matrix.points.iter().find(|p| p.library == *library && p.collection == *collection).and_then(|p| p.vs_mutex) {
+                Some(ratio) => write!(out, " {:>14.2}x", ratio)?,
+                None => write!(out, " {:>15}", "-")?,
+            }
+        }
+        writeln!(out)?;
+    }
+
+    writeln!(out, "\n=== LOCK OVERHEAD ===")?;
+    writeln!(out, "{:<20} {:<22} {:<13} {:<16} {:<13} {:<10}", "Category", "Library", "Mutex", "Fastest", "Median", "Overhead")?;
+    writeln!(out, "{:-<99}", "")?;
+    let mut ranked: Vec<_> = matrix.overheads.iter().collect();
+    ranked.sort_by(|a, b| b.overhead_share.total_cmp(&a.overhead_share));
+    for overhead in ranked {
+        writeln!(out, "{:<20} {:<22} {:<13.2?} {:<16} {:<13.2?} {:<10}", overhead.category, overhead.library,
+                 overhead.mutex, overhead.fastest.as_str(), overhead.fastest_median,
+                 format!("{:.1}%", overhead.overhead_share * 100.0))?;
+    }
+    if matrix.overheads.is_empty() {
+        writeln!(out, "Needs at least one strategy besides the mutex (try --collections mutex,slices).")?;
+    }
+    writeln!(out, "\nOverhead is the share of the mutex run's time the fastest other strategy saves.")?;
+    Ok(())
+}
+
+// Per runtime, every lock's median at each contention level, then the fastest lock for each
+pub fn write_locks(comparison: &LockComparison, out: &mut dyn Write) -> io::Result<()> {
+    writeln!(out, "=== LOCK COMPARISON ===")?;
+    writeln!(out, "{} data points, {} iterations per lock, seed {}, {} values per task",
+             comparison.data_size, comparison.iterations, comparison.seed, comparison.chunk_size)?;
+    writeln!(out, "Work per value: {} units (~{:.2?}) outside the lock, {} unit(s) holding it",
+             comparison.cost.units, comparison.cost.item_time(), comparison.hold_units)?;
+    writeln!(out, "Per value: {} read acquisition(s), then one write", comparison.reads)?;
+    writeln!(out, "Machine: {}/{}, {} logical CPUs ({} physical)",
+             comparison.metadata.os, comparison.metadata.arch, comparison.metadata.logical_cpus, comparison.metadata.physical_cpus)?;
+
+    for runtime in &comparison.runtimes {
+        writeln!(out, "\n=== {} (median, vs std Mutex) ===", runtime.as_str().to_uppercase())?;
+        write!(out, "{:<20}", "Lock")?;
+        for shards in &comparison.shard_counts {
+            write!(out, " {:>22}", format!("{} shard(s)", shards))?;
+        }
+        writeln!(out)?;
+        writeln!(out, "{:-<1$}", "", 20 + 23 * comparison.shard_counts.len())?;
+        for lock in &comparison.locks {
+            write!(out, "{:<20}", lock.as_str())?;
+            for shards in &comparison.shard_counts {
+                let point = comparison.points.iter()
+                    .find(|p| p.runtime == *runtime && p.lock == *lock && p.shards == *shards);
+                match point {
+                    Some(point) => {
+                        let ratio = point.vs_std_mutex.map_or("-".to_string(), |r| format!("{:.2}x", r));
+                        write!(out, " {:>22}", format!("{:.2?} ({})", point.median, ratio))?
+                    }
+                    None => write!(out, " {:>22}", "-")?,
+                }
+            }
+            writeln!(out)?;
+        }
+    }
+
+    writeln!(out, "\n=== FASTEST LOCK ===")?;
+    writeln!(out, "{:<12} {:<8} {:<20} {:<13} {:<10}", "Runtime", "Shards", "Lock", "Median", "Kind")?;
+    writeln!(out, "{:-<67}", "")?;
+    for fastest in &comparison.fastest {
+        let kind = if fastest.lock.is_async() { "async" } else { "sync" };
+        writeln!(out, "{:<12} {:<8} {:<20} {:<13.2?} {:<10}", fastest.runtime.as_str(), fastest.shards,
+                 fastest.lock.as_str(), fastest.median, kind)?;
+    }
+    writeln!(out, "\nSync locks block the worker thread while they wait; async ones suspend the task.")?;
+    writeln!(out, "Under Rayon the async locks are taken through their blocking methods.")?;
+    Ok(())
+}
+
+// Messages/sec per channel, bound and flavor across the shapes, then round-trip latency
+pub fn write_channels(matrix: &ChannelMatrix, out: &mut dyn Write) -> io::Result<()> {
+    writeln!(out, "=== CHANNEL MATRIX ===")?;
+    writeln!(out, "{} messages per run, {} iterations, {} producer(s) for MPSC/MPMC, {} consumer(s) for MPMC",
+             matrix.messages, matrix.iterations, matrix.producers, matrix.consumers)?;
+    writeln!(out, "Machine: {}/{}, {} logical CPUs ({} physical)",
+             matrix.metadata.os, matrix.metadata.arch, matrix.metadata.logical_cpus, matrix.metadata.physical_cpus)?;
+    if !matrix.unsupported.is_empty() {
+        writeln!(out, "Not supported, skipped: {}", matrix.unsupported.join(", "))?;
+    }
+
+    writeln!(out, "\n=== THROUGHPUT (messages/s, median) ===")?;
+    write!(out, "{:<18} {:<10} {:<7}", "Channel", "Capacity", "Flavor")?;
+    for topology in &matrix.topologies {
+        write!(out, " {:>14}", topology.as_str())?;
+    }
+    writeln!(out)?;
+    writeln!(out, "{:-<1$}", "", 37 + 15 * matrix.topologies.len())?;
+    let mut lossy = false;
+    for channel in &matrix.channels {
+        for capacity in &matrix.capacities {
+            for flavor in &matrix.flavors {
+                let row: Vec<Option<&ChannelPoint>> = matrix.topologies.iter()
+                    .map(|topology| matrix.points.iter().find(|p| p.channel == *channel && p.topology == *topology
+                        && p.capacity == *capacity && p.flavor == *flavor))
+                    .collect();
+                if row.iter().all(Option::is_none) {
+                    continue;
+                }
+                write!(out, "{:<18} {:<10} {:<7}", channel.as_str(), capacity_str(*capacity), flavor.as_str())?;
+                for point in row {
+                    match point {
+                        Some(point) => {
+                            let mark = if point.lost > 0 { "*" } else { "" };
+                            lossy |= point.lost > 0;
+                            write!(out, " {:>14}", format!("{:.0}{}", point.messages_per_sec, mark))?
+                        }
+                        None => write!(out, " {:>14}", "-")?,
+                    }
+                }
+                writeln!(out)?;
+            }
+        }
+    }
+    if lossy {
+        writeln!(out, "* tokio broadcast dropped messages its receiver fell behind on; only delivered ones count")?;
+    }
+
+    if !matrix.round_trips.is_empty() {
+        writeln!(out, "\n=== ROUND-TRIP LATENCY (one message in flight) ===")?;
+        writeln!(out, "{:<18} {:<10} {:<7} {:<12} {:<12} {:<12}", "Channel", "Capacity", "Flavor", "p50", "p99", "Max")?;
+        writeln!(out, "{:-<76}", "")?;
+        for trip in &matrix.round_trips {
+            writeln!(out, "{:<18} {:<10} {:<7} {:<12.2?} {:<12.2?} {:<12.2?}", trip.channel.as_str(),
+                     capacity_str(trip.capacity), trip.flavor.as_str(), trip.p50, trip.p99, trip.max)?;
+        }
+    }
+    writeln!(out, "\nsync: blocking calls on threads; async: Tokio tasks; mixed: blocking senders, awaiting receivers.")?;
+    Ok(())
+}
+
+// Throughput, latency and the backpressure check per runtime, then how full each
+// queue was and how that changed over the median run
+pub fn write_pipeline(benchmark: &PipelineBenchmark, out: &mut dyn Write) -> io::Result<()> {
+    const TIMELINE_ROWS: usize = 20;
+
+    writeln!(out, "=== BACKPRESSURE PIPELINE ===")?;
+    writeln!(out, "Stages: {}", benchmark.stages.join(" -> "))?;
+    writeln!(out, "{} items per run, {} iterations, seed {}, queues of {}, {} worker(s) per stage",
+             benchmark.items, benchmark.iterations, benchmark.seed, benchmark.capacity, benchmark.stage_workers)?;
+    writeln!(out, "Work per item per stage: {} units (~{:.2?})", benchmark.cost.units, benchmark.cost.item_time())?;
+    if let Some(slow) = benchmark.slow_stage {
+        writeln!(out, "Slow stage: {} ({}x the work)", benchmark.stages[slow], benchmark.slow_factor)?;
+    }
+    writeln!(out, "Machine: {}/{}, {} logical CPUs ({} physical)",
+             benchmark.metadata.os, benchmark.metadata.arch, benchmark.metadata.logical_cpus, benchmark.metadata.physical_cpus)?;
+
+    writeln!(out, "\n=== THROUGHPUT AND END-TO-END LATENCY ===")?;
+    writeln!(out, "{:<10} {:<12} {:<13} {:<12} {:<12} {:<12} {:<10} {:<12}",
+             "Runtime", "Items/s", "Median", "p50", "p99", "Max", "In flight", "Backpressure")?;
+    writeln!(out, "{:-<100}", "")?;
+    for result in &benchmark.results {
+        let held = if result.backpressure_held { "held" } else { "EXCEEDED" };
+        writeln!(out, "{:<10} {:<12.0} {:<13.2?} {:<12.2?} {:<12.2?} {:<12.2?} {:<10} {:<12}", result.runtime.as_str(),
+                 result.items_per_sec, result.median, result.p50, result.p99, result.max, result.max_in_flight, held)?;
+    }
+    writeln!(out, "In flight: most items between source and aggregate at once; bounded queues keep it at or under {}.",
+             benchmark.in_flight_bound)?;
+
+    for result in &benchmark.results {
+        writeln!(out, "\n--- {} QUEUE OCCUPANCY (median run) ---", result.runtime.as_str().to_uppercase())?;
+        writeln!(out, "{:<16} {:<8} {:<8} {:<8}", "Queue into", "Mean", "Max", "Full")?;
+        for queue in &result.queues {
+            writeln!(out, "{:<16} {:<8.1} {:<8} {:<8}", queue.stage, queue.mean, queue.max,
+                     format!("{:.0}%", queue.full_share * 100.0))?;
+        }
+
+        write!(out, "\n{:<12}", "Time")?;
+        for stage in &benchmark.stages {
+            write!(out, " {:>12}", stage)?;
+        }
+        writeln!(out)?;
+        let step = result.timeline.len().div_ceil(TIMELINE_ROWS).max(1);
+        for sample in result.timeline.iter().step_by(step) {
+            write!(out, "{:<12.2?}", sample.at)?;
+            for len in &sample.queues {
+                write!(out, " {:>12}", len)?;
+            }
+            writeln!(out)?;
+        }
+    }
+    Ok(())
+}
+
+// Throughput and latency percentiles per echo server, then each one's latency histogram
+pub fn write_echo(benchmark: &EchoBenchmark, out: &mut dyn Write) -> io::Result<()> {
+    writeln!(out, "=== TCP ECHO (loopback) ===")?;
+    writeln!(out, "{} connections, {}-byte messages, {:?} measured after {:?} warm-up",
+             benchmark.connections, benchmark.message_size, benchmark.duration, benchmark.warmup)?;
+    writeln!(out, "Machine: {}/{}, {} logical CPUs ({} physical)",
+             benchmark.metadata.os, benchmark.metadata.arch, benchmark.metadata.logical_cpus, benchmark.metadata.physical_cpus)?;
+
+    writeln!(out, "\n{:<20} {:<14} {:<12} {:<12} {:<12} {:<12} {:<8}",
+             "Library", "Requests/s", "p50", "p99", "p99.9", "Max", "Errors")?;
+    writeln!(out, "{:-<96}", "")?;
+    let mut ranked: Vec<&EchoResult> = benchmark.results.iter().collect();
+    ranked.sort_by(|a, b| b.requests_per_sec.total_cmp(&a.requests_per_sec));
+    for result in &ranked {
+        writeln!(out, "{:<20} {:<14.0} {:<12.2?} {:<12.2?} {:<12.2?} {:<12.2?} {:<8}", result.library,
+                 result.requests_per_sec, result.p50, result.p99, result.p999, result.max, result.errors)?;
+    }
+
+    for result in &ranked {
+        writeln!(out, "\n--- {} LATENCY HISTOGRAM ---", result.library.to_uppercase())?;
+        let peak = result.histogram.iter().map(|b| b.count).max().unwrap_or(0).max(1);
+        for bucket in &result.histogram {
+            let bar = "#".repeat((bucket.count * 50).div_ceil(peak) as usize);
+            writeln!(out, "<= {:<12.2?} {:>10} {}", bucket.upper, bucket.count, bar)?;
+        }
+    }
+    Ok(())
+}
+
+// p99 latency against offered load per library, every step in full, then where each one saturates
+pub fn write_open_loop(open_loop: &OpenLoop, out: &mut dyn Write) -> io::Result<()> {
+    writeln!(out, "=== OPEN-LOOP LOAD ===")?;
+    writeln!(out, "{:?} arrivals, {:?} per rate, seed {}", open_loop.arrivals, open_loop.duration, open_loop.seed)?;
+    writeln!(out, "Work per item: {} units (~{:.2?})", open_loop.cost.units, open_loop.cost.item_time())?;
+    writeln!(out, "Latency is measured from each item's intended arrival (corrected for coordinated omission)")?;
+    writeln!(out, "Machine: {}/{}, {} logical CPUs ({} physical)",
+             open_loop.metadata.os, open_loop.metadata.arch, open_loop.metadata.logical_cpus, open_loop.metadata.physical_cpus)?;
+    if !open_loop.unsupported.is_empty() {
+        writeln!(out, "No open-loop submission, skipped: {}", open_loop.unsupported.join(", "))?;
+    }
+
+    writeln!(out, "\n=== p99 LATENCY vs OFFERED LOAD (items/s) ===")?;
+    write!(out, "{:<20}", "Library")?;
+    for rate in &open_loop.rates {
+        write!(out, " {:>10.0}", rate)?;
+    }
+    writeln!(out)?;
+    writeln!(out, "{:-<1$}", "", 20 + 11 * open_loop.rates.len())?;
+    for summary in &open_loop.saturation {
+        write!(out, "{:<20}", summary.library)?;
+        for rate in &open_loop.rates {
+            match open_loop.points.iter().find(|p| p.library == summary.library && p.offered_rate == *rate) {
+                Some(point) => write!(out, " {:>10}", format!("{:.1?}{}", point.p99, if point.sustained { "" } else { "*" }))?,
+                None => write!(out, " {:>10}", "-")?,
+            }
+        }
+        writeln!(out)?;
+    }
+    writeln!(out, "* not sustained (completed < {:.0}% of the offered rate); higher rates are skipped",
+             SUSTAINED_SHARE * 100.0)?;
+
+    writeln!(out, "\n=== LATENCY BY STEP ===")?;
+    writeln!(out, "{:<20} {:<10} {:<10} {:<11} {:<11} {:<11} {:<11} {:<14}", "Library", "Offered/s", "Achieved/s",
+             "p50", "p99", "p99.9", "Max", "p99 (uncorr.)")?;
+    writeln!(out, "{:-<104}", "")?;
+    for point in &open_loop.points {
+        writeln!(out, "{:<20} {:<10.0} {:<10.0} {:<11.2?} {:<11.2?} {:<11.2?} {:<11.2?} {:<14.2?}", point.library,
+                 point.offered_rate, point.achieved_rate, point.p50, point.p99, point.p999, point.max, point.uncorrected_p99)?;
+    }
+
+    writeln!(out, "\n=== SATURATION ===")?;
+    writeln!(out, "{:<20} {:<20} {:<20} {:<20}", "Category", "Library", "Max Sustained/s", "Saturated At/s")?;
+    writeln!(out, "{:-<80}", "")?;
+    for summary in &open_loop.saturation {
+        let rate = |rate: Option<f64>| rate.map_or("-".to_string(), |r| format!("{:.0}", r));
+        writeln!(out, "{:<20} {:<20} {:<20} {:<20}", summary.category, summary.library,
+                 rate(summary.max_sustained_rate), rate(summary.saturated_at))?;
+    }
+    Ok(())
+}
+
+pub fn write_library_list(adapters: &[Box<dyn RuntimeAdapter>], out: &mut dyn Write) -> io::Result<()> {
+    writeln!(out, "{:<20} {:<20}", "Category", "Library")?;
+    writeln!(out, "{:-<40}", "")?;
+    for adapter in adapters {
+        writeln!(out, "{:<20} {:<20}", adapter.category(), adapter.name())?;
+    }
+    Ok(())
+}
+
+// Per-library change between two exported runs, matched on category and library name
+pub fn write_comparison(baseline: &BenchmarkRun, candidate: &BenchmarkRun, out: &mut dyn Write) -> io::Result<()> {
+    writeln!(out, "=== RUN COMPARISON ===")?;
+    writeln!(out, "Baseline:  {} data points, {} iterations, {}/{} with {} logical CPUs",
+             baseline.workload.data_size, baseline.workload.iterations,
+             baseline.metadata.os, baseline.metadata.arch, baseline.metadata.logical_cpus)?;
+    writeln!(out, "Candidate: {} data points, {} iterations, {}/{} with {} logical CPUs",
+             candidate.workload.data_size, candidate.workload.iterations,
+             candidate.metadata.os, candidate.metadata.arch, candidate.metadata.logical_cpus)?;
+
+    writeln!(out, "\n{:<20} {:<20} {:<15} {:<15} {:<14} {:<15} {:<15} {:<14}",
+             "Category", "Library", "Base Best", "Cand Best", "Best Diff (%)", "Base Avg", "Cand Avg", "Avg Diff (%)")?;
+    writeln!(out, "{:-<134}", "")?;
+
+    let change = |base: Duration, cand: Duration| {
+        ((cand.as_nanos() as f64 / base.as_nanos().max(1) as f64) - 1.0) * 100.0
+    };
+
+    for base in &baseline.results {
+        let matching = candidate.results.iter()
+            .find(|c| c.category == base.category && c.library == base.library);
+        match matching {
+            Some(cand) => writeln!(out, "{:<20} {:<20} {:<15?} {:<15?} {:<+14.2} {:<15?} {:<15?} {:<+14.2}",
+                                   base.category, base.library,
+                                   base.best_time, cand.best_time, change(base.best_time, cand.best_time),
+                                   base.avg_time, cand.avg_time, change(base.avg_time, cand.avg_time))?,
+            None => writeln!(out, "{:<20} {:<20} only in baseline", base.category, base.library)?,
+        }
+    }
+
+    for cand in &candidate.results {
+        if !baseline.results.iter().any(|b| b.category == cand.category && b.library == cand.library) {
+            writeln!(out, "{:<20} {:<20} only in candidate", cand.category, cand.library)?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,452 @@
+use clap::ValueEnum;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::adapter::{Measurement, RuntimeAdapter};
+use crate::collect::Collection;
+use crate::significance::PairwiseComparison;
+use crate::stats::Statistics;
+use crate::timing::TaskTimes;
+
+// Which family of libraries a result belongs to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, ValueEnum)]
+pub enum Category {
+    // Single-threaded baseline, always run
+    Sequential,
+    #[value(alias = "async")]
+    Asynchronous,
+    Parallel,
+    Hybrid,
+    // Actor frameworks, sent the work as messages
+    Actor,
+}
+
+impl Category {
+    pub const ALL: [Category; 5] =
+        [Category::Sequential, Category::Asynchronous, Category::Parallel, Category::Hybrid, Category::Actor];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Category::Sequential => "Sequential",
+            Category::Asynchronous => "Asynchronous",
+            Category::Parallel => "Parallel",
+            Category::Hybrid => "Hybrid",
+            Category::Actor => "Actor",
+        }
+    }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // `pad` so table widths like `{:<20}` still apply
+        f.pad(self.as_str())
+    }
+}
+
+// Unit used for every serialized duration
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TimeUnit {
+    #[serde(rename = "ns")]
+    Nanoseconds,
+}
+
+// How many untimed runs precede the timed iterations of each library
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum Warmup {
+    Fixed { iterations: usize },
+    // Keep warming up until the last `window` runs have a coefficient of
+    // variation below `cv_threshold`, giving up after `max_iterations`
+    Auto { max_iterations: usize, window: usize, cv_threshold: f64 },
+}
+
+impl Default for Warmup {
+    fn default() -> Self {
+        Warmup::Fixed { iterations: 0 }
+    }
+}
+
+// Part of each iteration that `all_times` and the statistics are computed from
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    /// Building the runtime, executor or pool
+    Startup,
+    /// Spawning and joining the work
+    #[default]
+    Workload,
+    /// Dropping the runtime
+    Shutdown,
+    /// All three phases together
+    Total,
+}
+
+impl Phase {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Phase::Startup => "startup",
+            Phase::Workload => "workload",
+            Phase::Shutdown => "shutdown",
+            Phase::Total => "total",
+        }
+    }
+}
+
+// Per-iteration timings of each phase, index-aligned
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PhaseTimes {
+    #[serde(with = "nanos_vec")]
+    pub startup: Vec<Duration>,
+    #[serde(with = "nanos_vec")]
+    pub workload: Vec<Duration>,
+    #[serde(with = "nanos_vec")]
+    pub shutdown: Vec<Duration>,
+}
+
+impl PhaseTimes {
+    pub fn samples(&self, phase: Phase) -> Vec<Duration> {
+        match phase {
+            Phase::Startup => self.startup.clone(),
+            Phase::Workload => self.workload.clone(),
+            Phase::Shutdown => self.shutdown.clone(),
+            Phase::Total => self.startup.iter()
+                .zip(&self.workload)
+                .zip(&self.shutdown)
+                .map(|((startup, workload), shutdown)| *startup + *workload + *shutdown)
+                .collect(),
+        }
+    }
+}
+
+// How each library's output is checked against the sequential reference
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum VerifyMode {
+    /// Compare every value
+    Full,
+    /// Compare a checksum of the whole output
+    Checksum,
+    /// Only check that there is one value per input
+    #[default]
+    Off,
+}
+
+impl VerifyMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VerifyMode::Full => "full",
+            VerifyMode::Checksum => "checksum",
+            VerifyMode::Off => "off",
+        }
+    }
+}
+
+// Outcome of checking one library's output on every timed iteration
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Verification {
+    pub mode: VerifyMode,
+    pub passed: bool,
+    // Timed iterations whose output didn't match the reference
+    pub failed_iterations: Vec<usize>,
+    // What went wrong on the first failing iteration
+    pub detail: Option<String>,
+}
+
+impl Default for Verification {
+    fn default() -> Self {
+        Verification { mode: VerifyMode::Off, passed: true, failed_iterations: Vec::new(), detail: None }
+    }
+}
+
+// How much CPU work each item costs
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WorkCost {
+    // Multiply-add rounds per call to `process_value`
+    pub units: u64,
+    // Calibrated time of one unit on the machine that ran the benchmark; 0 if unknown
+    pub ns_per_unit: f64,
+}
+
+impl WorkCost {
+    pub fn item_time(&self) -> Duration {
+        Duration::from_nanos((self.units as f64 * self.ns_per_unit).round() as u64)
+    }
+}
+
+impl Default for WorkCost {
+    // What every report written before the cost was configurable used
+    fn default() -> Self {
+        WorkCost { units: 1000, ns_per_unit: 0.0 }
+    }
+}
+
+// Distribution each value's simulated I/O wait is drawn from
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Latency {
+    Fixed {
+        #[serde(with = "nanos")]
+        delay: Duration,
+    },
+    Uniform {
+        #[serde(with = "nanos")]
+        min: Duration,
+        #[serde(with = "nanos")]
+        max: Duration,
+    },
+    Exponential {
+        #[serde(with = "nanos")]
+        mean: Duration,
+    },
+}
+
+impl Latency {
+    pub fn sample(&self, rng: &mut impl Rng) -> Duration {
+        match *self {
+            Latency::Fixed { delay } => delay,
+            Latency::Uniform { min, max } => Duration::from_nanos(rng.random_range(min.as_nanos() as u64..=max.as_nanos() as u64)),
+            Latency::Exponential { mean } => {
+                let u: f64 = rng.random();
+                mean.mul_f64(-(1.0 - u).ln())
+            }
+        }
+    }
+}
+
+impl fmt::Display for Latency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Latency::Fixed { delay } => write!(f, "fixed {:?}", delay),
+            Latency::Uniform { min, max } => write!(f, "uniform {:?}..{:?}", min, max),
+            Latency::Exponential { mean } => write!(f, "exponential, mean {:?}", mean),
+        }
+    }
+}
+
+// Parameters of the workload every library was run against
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Workload {
+    pub name: String,
+    pub data_size: usize,
+    pub iterations: usize,
+    #[serde(default)]
+    pub warmup: Warmup,
+    // Which phase `all_times` holds
+    #[serde(default)]
+    pub phase: Phase,
+    // Seed the input datasets were generated from; pass to --seed to replay
+    #[serde(default)]
+    pub seed: u64,
+    #[serde(default)]
+    pub verify: VerifyMode,
+    #[serde(default)]
+    pub cost: WorkCost,
+    // Items handed to each spawned task
+    #[serde(default = "one")]
+    pub chunk_size: usize,
+    // Set for simulated I/O: every value first waits a delay drawn from this
+    #[serde(default)]
+    pub latency: Option<Latency>,
+    // How tasks hand their values back
+    #[serde(default)]
+    pub collection: Collection,
+}
+
+fn one() -> usize {
+    1
+}
+
+impl Workload {
+    pub fn cpu_bound(data_size: usize, iterations: usize, warmup: Warmup, phase: Phase, seed: u64, verify: VerifyMode,
+                     cost: WorkCost) -> Self {
+        Workload {
+            name: "process_value".to_string(),
+            data_size,
+            iterations,
+            warmup,
+            phase,
+            seed,
+            verify,
+            cost,
+            chunk_size: 1,
+            latency: None,
+            collection: Collection::Mutex,
+        }
+    }
+
+    // Same, but every value waits out a delay drawn from `latency` before it's processed
+    pub fn simulated_io(mut self, latency: Latency) -> Self {
+        self.name = "simulated_io".to_string();
+        self.latency = Some(latency);
+        self
+    }
+}
+
+// Where and when a run happened
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RunMetadata {
+    pub started_at_unix: u64,
+    pub harness_version: String,
+    pub os: String,
+    pub arch: String,
+    pub logical_cpus: usize,
+    pub physical_cpus: usize,
+}
+
+impl RunMetadata {
+    pub fn collect() -> Self {
+        RunMetadata {
+            started_at_unix: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            harness_version: env!("CARGO_PKG_VERSION").to_string(),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            logical_cpus: num_cpus::get(),
+            physical_cpus: num_cpus::get_physical(),
+        }
+    }
+}
+
+// One library's measurements. Durations are serialized as integer nanoseconds.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BenchmarkResult {
+    pub category: Category,
+    pub library: String,
+    // Worker threads the library spread the work over; 0 in older reports
+    #[serde(default)]
+    pub threads: usize,
+    pub unit: TimeUnit,
+    pub workload: Workload,
+    #[serde(with = "nanos")]
+    pub best_time: Duration,
+    #[serde(with = "nanos")]
+    pub avg_time: Duration,
+    // Samples of the phase selected in `workload.phase`
+    #[serde(with = "nanos_vec")]
+    pub all_times: Vec<Duration>,
+    // Every phase of every timed iteration
+    #[serde(default)]
+    pub phases: PhaseTimes,
+    // Warm-up runs, not included in any statistic
+    #[serde(with = "nanos_vec", default)]
+    pub warmup_times: Vec<Duration>,
+    // Missing from reports written before statistics were recorded
+    #[serde(default)]
+    pub stats: Statistics,
+    // Reports written before output was verified count as unverified passes
+    #[serde(default)]
+    pub verification: Verification,
+    // Queue wait and execution time of every spawned task, with --task-timing
+    #[serde(default)]
+    pub task_times: Option<TaskTimes>,
+}
+
+impl BenchmarkResult {
+    pub fn from_measurement(adapter: &dyn RuntimeAdapter, workload: &Workload, measurement: Measurement) -> Self {
+        let Measurement { warmup_times, phases, verification, task_times } = measurement;
+        let all_times = phases.samples(workload.phase);
+        let best_time = all_times.iter().copied().min().unwrap_or_default();
+        let avg_time = all_times.iter().sum::<Duration>() / all_times.len().max(1) as u32;
+        let stats = Statistics::from_samples(&all_times);
+        BenchmarkResult {
+            category: adapter.category(),
+            library: adapter.name().to_string(),
+            threads: adapter.threads(),
+            unit: TimeUnit::Nanoseconds,
+            workload: workload.clone(),
+            best_time,
+            avg_time,
+            all_times,
+            phases,
+            warmup_times,
+            stats,
+            verification,
+            task_times,
+        }
+    }
+}
+
+// Everything produced by one invocation of the harness
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BenchmarkRun {
+    pub metadata: RunMetadata,
+    pub workload: Workload,
+    pub results: Vec<BenchmarkResult>,
+    // Significance test between every pair of results
+    #[serde(default)]
+    pub comparisons: Vec<PairwiseComparison>,
+}
+
+impl BenchmarkRun {
+    pub fn new(workload: Workload) -> Self {
+        BenchmarkRun {
+            metadata: RunMetadata::collect(),
+            workload,
+            results: Vec::new(),
+            comparisons: Vec::new(),
+        }
+    }
+}
+
+pub(crate) mod nanos {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(duration.as_nanos() as u64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        Ok(Duration::from_nanos(u64::deserialize(deserializer)?))
+    }
+}
+
+pub(crate) mod nanos_vec {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(durations: &[Duration], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(durations.iter().map(|d| d.as_nanos() as u64))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Duration>, D::Error> {
+        Ok(Vec::<u64>::deserialize(deserializer)?
+            .into_iter()
+            .map(Duration::from_nanos)
+            .collect())
+    }
+}
+
+pub(crate) mod nanos_option {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(duration: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error> {
+        match duration {
+            Some(d) => serializer.serialize_some(&(d.as_nanos() as u64)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Duration>, D::Error> {
+        Ok(Option::<u64>::deserialize(deserializer)?.map(Duration::from_nanos))
+    }
+}
+
+pub(crate) mod nanos_pair {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(pair: &(Duration, Duration), serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq([pair.0, pair.1].iter().map(|d| d.as_nanos() as u64))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<(Duration, Duration), D::Error> {
+        let (low, high) = <(u64, u64)>::deserialize(deserializer)?;
+        Ok((Duration::from_nanos(low), Duration::from_nanos(high)))
+    }
+}
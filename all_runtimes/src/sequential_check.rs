@@ -0,0 +1,71 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::adapter::{run_category, Datasets, RuntimeAdapter};
+use crate::cli::Selection;
+use crate::collect::Collection;
+use crate::report::Progress;
+use crate::results::{BenchmarkResult, Category, Workload};
+use crate::trace;
+
+// Import the processing function from the async module
+use crate::async_check::process_value;
+
+const CATEGORY: Category = Category::Sequential;
+
+pub fn adapters() -> Vec<Box<dyn RuntimeAdapter>> {
+    vec![Box::new(SequentialAdapter)]
+}
+
+// Plain loop on the calling thread. Every other library's speedup and
+// efficiency is measured against this.
+pub struct SequentialAdapter;
+
+impl RuntimeAdapter for SequentialAdapter {
+    fn name(&self) -> &'static str {
+        "Sequential"
+    }
+
+    fn category(&self) -> Category {
+        CATEGORY
+    }
+
+    fn threads(&self) -> usize {
+        1
+    }
+
+    // Nothing is spawned, so there is nothing to chunk
+    fn honours_chunk_size(&self) -> bool {
+        false
+    }
+
+    // Values are collected straight into the output, whatever the strategy, so the
+    // baseline stays the same one for every strategy
+    fn supports_collection(&self, _collection: Collection) -> bool {
+        true
+    }
+
+    // Every wait is served back to back
+    fn run(&mut self, data: Arc<Vec<u32>>, delays: Option<Arc<[Duration]>>, workload: &Workload) -> Option<Vec<u32>> {
+        let cost = workload.cost;
+        let mut trace = trace::task(0);
+        let processed = data.iter().enumerate().map(|(idx, &value)| {
+            if let Some(delays) = &delays {
+                std::thread::sleep(delays[idx]);
+            }
+            trace.item(idx, || process_value(value, cost))
+        });
+        Some(processed.collect())
+    }
+}
+
+// Main function to benchmark the single-threaded baseline
+pub fn benchmark_sequential_baseline(workload: &Workload, datasets: &Datasets, selection: &Selection,
+                                     progress: Progress) -> Vec<BenchmarkResult> {
+    progress.line("Starting sequential baseline...");
+
+    let results = run_category(CATEGORY, workload, datasets, selection);
+
+    progress.line("Sequential baseline completed.");
+    results
+}
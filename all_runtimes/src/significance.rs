@@ -0,0 +1,349 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::results::BenchmarkResult;
+use crate::stats::{mean, std_dev};
+
+// Below this many samples per side without ties, Mann-Whitney p-values are exact
+const EXACT_MANN_WHITNEY_LIMIT: usize = 20;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+pub enum SignificanceTest {
+    /// Rank-based, makes no assumption about the timing distribution
+    MannWhitney,
+    /// Compares means, assumes roughly normal samples with unequal variances
+    Welch,
+}
+
+// Outcome of testing whether two libraries' samples come from the same distribution
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PairwiseComparison {
+    pub library_a: String,
+    pub library_b: String,
+    pub test: SignificanceTest,
+    pub p_value: f64,
+    pub alpha: f64,
+    pub significant: bool,
+    // Which library has the lower median, if the difference is significant
+    pub faster: Option<String>,
+}
+
+// Test every pair of results against each other
+pub fn compare_all(results: &[BenchmarkResult], test: SignificanceTest, alpha: f64) -> Vec<PairwiseComparison> {
+    let mut comparisons = Vec::new();
+
+    for (i, a) in results.iter().enumerate() {
+        for b in &results[i + 1..] {
+            let p_value = p_value(&a.all_times, &b.all_times, test);
+            let significant = p_value < alpha;
+            let faster = significant.then(|| {
+                if a.stats.median <= b.stats.median { a.library.clone() } else { b.library.clone() }
+            });
+            comparisons.push(PairwiseComparison {
+                library_a: a.library.clone(),
+                library_b: b.library.clone(),
+                test,
+                p_value,
+                alpha,
+                significant,
+                faster,
+            });
+        }
+    }
+
+    comparisons
+}
+
+pub fn find<'a>(comparisons: &'a [PairwiseComparison], a: &str, b: &str) -> Option<&'a PairwiseComparison> {
+    comparisons.iter().find(|c| {
+        (c.library_a == a && c.library_b == b) || (c.library_a == b && c.library_b == a)
+    })
+}
+
+pub fn p_value(a: &[Duration], b: &[Duration], test: SignificanceTest) -> f64 {
+    let a: Vec<f64> = a.iter().map(|d| d.as_nanos() as f64).collect();
+    let b: Vec<f64> = b.iter().map(|d| d.as_nanos() as f64).collect();
+    match test {
+        SignificanceTest::MannWhitney => mann_whitney_u(&a, &b),
+        SignificanceTest::Welch => welch_t_test(&a, &b),
+    }
+}
+
+// Two-sided Mann-Whitney U test. Exact for small tie-free samples, otherwise
+// the normal approximation with tie and continuity correction.
+pub fn mann_whitney_u(a: &[f64], b: &[f64]) -> f64 {
+    let (m, n) = (a.len(), b.len());
+    if m == 0 || n == 0 {
+        return 1.0;
+    }
+
+    // Rank the pooled samples, giving tied values their average rank
+    let mut pooled: Vec<(f64, bool)> = a.iter().map(|&v| (v, true)).chain(b.iter().map(|&v| (v, false))).collect();
+    pooled.sort_by(|x, y| x.0.total_cmp(&y.0));
+
+    let total = pooled.len();
+    let mut rank_sum_a = 0.0;
+    let mut tie_term = 0.0;
+    let mut has_ties = false;
+    let mut i = 0;
+    while i < total {
+        let mut j = i;
+        while j + 1 < total && pooled[j + 1].0 == pooled[i].0 {
+            j += 1;
+        }
+        let tied = (j - i + 1) as f64;
+        if tied > 1.0 {
+            has_ties = true;
+            tie_term += tied.powi(3) - tied;
+        }
+        let average_rank = (i + j) as f64 / 2.0 + 1.0;
+        rank_sum_a += pooled[i..=j].iter().filter(|(_, from_a)| *from_a).count() as f64 * average_rank;
+        i = j + 1;
+    }
+
+    let u = rank_sum_a - (m * (m + 1)) as f64 / 2.0;
+
+    if !has_ties && m <= EXACT_MANN_WHITNEY_LIMIT && n <= EXACT_MANN_WHITNEY_LIMIT {
+        return exact_mann_whitney(m, n, u.round() as usize);
+    }
+
+    let (mf, nf, nt) = (m as f64, n as f64, total as f64);
+    let mean_u = mf * nf / 2.0;
+    let variance = mf * nf / 12.0 * ((nt + 1.0) - tie_term / (nt * (nt - 1.0)));
+    if variance <= 0.0 {
+        return 1.0;
+    }
+    let z = ((u - mean_u).abs() - 0.5).max(0.0) / variance.sqrt();
+    (2.0 * (1.0 - normal_cdf(z))).min(1.0)
+}
+
+// Two-sided p-value from the exact null distribution of U
+fn exact_mann_whitney(m: usize, n: usize, u: usize) -> f64 {
+    // counts[i][j][k]: arrangements of i values from `a` and j from `b` with U = k
+    let max_u = m * n;
+    let mut counts = vec![vec![vec![0f64; max_u + 1]; n + 1]; m + 1];
+    for i in 0..=m {
+        for j in 0..=n {
+            if i == 0 || j == 0 {
+                counts[i][j][0] = 1.0;
+                continue;
+            }
+            for k in 0..=i * j {
+                // The largest value is either from `a` (beating all j values of `b`) or from `b`
+                let from_a = if k >= j { counts[i - 1][j][k - j] } else { 0.0 };
+                let from_b = counts[i][j - 1][k];
+                counts[i][j][k] = from_a + from_b;
+            }
+        }
+    }
+
+    let distribution = &counts[m][n];
+    let total: f64 = distribution.iter().sum();
+    let lower: f64 = distribution[..=u.min(max_u)].iter().sum::<f64>() / total;
+    let upper: f64 = distribution[u.min(max_u)..].iter().sum::<f64>() / total;
+    (2.0 * lower.min(upper)).min(1.0)
+}
+
+// Two-sided Welch's t-test
+pub fn welch_t_test(a: &[f64], b: &[f64]) -> f64 {
+    if a.len() < 2 || b.len() < 2 {
+        return 1.0;
+    }
+
+    let Some((t, df)) = welch_t(a, b) else {
+        return if mean(a) == mean(b) { 1.0 } else { 0.0 };
+    };
+
+    // P(|T| > t) for Student's t with `df` degrees of freedom
+    regularized_incomplete_beta(df / (df + t * t), df / 2.0, 0.5)
+}
+
+// The t statistic and Welch-Satterthwaite degrees of freedom, None if neither sample varies
+fn welch_t(a: &[f64], b: &[f64]) -> Option<(f64, f64)> {
+    let (mean_a, mean_b) = (mean(a), mean(b));
+    let var_a = std_dev(a, mean_a).powi(2) / a.len() as f64;
+    let var_b = std_dev(b, mean_b).powi(2) / b.len() as f64;
+    let standard_error = (var_a + var_b).sqrt();
+
+    if standard_error == 0.0 {
+        return None;
+    }
+
+    let t = (mean_a - mean_b) / standard_error;
+    let df = (var_a + var_b).powi(2)
+        / (var_a.powi(2) / (a.len() - 1) as f64 + var_b.powi(2) / (b.len() - 1) as f64);
+    Some((t, df))
+}
+
+fn normal_cdf(z: f64) -> f64 {
+    0.5 * (1.0 + erf(z / std::f64::consts::SQRT_2))
+}
+
+// Abramowitz & Stegun 7.1.26, accurate to about 1.5e-7
+fn erf(x: f64) -> f64 {
+    let sign = if x < 0.0 { -1.0 } else { 1.0 };
+    let x = x.abs();
+    let t = 1.0 / (1.0 + 0.3275911 * x);
+    let poly = t * (0.254829592 + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    sign * (1.0 - poly * (-x * x).exp())
+}
+
+// Lanczos approximation of ln Γ(x)
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 6] = [
+        76.18009172947146,
+        -86.50532032941677,
+        24.01409824083091,
+        -1.231739572450155,
+        0.1208650973866179e-2,
+        -0.5395239384953e-5,
+    ];
+    let tmp = x + 5.5;
+    let tmp = tmp - (x + 0.5) * tmp.ln();
+    let mut series = 1.000000000190015;
+    for (i, c) in COEFFICIENTS.iter().enumerate() {
+        series += c / (x + 1.0 + i as f64);
+    }
+    -tmp + (2.5066282746310005 * series / x).ln()
+}
+
+// I_x(a, b), evaluated with the continued fraction from Numerical Recipes
+fn regularized_incomplete_beta(x: f64, a: f64, b: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+
+    let front = (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln()).exp();
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_continued_fraction(x, a, b) / a
+    } else {
+        1.0 - front * beta_continued_fraction(1.0 - x, b, a) / b
+    }
+}
+
+fn beta_continued_fraction(x: f64, a: f64, b: f64) -> f64 {
+    const MAX_ITERATIONS: usize = 200;
+    const EPSILON: f64 = 3e-14;
+    const TINY: f64 = 1e-300;
+
+    let (qab, qap, qam) = (a + b, a + 1.0, a - 1.0);
+    let mut c = 1.0;
+    let mut d = 1.0 - qab * x / qap;
+    if d.abs() < TINY {
+        d = TINY;
+    }
+    d = 1.0 / d;
+    let mut h = d;
+
+    for m in 1..=MAX_ITERATIONS {
+        let m = m as f64;
+        let m2 = 2.0 * m;
+
+        let aa = m * (b - m) * x / ((qam + m2) * (a + m2));
+        d = 1.0 + aa * d;
+        if d.abs() < TINY {
+            d = TINY;
+        }
+        c = 1.0 + aa / c;
+        if c.abs() < TINY {
+            c = TINY;
+        }
+        d = 1.0 / d;
+        h *= d * c;
+
+        let aa = -(a + m) * (qab + m) * x / ((a + m2) * (qap + m2));
+        d = 1.0 + aa * d;
+        if d.abs() < TINY {
+            d = TINY;
+        }
+        c = 1.0 + aa / c;
+        if c.abs() < TINY {
+            c = TINY;
+        }
+        d = 1.0 / d;
+        let delta = d * c;
+        h *= delta;
+
+        if (delta - 1.0).abs() < EPSILON {
+            break;
+        }
+    }
+
+    h
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!((actual - expected).abs() <= tolerance, "{} is not within {} of {}", actual, tolerance, expected);
+    }
+
+    #[test]
+    fn exact_mann_whitney_on_separated_samples() {
+        // Only 1 of the C(6, 3) = 20 arrangements is this extreme on each side
+        assert_close(mann_whitney_u(&[1.0, 2.0, 3.0], &[4.0, 5.0, 6.0]), 0.1, 1e-12);
+        assert_close(mann_whitney_u(&[4.0, 5.0, 6.0], &[1.0, 2.0, 3.0]), 0.1, 1e-12);
+    }
+
+    #[test]
+    fn mann_whitney_switches_to_the_normal_approximation_above_twenty() {
+        let separated = |n: usize| -> (Vec<f64>, Vec<f64>) {
+            ((0..n).map(|v| v as f64).collect(), (n..2 * n).map(|v| v as f64).collect())
+        };
+
+        // Exact: 2 / C(40, 20)
+        let (a, b) = separated(EXACT_MANN_WHITNEY_LIMIT);
+        assert_close(mann_whitney_u(&a, &b), 1.4508889103849688e-11, 1e-20);
+
+        // Normal approximation, far above the exact 2 / C(42, 21) = 3.7e-12
+        let (a, b) = separated(EXACT_MANN_WHITNEY_LIMIT + 1);
+        assert_close(mann_whitney_u(&a, &b), 3.1253999832614454e-8, 1e-9);
+    }
+
+    #[test]
+    fn mann_whitney_with_ties_uses_average_ranks() {
+        // U = 3.5, tie term 30: z = 8.5 / sqrt(25 / 12 * (11 - 30 / 90))
+        let p = mann_whitney_u(&[1.0, 2.0, 2.0, 3.0, 4.0], &[2.0, 4.0, 5.0, 6.0, 7.0]);
+        assert_close(p, 0.07136901092402148, 1e-6);
+    }
+
+    #[test]
+    fn welch_on_a_textbook_pair() {
+        // Welch's t-test example 1 on Wikipedia: t = -2.46, df = 24.9, p = 0.021
+        let a = [27.5, 21.0, 19.0, 23.6, 17.0, 17.9, 16.9, 20.1, 21.9, 22.6, 23.1, 19.6, 19.0, 21.7, 21.4];
+        let b = [27.1, 22.0, 20.8, 23.4, 23.4, 23.5, 25.8, 22.0, 24.8, 20.2, 21.9, 22.1, 22.9, 20.5, 24.4];
+
+        let (t, df) = welch_t(&a, &b).expect("samples vary");
+        assert_close(t, -2.455356398286006, 1e-9);
+        assert_close(df, 24.98852929023142, 1e-9);
+        assert_close(welch_t_test(&a, &b), 0.021, 5e-4);
+        assert_eq!(welch_t_test(&[3.0, 3.0], &[3.0, 3.0]), 1.0);
+    }
+
+    #[test]
+    fn erf_matches_table_values() {
+        for (x, expected) in [(0.0, 0.0), (0.5, 0.5204998778), (1.0, 0.8427007929), (2.0, 0.9953222650)] {
+            assert_close(erf(x), expected, 1.5e-7);
+            assert_close(erf(-x), -expected, 1.5e-7);
+        }
+    }
+
+    #[test]
+    fn incomplete_beta_matches_table_values() {
+        // Closed forms: I_x(1, 1) = x, I_x(a, 1) = x^a, I_0.4(2, 3) = 0.5248, I_0.5(a, a) = 0.5
+        assert_close(regularized_incomplete_beta(0.3, 1.0, 1.0), 0.3, 1e-10);
+        assert_close(regularized_incomplete_beta(0.7, 3.0, 1.0), 0.343, 1e-10);
+        assert_close(regularized_incomplete_beta(0.4, 2.0, 3.0), 0.5248, 1e-10);
+        assert_close(regularized_incomplete_beta(0.5, 7.5, 7.5), 0.5, 1e-10);
+
+        // Student's t table: two-sided 5% critical value for 10 degrees of freedom is 2.228
+        let (t, df): (f64, f64) = (2.228, 10.0);
+        assert_close(regularized_incomplete_beta(df / (df + t * t), df / 2.0, 0.5), 0.05, 1e-4);
+    }
+}
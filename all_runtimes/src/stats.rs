@@ -0,0 +1,174 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::results::{nanos, nanos_pair};
+
+// Resamples drawn for each bootstrap confidence interval
+const BOOTSTRAP_RESAMPLES: usize = 2000;
+// Fixed so the same samples always produce the same interval
+const BOOTSTRAP_SEED: u64 = 0x5EED;
+
+// Summary of one library's timing samples. Durations are serialized as integer nanoseconds.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Statistics {
+    pub samples: usize,
+    #[serde(with = "nanos")]
+    pub min: Duration,
+    #[serde(with = "nanos")]
+    pub max: Duration,
+    #[serde(with = "nanos")]
+    pub mean: Duration,
+    #[serde(with = "nanos")]
+    pub median: Duration,
+    #[serde(with = "nanos")]
+    pub p90: Duration,
+    #[serde(with = "nanos")]
+    pub p99: Duration,
+    #[serde(with = "nanos")]
+    pub std_dev: Duration,
+    // Standard deviation divided by the mean
+    pub coefficient_of_variation: f64,
+    // Bootstrap 95% confidence intervals
+    #[serde(with = "nanos_pair")]
+    pub median_ci95: (Duration, Duration),
+    #[serde(with = "nanos_pair")]
+    pub mean_ci95: (Duration, Duration),
+}
+
+impl Statistics {
+    pub fn from_samples(samples: &[Duration]) -> Self {
+        if samples.is_empty() {
+            return Statistics::default();
+        }
+
+        let mut nanos: Vec<f64> = samples.iter().map(|d| d.as_nanos() as f64).collect();
+        nanos.sort_by(|a, b| a.total_cmp(b));
+
+        let mean_nanos = mean(&nanos);
+        let std_dev_nanos = std_dev(&nanos, mean_nanos);
+
+        Statistics {
+            samples: nanos.len(),
+            min: to_duration(nanos[0]),
+            max: to_duration(nanos[nanos.len() - 1]),
+            mean: to_duration(mean_nanos),
+            median: to_duration(percentile(&nanos, 50.0)),
+            p90: to_duration(percentile(&nanos, 90.0)),
+            p99: to_duration(percentile(&nanos, 99.0)),
+            std_dev: to_duration(std_dev_nanos),
+            coefficient_of_variation: if mean_nanos > 0.0 { std_dev_nanos / mean_nanos } else { 0.0 },
+            median_ci95: bootstrap_ci95(&nanos, |s| percentile(s, 50.0)),
+            mean_ci95: bootstrap_ci95(&nanos, mean),
+        }
+    }
+}
+
+// Median alone, without the cost of the full summary
+pub fn median(samples: &[Duration]) -> Duration {
+    let mut nanos: Vec<f64> = samples.iter().map(|d| d.as_nanos() as f64).collect();
+    nanos.sort_by(|a, b| a.total_cmp(b));
+    to_duration(percentile(&nanos, 50.0))
+}
+
+fn to_duration(nanos: f64) -> Duration {
+    Duration::from_nanos(nanos.max(0.0).round() as u64)
+}
+
+pub fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len().max(1) as f64
+}
+
+// Sample (n - 1) standard deviation
+pub fn std_dev(values: &[f64], mean: f64) -> f64 {
+    if values.len() < 2 {
+        return 0.0;
+    }
+    let sum_sq: f64 = values.iter().map(|v| (v - mean).powi(2)).sum();
+    (sum_sq / (values.len() - 1) as f64).sqrt()
+}
+
+// Linear interpolation between closest ranks; `sorted` must be ascending
+pub fn percentile(sorted: &[f64], pct: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = (pct / 100.0).clamp(0.0, 1.0) * (sorted.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    let weight = rank - lower as f64;
+    sorted[lower] + (sorted[upper] - sorted[lower]) * weight
+}
+
+// Percentile bootstrap: resample with replacement, take the 2.5th and 97.5th
+// percentiles of the statistic over all resamples
+fn bootstrap_ci95(sorted: &[f64], statistic: impl Fn(&[f64]) -> f64) -> (Duration, Duration) {
+    if sorted.len() < 2 {
+        let value = to_duration(statistic(sorted));
+        return (value, value);
+    }
+
+    let mut rng = StdRng::seed_from_u64(BOOTSTRAP_SEED);
+    let mut resample = vec![0.0; sorted.len()];
+    let mut estimates = Vec::with_capacity(BOOTSTRAP_RESAMPLES);
+
+    for _ in 0..BOOTSTRAP_RESAMPLES {
+        for slot in resample.iter_mut() {
+            *slot = sorted[rng.random_range(0..sorted.len())];
+        }
+        resample.sort_by(|a, b| a.total_cmp(b));
+        estimates.push(statistic(&resample));
+    }
+
+    estimates.sort_by(|a, b| a.total_cmp(b));
+    (to_duration(percentile(&estimates, 2.5)), to_duration(percentile(&estimates, 97.5)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn millis(values: &[u64]) -> Vec<Duration> {
+        values.iter().map(|&ms| Duration::from_millis(ms)).collect()
+    }
+
+    #[test]
+    fn median_of_odd_and_even_counts() {
+        assert_eq!(median(&millis(&[3, 1, 2])), Duration::from_millis(2));
+        assert_eq!(median(&millis(&[4, 1, 3, 2])), Duration::from_micros(2500));
+        assert_eq!(Statistics::from_samples(&millis(&[5, 9, 1, 7, 3])).median, Duration::from_millis(5));
+    }
+
+    #[test]
+    fn tail_percentiles_interpolate_between_ranks() {
+        let stats = Statistics::from_samples(&millis(&[10, 9, 8, 7, 6, 5, 4, 3, 2, 1]));
+        assert_eq!(stats.min, Duration::from_millis(1));
+        assert_eq!(stats.max, Duration::from_millis(10));
+        assert_eq!(stats.p90, Duration::from_micros(9100));
+        assert_eq!(stats.p99, Duration::from_micros(9910));
+    }
+
+    #[test]
+    fn equal_samples_have_no_spread() {
+        let stats = Statistics::from_samples(&millis(&[7; 5]));
+        assert_eq!(stats.std_dev, Duration::ZERO);
+        assert_eq!(stats.coefficient_of_variation, 0.0);
+        assert_eq!(stats.median_ci95, (Duration::from_millis(7), Duration::from_millis(7)));
+    }
+
+    #[test]
+    fn bootstrap_interval_brackets_the_median_and_repeats() {
+        assert_eq!(BOOTSTRAP_SEED, 0x5EED);
+        let samples = millis(&[12, 15, 11, 19, 14, 13, 30, 12, 16, 15, 14, 18]);
+        let first = Statistics::from_samples(&samples);
+        let (low, high) = first.median_ci95;
+        assert!(low < high, "degenerate interval {:?}", first.median_ci95);
+        assert!(low <= first.median && first.median <= high, "{:?} outside {:?}", first.median, first.median_ci95);
+        assert!(first.mean_ci95.0 <= first.mean && first.mean <= first.mean_ci95.1);
+
+        let second = Statistics::from_samples(&samples);
+        assert_eq!(first.median_ci95, second.median_ci95);
+        assert_eq!(first.mean_ci95, second.mean_ci95);
+    }
+}
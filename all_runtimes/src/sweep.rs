@@ -0,0 +1,733 @@
+use serde::{Deserialize, Serialize};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use crate::adapter::{measure, registry, Datasets};
+use crate::async_check::calibrate;
+use crate::cli::{CollectArgs, MeasureOneArgs, ScaleArgs, SweepArgs, ThreadsArgs};
+use crate::collect::Collection;
+use crate::results::{nanos, nanos_option, BenchmarkResult, Category, Phase, RunMetadata, Warmup, WorkCost, Workload};
+use crate::sequential_check::SequentialAdapter;
+
+// One library at one per-item cost and chunk size
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SweepPoint {
+    pub category: Category,
+    pub library: String,
+    pub work_units: u64,
+    #[serde(with = "nanos")]
+    pub item_time: Duration,
+    // None for libraries that split the work their own way
+    pub chunk_size: Option<usize>,
+    // Work handed to each task: item cost times chunk size (just the item cost
+    // for libraries without a chunk size)
+    #[serde(with = "nanos")]
+    pub task_time: Duration,
+    #[serde(with = "nanos")]
+    pub median: Duration,
+    // Against the sequential baseline at the same item cost
+    pub speedup: f64,
+    pub efficiency: f64,
+}
+
+// Smallest task size from which a library stays at or above each threshold,
+// or None if it never gets there within the sweep
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Crossover {
+    pub category: Category,
+    pub library: String,
+    #[serde(with = "nanos_option")]
+    pub matches_baseline: Option<Duration>,
+    #[serde(with = "nanos_option")]
+    pub reaches_efficiency: Option<Duration>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Sweep {
+    pub metadata: RunMetadata,
+    pub data_size: usize,
+    pub iterations: usize,
+    pub seed: u64,
+    pub ns_per_unit: f64,
+    pub efficiency_target: f64,
+    pub points: Vec<SweepPoint>,
+    pub crossovers: Vec<Crossover>,
+}
+
+// Measure every selected library at every item cost and chunk size
+pub fn run_sweep(args: &SweepArgs) -> Sweep {
+    let ns_per_unit = calibrate();
+    let seed = args.seed.unwrap_or_else(rand::random);
+    let mut points = Vec::new();
+
+    for &target in &args.costs {
+        let units = ((target.as_nanos() as f64 / ns_per_unit).round() as u64).max(1);
+        let cost = WorkCost { units, ns_per_unit };
+        eprintln!("Sweeping {:.2?} per value ({} units)...", cost.item_time(), units);
+
+        let mut workload = Workload::cpu_bound(args.size, args.iterations, Warmup::Fixed { iterations: args.warmup },
+                                               Phase::Workload, seed, args.verify, cost);
+        let datasets = Datasets::generate(seed, args.size, args.iterations, cost);
+
+        // Sweeps are CPU-bound, which every library can run, so `measure` only
+        // comes back empty for simulated I/O and never here
+        let Some(baseline) = measure(&mut SequentialAdapter, &workload, &datasets) else {
+            continue;
+        };
+        let baseline = BenchmarkResult::from_measurement(&SequentialAdapter, &workload, baseline);
+
+        let adapters = registry()
+            .into_iter()
+            .filter(|adapter| adapter.category() != Category::Sequential && args.selection.includes(adapter.as_ref()));
+
+        for mut adapter in adapters {
+            let chunk_sizes: Vec<Option<usize>> = if adapter.honours_chunk_size() {
+                args.chunk_sizes.iter().map(|&n| Some(n)).collect()
+            } else {
+                vec![None]
+            };
+
+            for chunk_size in chunk_sizes {
+                let task_time = u32::try_from(chunk_size.unwrap_or(1))
+                    .ok()
+                    .and_then(|n| cost.item_time().checked_mul(n));
+                let Some(task_time) = task_time else {
+                    eprintln!("{} at chunk size {} makes a task too long to time, skipped", adapter.name(),
+                              chunk_size.unwrap_or(1));
+                    continue;
+                };
+
+                workload.chunk_size = chunk_size.unwrap_or(1);
+                let Some(measurement) = measure(adapter.as_mut(), &workload, &datasets) else {
+                    continue;
+                };
+                let result = BenchmarkResult::from_measurement(adapter.as_ref(), &workload, measurement);
+                if !result.verification.passed {
+                    eprintln!("{} produced wrong output at chunk size {}, skipped: {}", result.library,
+                              workload.chunk_size, result.verification.detail.as_deref().unwrap_or_default());
+                    continue;
+                }
+
+                let speedup = baseline.stats.median.as_nanos() as f64 / result.stats.median.as_nanos().max(1) as f64;
+                points.push(SweepPoint {
+                    category: result.category,
+                    library: result.library,
+                    work_units: units,
+                    item_time: cost.item_time(),
+                    chunk_size,
+                    task_time,
+                    median: result.stats.median,
+                    speedup,
+                    efficiency: speedup / result.threads.max(1) as f64,
+                });
+            }
+        }
+    }
+
+    let crossovers = crossovers(&points, args.efficiency);
+    Sweep {
+        metadata: RunMetadata::collect(),
+        data_size: args.size,
+        iterations: args.iterations,
+        seed,
+        ns_per_unit,
+        efficiency_target: args.efficiency,
+        points,
+        crossovers,
+    }
+}
+
+fn crossovers(points: &[SweepPoint], efficiency_target: f64) -> Vec<Crossover> {
+    let mut libraries: Vec<(Category, &str)> = Vec::new();
+    for point in points {
+        if !libraries.iter().any(|(_, name)| *name == point.library) {
+            libraries.push((point.category, &point.library));
+        }
+    }
+
+    libraries
+        .into_iter()
+        .map(|(category, library)| {
+            let mut own: Vec<&SweepPoint> = points.iter().filter(|p| p.library == library).collect();
+            own.sort_by_key(|p| p.task_time);
+            Crossover {
+                category,
+                library: library.to_string(),
+                matches_baseline: threshold(&own, |p| p.speedup >= 1.0),
+                reaches_efficiency: threshold(&own, |p| p.efficiency >= efficiency_target),
+            }
+        })
+        .collect()
+}
+
+// Smallest task size from which every larger one also meets `meets`; a single
+// lucky point below a failing one doesn't count. `sorted` must be by task size.
+fn threshold(sorted: &[&SweepPoint], meets: impl Fn(&SweepPoint) -> bool) -> Option<Duration> {
+    let first_of_tail = sorted.iter().rposition(|p| !meets(p)).map_or(0, |i| i + 1);
+    sorted.get(first_of_tail).map(|p| p.task_time)
+}
+
+// One library at one data size
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScalingPoint {
+    pub category: Category,
+    pub library: String,
+    pub data_size: usize,
+    #[serde(with = "nanos")]
+    pub median: Duration,
+    // Values processed per second at the median time
+    pub throughput: f64,
+}
+
+// Where a library's throughput levels off
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Saturation {
+    pub category: Category,
+    pub library: String,
+    pub peak_throughput: f64,
+    pub peak_size: usize,
+    // Smallest size reaching SATURATION_SHARE of the peak
+    pub saturates_at: usize,
+}
+
+// Share of peak throughput that counts as saturated
+pub const SATURATION_SHARE: f64 = 0.9;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Scaling {
+    pub metadata: RunMetadata,
+    pub iterations: usize,
+    pub seed: u64,
+    pub cost: WorkCost,
+    pub chunk_size: usize,
+    pub sizes: Vec<usize>,
+    pub points: Vec<ScalingPoint>,
+    pub saturation: Vec<Saturation>,
+}
+
+// Measure every selected library (and the sequential baseline) at every data size
+pub fn run_scaling(args: &ScaleArgs, cost: WorkCost) -> Scaling {
+    let seed = args.seed.unwrap_or_else(rand::random);
+    let sizes = args.sizes.sizes(args.points_per_decade);
+    let mut points = Vec::new();
+
+    for &size in &sizes {
+        eprintln!("Scaling at {} values...", size);
+        let mut workload = Workload::cpu_bound(size, args.iterations, Warmup::Fixed { iterations: args.warmup },
+                                               Phase::Workload, seed, args.verify, cost);
+        workload.chunk_size = args.chunk_size.max(1);
+        let datasets = Datasets::generate(seed, size, args.iterations, cost);
+
+        let adapters = registry().into_iter().filter(|adapter| args.selection.includes(adapter.as_ref()));
+        for mut adapter in adapters {
+            let Some(measurement) = measure(adapter.as_mut(), &workload, &datasets) else {
+                continue;
+            };
+            let result = BenchmarkResult::from_measurement(adapter.as_ref(), &workload, measurement);
+            if !result.verification.passed {
+                eprintln!("{} produced wrong output at {} values, skipped: {}", result.library, size,
+                          result.verification.detail.as_deref().unwrap_or_default());
+                continue;
+            }
+
+            points.push(ScalingPoint {
+                category: result.category,
+                library: result.library,
+                data_size: size,
+                median: result.stats.median,
+                throughput: size as f64 / result.stats.median.as_secs_f64().max(1e-9),
+            });
+        }
+    }
+
+    let saturation = saturation(&points);
+    Scaling {
+        metadata: RunMetadata::collect(),
+        iterations: args.iterations,
+        seed,
+        cost,
+        chunk_size: args.chunk_size.max(1),
+        sizes,
+        points,
+        saturation,
+    }
+}
+
+fn saturation(points: &[ScalingPoint]) -> Vec<Saturation> {
+    let mut summary: Vec<Saturation> = Vec::new();
+    for point in points {
+        if summary.iter().any(|s| s.library == point.library) {
+            continue;
+        }
+        let series: Vec<&ScalingPoint> = points.iter().filter(|p| p.library == point.library).collect();
+        let peak = series.iter().max_by(|a, b| a.throughput.total_cmp(&b.throughput)).unwrap();
+        let saturates_at = series.iter()
+            .filter(|p| p.throughput >= peak.throughput * SATURATION_SHARE)
+            .map(|p| p.data_size)
+            .min()
+            .unwrap_or(peak.data_size);
+        summary.push(Saturation {
+            category: point.category,
+            library: point.library.clone(),
+            peak_throughput: peak.throughput,
+            peak_size: peak.data_size,
+            saturates_at,
+        });
+    }
+    summary
+}
+
+// One library at one worker count
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ThreadPoint {
+    pub category: Category,
+    pub library: String,
+    pub threads: usize,
+    #[serde(with = "nanos")]
+    pub median: Duration,
+    // Against the same library on a single worker
+    pub speedup: f64,
+    pub efficiency: f64,
+}
+
+// Amdahl's law fitted to a library's strong-scaling curve: T(n) = T(1) * (s + (1 - s) / n)
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AmdahlFit {
+    pub category: Category,
+    pub library: String,
+    pub serial_fraction: f64,
+    // 1 / s, the speedup no number of workers can beat; None when s is 0
+    pub max_speedup: Option<f64>,
+    pub r_squared: f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ThreadScaling {
+    pub metadata: RunMetadata,
+    pub data_size: usize,
+    pub iterations: usize,
+    pub seed: u64,
+    pub cost: WorkCost,
+    pub chunk_size: usize,
+    pub thread_counts: Vec<usize>,
+    pub points: Vec<ThreadPoint>,
+    pub fits: Vec<AmdahlFit>,
+    // Libraries whose worker count can't be set, left out of the sweep
+    pub fixed: Vec<String>,
+}
+
+// Measure every selected library at every worker count
+pub fn run_thread_scaling(args: &ThreadsArgs, cost: WorkCost) -> ThreadScaling {
+    let seed = args.seed.unwrap_or_else(rand::random);
+    let thread_counts = args.thread_counts();
+    let mut workload = Workload::cpu_bound(args.size, args.iterations, Warmup::Fixed { iterations: args.warmup },
+                                           Phase::Workload, seed, args.verify, cost);
+    workload.chunk_size = args.chunk_size.max(1);
+    let datasets = Datasets::generate(seed, args.size, args.iterations, cost);
+
+    let mut points = Vec::new();
+    let mut fixed = Vec::new();
+
+    let adapters = registry()
+        .into_iter()
+        .filter(|adapter| adapter.category() != Category::Sequential && args.selection.includes(adapter.as_ref()));
+
+    for mut adapter in adapters {
+        let env_var = adapter.workers_env_var();
+        if !adapter.set_workers(1) && env_var.is_none() {
+            fixed.push(adapter.name().to_string());
+            continue;
+        }
+
+        let mut series: Vec<(usize, Duration)> = Vec::new();
+        for &threads in &thread_counts {
+            eprintln!("Measuring {} on {} worker(s)...", adapter.name(), threads);
+            let result = match env_var {
+                Some(var) => match measure_in_subprocess(adapter.name(), var, threads, &workload) {
+                    Ok(result) => result,
+                    Err(e) => {
+                        eprintln!("{} on {} worker(s) failed, skipped: {}", adapter.name(), threads, e);
+                        continue;
+                    }
+                },
+                None => {
+                    adapter.set_workers(threads);
+                    let Some(measurement) = measure(adapter.as_mut(), &workload, &datasets) else {
+                        continue;
+                    };
+                    BenchmarkResult::from_measurement(adapter.as_ref(), &workload, measurement)
+                }
+            };
+            if !result.verification.passed {
+                eprintln!("{} produced wrong output on {} worker(s), skipped: {}", result.library, threads,
+                          result.verification.detail.as_deref().unwrap_or_default());
+                continue;
+            }
+            series.push((threads, result.stats.median));
+        }
+
+        let Some(&(_, single)) = series.iter().find(|(threads, _)| *threads == 1) else {
+            continue;
+        };
+        for &(threads, median) in &series {
+            let speedup = single.as_nanos() as f64 / median.as_nanos().max(1) as f64;
+            points.push(ThreadPoint {
+                category: adapter.category(),
+                library: adapter.name().to_string(),
+                threads,
+                median,
+                speedup,
+                efficiency: speedup / threads as f64,
+            });
+        }
+    }
+
+    let fits = amdahl_fits(&points);
+    ThreadScaling {
+        metadata: RunMetadata::collect(),
+        data_size: args.size,
+        iterations: args.iterations,
+        seed,
+        cost,
+        chunk_size: workload.chunk_size,
+        thread_counts,
+        points,
+        fits,
+        fixed,
+    }
+}
+
+// Run `measure-one` in a fresh copy of this binary with the executor's
+// environment variable set, since a global executor only reads it once
+fn measure_in_subprocess(library: &str, env_var: &str, threads: usize, workload: &Workload) -> Result<BenchmarkResult, String> {
+    let warmup = match workload.warmup {
+        Warmup::Fixed { iterations } => iterations,
+        Warmup::Auto { max_iterations, .. } => max_iterations,
+    };
+    let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    let output = Command::new(exe)
+        .arg("measure-one")
+        .arg(library)
+        .args(["--size", &workload.data_size.to_string()])
+        .args(["--iterations", &workload.iterations.to_string()])
+        .args(["--warmup", &warmup.to_string()])
+        .args(["--seed", &workload.seed.to_string()])
+        .args(["--work-units", &workload.cost.units.to_string()])
+        .args(["--ns-per-unit", &workload.cost.ns_per_unit.to_string()])
+        .args(["--chunk-size", &workload.chunk_size.to_string()])
+        .args(["--verify", workload.verify.as_str()])
+        .env(env_var, threads.to_string())
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(format!("measurement process exited with {}", output.status));
+    }
+    serde_json::from_slice(&output.stdout).map_err(|e| e.to_string())
+}
+
+// The child side of `measure_in_subprocess`
+pub fn measure_one(args: &MeasureOneArgs) -> Result<BenchmarkResult, String> {
+    let mut adapter = registry()
+        .into_iter()
+        .find(|adapter| adapter.name().eq_ignore_ascii_case(&args.library))
+        .ok_or_else(|| format!("unknown library {}", args.library))?;
+
+    let cost = WorkCost { units: args.work_units, ns_per_unit: args.ns_per_unit };
+    let mut workload = Workload::cpu_bound(args.size, args.iterations, Warmup::Fixed { iterations: args.warmup },
+                                           Phase::Workload, args.seed, args.verify, cost);
+    workload.chunk_size = args.chunk_size.max(1);
+    let datasets = Datasets::generate(args.seed, args.size, args.iterations, cost);
+
+    let measurement = measure(adapter.as_mut(), &workload, &datasets)
+        .ok_or_else(|| format!("{} couldn't run the workload", adapter.name()))?;
+    Ok(BenchmarkResult::from_measurement(adapter.as_ref(), &workload, measurement))
+}
+
+// Least-squares fit of T(n) = a + b / n per library, so s = a / (a + b)
+fn amdahl_fits(points: &[ThreadPoint]) -> Vec<AmdahlFit> {
+    let mut fits: Vec<AmdahlFit> = Vec::new();
+    for point in points {
+        if fits.iter().any(|f| f.library == point.library) {
+            continue;
+        }
+        let series: Vec<(f64, f64)> = points.iter()
+            .filter(|p| p.library == point.library)
+            .map(|p| (1.0 / p.threads as f64, p.median.as_nanos() as f64))
+            .collect();
+        if series.len() < 2 {
+            continue;
+        }
+
+        let n = series.len() as f64;
+        let mean_x = series.iter().map(|(x, _)| x).sum::<f64>() / n;
+        let mean_y = series.iter().map(|(_, y)| y).sum::<f64>() / n;
+        let sxx: f64 = series.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+        let sxy: f64 = series.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+        let syy: f64 = series.iter().map(|(_, y)| (y - mean_y).powi(2)).sum();
+        let b = if sxx > 0.0 { sxy / sxx } else { 0.0 };
+        let a = mean_y - b * mean_x;
+
+        let serial_fraction = if a + b > 0.0 { (a / (a + b)).clamp(0.0, 1.0) } else { 1.0 };
+        fits.push(AmdahlFit {
+            category: point.category,
+            library: point.library.clone(),
+            serial_fraction,
+            max_speedup: (serial_fraction > 0.0).then(|| 1.0 / serial_fraction),
+            r_squared: if sxx > 0.0 && syy > 0.0 { sxy * sxy / (sxx * syy) } else { 0.0 },
+        });
+    }
+    fits
+}
+
+// One library collecting its results one way
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CollectionPoint {
+    pub category: Category,
+    pub library: String,
+    pub collection: Collection,
+    #[serde(with = "nanos")]
+    pub median: Duration,
+    // Against the same library collecting through the mutex
+    pub vs_mutex: Option<f64>,
+}
+
+// How much of a library's time goes on the shared lock: what the fastest other
+// strategy saves over the mutex, as a share of the mutex run
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LockOverhead {
+    pub category: Category,
+    pub library: String,
+    #[serde(with = "nanos")]
+    pub mutex: Duration,
+    pub fastest: Collection,
+    #[serde(with = "nanos")]
+    pub fastest_median: Duration,
+    // Zero when nothing beats the mutex
+    pub overhead_share: f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CollectionMatrix {
+    pub metadata: RunMetadata,
+    pub data_size: usize,
+    pub iterations: usize,
+    pub seed: u64,
+    pub cost: WorkCost,
+    pub chunk_size: usize,
+    pub collections: Vec<Collection>,
+    pub points: Vec<CollectionPoint>,
+    pub overheads: Vec<LockOverhead>,
+    // Libraries left out of a strategy they can't collect through
+    pub unsupported: Vec<(String, Collection)>,
+}
+
+// Measure every selected library with every collection strategy. The sequential
+// baseline collects nothing across tasks, so it isn't part of it.
+pub fn run_collection(args: &CollectArgs, cost: WorkCost) -> CollectionMatrix {
+    let seed = args.seed.unwrap_or_else(rand::random);
+    let mut collections = vec![Collection::Mutex];
+    for &collection in &args.collections {
+        if !collections.contains(&collection) {
+            collections.push(collection);
+        }
+    }
+    let mut workload = Workload::cpu_bound(args.size, args.iterations, Warmup::Fixed { iterations: args.warmup },
+                                           Phase::Workload, seed, args.verify, cost);
+    workload.chunk_size = args.chunk_size.max(1);
+    let datasets = Datasets::generate(seed, args.size, args.iterations, cost);
+
+    let mut points = Vec::new();
+    let mut unsupported = Vec::new();
+
+    let adapters = registry()
+        .into_iter()
+        .filter(|adapter| adapter.category() != Category::Sequential && args.selection.includes(adapter.as_ref()));
+
+    for mut adapter in adapters {
+        let mut series: Vec<(Collection, Duration)> = Vec::new();
+        for &collection in &collections {
+            if !adapter.supports_collection(collection) {
+                unsupported.push((adapter.name().to_string(), collection));
+                continue;
+            }
+            eprintln!("Measuring {} collecting through {}...", adapter.name(), collection.as_str());
+            workload.collection = collection;
+            let Some(measurement) = measure(adapter.as_mut(), &workload, &datasets) else {
+                continue;
+            };
+            let result = BenchmarkResult::from_measurement(adapter.as_ref(), &workload, measurement);
+            if !result.verification.passed {
+                eprintln!("{} produced wrong output collecting through {}, skipped: {}", result.library,
+                          collection.as_str(), result.verification.detail.as_deref().unwrap_or_default());
+                continue;
+            }
+            series.push((collection, result.stats.median));
+        }
+
+        let mutex = series.iter().find(|(collection, _)| *collection == Collection::Mutex).map(|&(_, median)| median);
+        for &(collection, median) in &series {
+            points.push(CollectionPoint {
+                category: adapter.category(),
+                library: adapter.name().to_string(),
+                collection,
+                median,
+                vs_mutex: mutex.map(|mutex| median.as_nanos() as f64 / mutex.as_nanos().max(1) as f64),
+            });
+        }
+    }
+
+    let overheads = lock_overheads(&points);
+    CollectionMatrix {
+        metadata: RunMetadata::collect(),
+        data_size: args.size,
+        iterations: args.iterations,
+        seed,
+        cost,
+        chunk_size: workload.chunk_size,
+        collections,
+        points,
+        overheads,
+        unsupported,
+    }
+}
+
+fn lock_overheads(points: &[CollectionPoint]) -> Vec<LockOverhead> {
+    let mut overheads: Vec<LockOverhead> = Vec::new();
+    for point in points.iter().filter(|p| p.collection == Collection::Mutex) {
+        let fastest = points.iter()
+            .filter(|p| p.library == point.library && p.collection != Collection::Mutex)
+            .min_by_key(|p| p.median);
+        let Some(fastest) = fastest else {
+            continue;
+        };
+        let saved = point.median.saturating_sub(fastest.median);
+        overheads.push(LockOverhead {
+            category: point.category,
+            library: point.library.clone(),
+            mutex: point.median,
+            fastest: fastest.collection,
+            fastest_median: fastest.median,
+            overhead_share: saved.as_nanos() as f64 / point.median.as_nanos().max(1) as f64,
+        });
+    }
+    overheads
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sweep_point(task_micros: u64, speedup: f64) -> SweepPoint {
+        SweepPoint {
+            category: Category::Parallel,
+            library: "Rayon".to_string(),
+            work_units: 1,
+            item_time: Duration::from_micros(1),
+            chunk_size: Some(task_micros as usize),
+            task_time: Duration::from_micros(task_micros),
+            median: Duration::from_millis(1),
+            speedup,
+            efficiency: speedup / 4.0,
+        }
+    }
+
+    fn scaling_point(library: &str, data_size: usize, throughput: f64) -> ScalingPoint {
+        ScalingPoint {
+            category: Category::Parallel,
+            library: library.to_string(),
+            data_size,
+            median: Duration::from_millis(1),
+            throughput,
+        }
+    }
+
+    #[test]
+    fn threshold_needs_every_larger_task_to_meet_it() {
+        let cases: [(&[f64], Option<u64>); 6] = [
+            (&[1.5, 2.0, 3.0], Some(1)),
+            (&[0.5, 0.8, 0.9], None),
+            (&[0.5, 1.2, 2.0], Some(2)),
+            // A lucky early point followed by a failing one doesn't count
+            (&[1.1, 0.7, 1.5, 2.5], Some(4)),
+            (&[1.5, 2.0, 0.9], None),
+            (&[], None),
+        ];
+        for (speedups, expected) in cases {
+            let points: Vec<SweepPoint> =
+                speedups.iter().enumerate().map(|(i, &s)| sweep_point(1 << i, s)).collect();
+            let sorted: Vec<&SweepPoint> = points.iter().collect();
+            assert_eq!(threshold(&sorted, |p| p.speedup >= 1.0), expected.map(Duration::from_micros),
+                       "speedups {:?}", speedups);
+        }
+    }
+
+    #[test]
+    fn saturation_is_the_smallest_size_near_the_peak() {
+        // (throughput at 1e2, 1e3, 1e4, 1e5 values) -> (peak size, saturates at)
+        let cases: [([f64; 4], usize, usize); 4] = [
+            ([1e6, 5e6, 9.5e6, 1e7], 100_000, 10_000),
+            ([1e6, 5e6, 8.9e6, 1e7], 100_000, 100_000),
+            ([4e6, 3e6, 2e6, 1e6], 100, 100),
+            ([9.2e6, 3e6, 1e7, 6e6], 10_000, 100),
+        ];
+        for (throughputs, peak_size, saturates_at) in cases {
+            let points: Vec<ScalingPoint> = throughputs.iter().enumerate()
+                .map(|(i, &t)| scaling_point("Rayon", 10usize.pow(i as u32 + 2), t))
+                .collect();
+            let summary = saturation(&points);
+            assert_eq!(summary.len(), 1);
+            assert_eq!((summary[0].peak_size, summary[0].saturates_at), (peak_size, saturates_at),
+                       "throughputs {:?}", throughputs);
+        }
+    }
+
+    #[test]
+    fn saturation_is_per_library() {
+        let points = [
+            scaling_point("Rayon", 100, 1.0),
+            scaling_point("Tokio", 100, 5.0),
+            scaling_point("Rayon", 1000, 10.0),
+            scaling_point("Tokio", 1000, 4.0),
+        ];
+        let summary = saturation(&points);
+        let found: Vec<(&str, usize, usize)> =
+            summary.iter().map(|s| (s.library.as_str(), s.peak_size, s.saturates_at)).collect();
+        assert_eq!(found, [("Rayon", 1000, 1000), ("Tokio", 100, 100)]);
+    }
+
+    #[test]
+    fn amdahl_fit_recovers_the_serial_fraction() {
+        // T(n) = T(1) * (s + (1 - s) / n) with T(1) = 8ms, exactly representable in nanoseconds
+        let cases: [(f64, Option<f64>); 4] = [(0.25, Some(4.0)), (0.0, None), (0.5, Some(2.0)), (1.0, Some(1.0))];
+        for (serial, max_speedup) in cases {
+            let points: Vec<ThreadPoint> = [1, 2, 4, 8]
+                .into_iter()
+                .map(|threads| ThreadPoint {
+                    category: Category::Parallel,
+                    library: "Rayon".to_string(),
+                    threads,
+                    median: Duration::from_nanos((8e6 * (serial + (1.0 - serial) / threads as f64)) as u64),
+                    speedup: 1.0,
+                    efficiency: 1.0,
+                })
+                .collect();
+            let fits = amdahl_fits(&points);
+            assert_eq!(fits.len(), 1);
+            assert!((fits[0].serial_fraction - serial).abs() < 1e-9,
+                    "fitted {} for s = {}", fits[0].serial_fraction, serial);
+            assert_eq!(fits[0].max_speedup.map(|m| (m * 1e6).round() / 1e6), max_speedup, "s = {}", serial);
+        }
+
+        // A single worker count can't be fitted
+        let single = ThreadPoint {
+            category: Category::Parallel,
+            library: "Rayon".to_string(),
+            threads: 4,
+            median: Duration::from_millis(1),
+            speedup: 1.0,
+            efficiency: 1.0,
+        };
+        assert!(amdahl_fits(&[single]).is_empty());
+    }
+}
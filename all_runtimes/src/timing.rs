@@ -0,0 +1,126 @@
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::results::nanos;
+use crate::stats;
+
+// Whether spawned tasks record their timings; off unless --task-timing is given
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+// Every task finished since the last `take`
+static TASKS: Mutex<Vec<TaskTiming>> = Mutex::new(Vec::new());
+
+pub fn enable(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+// When one task was spawned, first ran and finished
+#[derive(Clone, Copy, Debug)]
+pub struct TaskTiming {
+    pub spawned: Instant,
+    pub started: Instant,
+    pub finished: Instant,
+}
+
+impl TaskTiming {
+    // Time spent queued before the task first ran (first poll, for async tasks)
+    pub fn queue_wait(&self) -> Duration {
+        self.started.saturating_duration_since(self.spawned)
+    }
+
+    pub fn execution(&self) -> Duration {
+        self.finished.saturating_duration_since(self.started)
+    }
+}
+
+// Taken where a task is spawned and moved into it
+#[derive(Clone, Copy)]
+pub struct Spawned(Option<Instant>);
+
+pub fn spawned() -> Spawned {
+    Spawned(enabled().then(Instant::now))
+}
+
+impl Spawned {
+    // Call first thing in the task body; the task counts as finished when the guard drops
+    pub fn start(self) -> Running {
+        Running(self.0.map(|spawned| (spawned, Instant::now())))
+    }
+}
+
+pub struct Running(Option<(Instant, Instant)>);
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        if let Some((spawned, started)) = self.0 {
+            let finished = Instant::now();
+            TASKS.lock().unwrap().push(TaskTiming { spawned, started, finished });
+        }
+    }
+}
+
+// Hand over and forget everything recorded so far
+pub fn take() -> Vec<TaskTiming> {
+    std::mem::take(&mut *TASKS.lock().unwrap())
+}
+
+// Spread of one per-task duration
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Distribution {
+    #[serde(with = "nanos")]
+    pub mean: Duration,
+    #[serde(with = "nanos")]
+    pub p50: Duration,
+    #[serde(with = "nanos")]
+    pub p90: Duration,
+    #[serde(with = "nanos")]
+    pub p99: Duration,
+    #[serde(with = "nanos")]
+    pub max: Duration,
+}
+
+impl Distribution {
+    fn from_durations(durations: impl Iterator<Item = Duration>) -> Self {
+        let mut nanos: Vec<f64> = durations.map(|d| d.as_nanos() as f64).collect();
+        nanos.sort_by(|a, b| a.total_cmp(b));
+        let at = |pct: f64| Duration::from_nanos(stats::percentile(&nanos, pct).round() as u64);
+        Distribution {
+            mean: Duration::from_nanos(stats::mean(&nanos).round() as u64),
+            p50: at(50.0),
+            p90: at(90.0),
+            p99: at(99.0),
+            max: nanos.last().map_or(Duration::ZERO, |&max| Duration::from_nanos(max as u64)),
+        }
+    }
+}
+
+// Per-task scheduling delay and execution time over a library's timed iterations
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TaskTimes {
+    pub tasks: usize,
+    pub queue_wait: Distribution,
+    pub execution: Distribution,
+}
+
+impl TaskTimes {
+    pub fn from_timings(timings: &[TaskTiming]) -> Self {
+        TaskTimes {
+            tasks: timings.len(),
+            queue_wait: Distribution::from_durations(timings.iter().map(TaskTiming::queue_wait)),
+            execution: Distribution::from_durations(timings.iter().map(TaskTiming::execution)),
+        }
+    }
+
+    // Share of a task's time from spawn to finish spent waiting to run, on average
+    pub fn wait_share(&self) -> f64 {
+        let wait = self.queue_wait.mean.as_secs_f64();
+        let total = wait + self.execution.mean.as_secs_f64();
+        if total > 0.0 { wait / total } else { 0.0 }
+    }
+}
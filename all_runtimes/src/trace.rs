@@ -0,0 +1,182 @@
+use serde::Serialize;
+use serde_json::json;
+use std::cell::Cell;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+
+// Whether the first timed iteration of every library is traced; off unless --trace is given
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+// Whether items are being recorded right now
+static RECORDING: AtomicBool = AtomicBool::new(false);
+
+// What every timestamp is measured from
+static EPOCH: OnceLock<Instant> = OnceLock::new();
+
+// Items recorded since the last `stop`
+static EVENTS: Mutex<Vec<ItemEvent>> = Mutex::new(Vec::new());
+
+// Every library traced so far, in the order it ran
+static LIBRARIES: Mutex<Vec<(String, Vec<ItemEvent>)>> = Mutex::new(Vec::new());
+
+// Small sequential thread ids, since std's aren't numbers, and the name of each
+static NEXT_THREAD: AtomicU64 = AtomicU64::new(1);
+static THREAD_NAMES: Mutex<Vec<(u64, String)>> = Mutex::new(Vec::new());
+
+thread_local! {
+    static THREAD: Cell<u64> = const { Cell::new(0) };
+}
+
+// The calling thread's id, naming it the first time it's seen
+fn thread_id() -> u64 {
+    THREAD.with(|id| {
+        if id.get() == 0 {
+            id.set(NEXT_THREAD.fetch_add(1, Ordering::Relaxed));
+            let name = std::thread::current().name().unwrap_or("unnamed").to_string();
+            THREAD_NAMES.lock().unwrap().push((id.get(), name));
+        }
+        id.get()
+    })
+}
+
+// One value processed by one task on one thread
+#[derive(Clone, Debug)]
+pub struct ItemEvent {
+    pub thread: u64,
+    pub task: usize,
+    pub item: usize,
+    pub start: Instant,
+    pub end: Instant,
+}
+
+pub fn enable(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+// Start recording every item processed from now on
+pub fn start() {
+    EPOCH.get_or_init(Instant::now);
+    EVENTS.lock().unwrap().clear();
+    RECORDING.store(true, Ordering::Relaxed);
+}
+
+// Stop recording and file what was recorded under `library`
+pub fn stop(library: &str) {
+    RECORDING.store(false, Ordering::Relaxed);
+    let events = std::mem::take(&mut *EVENTS.lock().unwrap());
+    LIBRARIES.lock().unwrap().push((library.to_string(), events));
+}
+
+// Stop recording and throw away what was recorded
+pub fn discard() {
+    RECORDING.store(false, Ordering::Relaxed);
+    EVENTS.lock().unwrap().clear();
+}
+
+// Items one task processes, kept locally and handed over in one go when it's dropped
+pub struct TaskTrace {
+    task: usize,
+    events: Option<Vec<ItemEvent>>,
+}
+
+// Trace the items of the task identified by `task` (the index of its first item)
+pub fn task(task: usize) -> TaskTrace {
+    TaskTrace { task, events: RECORDING.load(Ordering::Relaxed).then(Vec::new) }
+}
+
+impl TaskTrace {
+    // Run `process` for item `item`, recording when and where if tracing is on
+    pub fn item<T>(&mut self, item: usize, process: impl FnOnce() -> T) -> T {
+        let Some(events) = &mut self.events else {
+            return process();
+        };
+        let start = Instant::now();
+        let output = process();
+        let end = Instant::now();
+        events.push(ItemEvent { thread: thread_id(), task: self.task, item, start, end });
+        output
+    }
+}
+
+impl Drop for TaskTrace {
+    fn drop(&mut self) {
+        if let Some(events) = self.events.take() {
+            EVENTS.lock().unwrap().extend(events);
+        }
+    }
+}
+
+// One entry of the Chrome Trace Event format; times are in microseconds
+#[derive(Serialize)]
+struct ChromeEvent<'a> {
+    name: String,
+    #[serde(skip_serializing_if = "str::is_empty")]
+    cat: &'a str,
+    ph: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ts: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dur: Option<f64>,
+    pid: usize,
+    tid: u64,
+    args: serde_json::Value,
+}
+
+// Everything traced, as a Chrome Trace Event file for Perfetto or chrome://tracing.
+// Each library is a process and each worker thread a track within it.
+pub fn write_chrome_trace(out: &mut dyn Write) -> io::Result<()> {
+    let epoch = *EPOCH.get_or_init(Instant::now);
+    let micros = |at: Instant| at.saturating_duration_since(epoch).as_nanos() as f64 / 1000.0;
+    let libraries = LIBRARIES.lock().unwrap();
+    let thread_names = THREAD_NAMES.lock().unwrap();
+
+    let mut events = Vec::new();
+    for (pid, (library, items)) in libraries.iter().enumerate() {
+        events.push(ChromeEvent {
+            name: "process_name".to_string(),
+            cat: "",
+            ph: "M",
+            ts: None,
+            dur: None,
+            pid,
+            tid: 0,
+            args: json!({ "name": library }),
+        });
+        let mut threads: Vec<u64> = items.iter().map(|item| item.thread).collect();
+        threads.sort_unstable();
+        threads.dedup();
+        for (tid, name) in thread_names.iter().filter(|(tid, _)| threads.binary_search(tid).is_ok()) {
+            events.push(ChromeEvent {
+                name: "thread_name".to_string(),
+                cat: "",
+                ph: "M",
+                ts: None,
+                dur: None,
+                pid,
+                tid: *tid,
+                args: json!({ "name": format!("{} #{}", name, tid) }),
+            });
+        }
+        for item in items {
+            events.push(ChromeEvent {
+                name: format!("item {}", item.item),
+                cat: library,
+                ph: "X",
+                ts: Some(micros(item.start)),
+                dur: Some(micros(item.end) - micros(item.start)),
+                pid,
+                tid: item.thread,
+                args: json!({ "task": item.task, "item": item.item }),
+            });
+        }
+    }
+
+    serde_json::to_writer(&mut *out, &json!({ "traceEvents": events, "displayTimeUnit": "ns" }))?;
+    writeln!(out)
+}
@@ -1,3 +1,4 @@
+use clap::Parser;
 use futures::future::join_all;
 use rand::{thread_rng, Rng};
 use std::sync::{Arc, Mutex};
@@ -13,10 +14,24 @@ fn process_value(value: u32) -> u32 {
     result
 }
 
+#[derive(Parser)]
+#[command(about = "Compare async runtimes and thread pools on a CPU-bound workload")]
+struct Args {
+    /// Number of values processed per iteration
+    #[arg(long, default_value_t = 10000)]
+    size: usize,
+
+    /// Iterations per runtime
+    #[arg(long, default_value_t = 5)]
+    iterations: usize,
+}
+
 fn main() {
-    const ITERATIONS: usize = 5;
-    println!("Starting benchmark with 10,000 values...");
-    println!("Running {} iterations for each runtime and showing best result", ITERATIONS);
+    let args = Args::parse();
+    let data_size = args.size;
+    let iterations = args.iterations.max(1);
+    println!("Starting benchmark with {} values...", data_size);
+    println!("Running {} iterations for each runtime and showing best result", iterations);
     
     // Track best times for each framework
     let mut actix_best = Duration::from_secs(u64::MAX);
@@ -28,20 +43,20 @@ fn main() {
     let mut crossbeam_best = Duration::from_secs(u64::MAX);
     
     // Track all times for calculating averages
-    let mut actix_durations = Vec::with_capacity(ITERATIONS);
-    let mut tokio_durations = Vec::with_capacity(ITERATIONS);
-    let mut async_std_durations = Vec::with_capacity(ITERATIONS);
-    let mut smol_durations = Vec::with_capacity(ITERATIONS);
-    let mut rayon_durations = Vec::with_capacity(ITERATIONS);
-    let mut std_thread_durations = Vec::with_capacity(ITERATIONS);
-    let mut crossbeam_durations = Vec::with_capacity(ITERATIONS);
+    let mut actix_durations = Vec::with_capacity(iterations);
+    let mut tokio_durations = Vec::with_capacity(iterations);
+    let mut async_std_durations = Vec::with_capacity(iterations);
+    let mut smol_durations = Vec::with_capacity(iterations);
+    let mut rayon_durations = Vec::with_capacity(iterations);
+    let mut std_thread_durations = Vec::with_capacity(iterations);
+    let mut crossbeam_durations = Vec::with_capacity(iterations);
     
-    for i in 0..ITERATIONS {
-        println!("\n--- Iteration {} of {} ---", i + 1, ITERATIONS);
+    for i in 0..iterations {
+        println!("\n--- Iteration {} of {} ---", i + 1, iterations);
         
         // Generate random data
         let mut rng = thread_rng();
-        let data: Vec<u32> = (0..10000).map(|_| rng.gen_range(0..10000)).collect();
+        let data: Vec<u32> = (0..data_size).map(|_| rng.gen_range(0..10000)).collect();
         let data_arc = Arc::new(data);
 
         // 1. Benchmark with Actix runtime
@@ -256,7 +271,7 @@ fn main() {
         
             // Print summary
             println!("\n=== BENCHMARK RESULTS ===");
-            println!("CPU-bound task processing 10,000 values with {} iterations", ITERATIONS);
+            println!("CPU-bound task processing {} values with {} iterations", data_size, iterations);
             println!("\nBest times for each framework:");
             println!("--------------------------------");
             println!("Actix:       {:?}", actix_best);